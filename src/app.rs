@@ -1,260 +1,5033 @@
 use std::{
     cell::{Ref, RefCell, RefMut},
-    cmp::min,
-    path::PathBuf,
-    sync::mpsc::{self, Receiver, Sender},
+    cmp::{max, min},
+    collections::HashMap,
+    env,
+    ffi::OsString,
+    fs::{self, File, OpenOptions},
+    io::{self, BufWriter, IsTerminal, Write, stdin},
+    num::NonZeroUsize,
+    ops::Range,
+    path::{Path, PathBuf},
+    process,
+    str::FromStr,
+    sync::{
+        Arc, Mutex, MutexGuard, PoisonError,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    },
     thread::{self},
+    time::{Duration, Instant},
 };
 
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use color_eyre::eyre::eyre;
-use crossterm::event::{KeyEvent, KeyEventKind};
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind};
 use keys::{Action, KeyState};
 use ratatui::{
-    DefaultTerminal,
+    DefaultTerminal, Frame,
     buffer::Buffer,
-    layout::{Rect, Size},
+    layout::{Constraint, Layout, Position, Rect, Size},
     style::Stylize,
-    widgets::{Paragraph, Widget},
+    text::{Line, Span},
+    widgets::{Block, Clear, Padding, Paragraph, Widget},
 };
+use signal_hook::{
+    consts::{SIGHUP, SIGINT, SIGTERM},
+    iterator::Signals,
+};
+use unicode_width::UnicodeWidthStr;
 
 use crate::{
+    command::{Command, Setting},
+    control::{self, ControlCommand},
+    diff::DiffMode,
+    encoding::InputEncoding,
     error::*,
     event::Event,
-    input::{Input, OpenedInput},
+    filter::FilterView,
+    input::{Input, InputKind, OpenedInput, ReadOptions, RenderOptions, StartPosition},
     keys,
+    positions::Positions,
+    prompt::{Prompt, PromptResult},
+    search::{MatchCount, MatchCounter, Pattern, Search, SearchDirection},
+    syntax::Syntax,
+    theme::{Background, Theme},
+    utils::{
+        ChopMarks, ControlChars, FormFeed, PromptFormat, PromptSpec, TAB_WIDTH, VerticalTab,
+        WrapIndent, format_timestamp, mark_cut_edges, wrap_line,
+    },
 };
 
+/// Rows at the bottom of the screen reserved for the status bar.
+const STATUS_BAR_HEIGHT: u16 = 1;
+
+/// Lines a screen shares with the next when `f` and `b` scroll, unless
+/// `--overlap` says otherwise.
+const DEFAULT_OVERLAP: usize = 1;
+
+/// Lines wrapped at a time to count the rows of a whole input, so that
+/// drawing the screen never waits on wrapping all of a long one.
+const ROW_COUNT_CHUNK: usize = 10_000;
+
 /// least: a minimal pager to replace `less`
 #[derive(Default, Parser, Debug)]
 #[clap(
     name = "least",
     version = "0.1.0",
     author = "ChenRuiwei",
-    about = "A lightweight pager as a simpler alternative to `less`"
+    about = "A lightweight pager as a simpler alternative to `less`",
+    after_help = "Default options can be read from the LEAST_OPTS environment variable; \
+                  options given on the command line override them.",
+    args_override_self = true
 )]
 pub struct Cli {
+    /// Files to page; `@LIST` reads further file names from LIST, one per
+    /// line (prefix a file actually named with an `@` with `./`). `+N` starts
+    /// at line N and `+/PATTERN` at the first match of PATTERN
     #[arg(value_name = "FILE", value_hint = clap::ValueHint::FilePath)]
     pub files: Vec<PathBuf>,
+
+    /// When to interpret styling sequences such as overstrike in the input
+    #[arg(long, visible_alias = "style", value_enum, default_value_t = ColorMode::Auto)]
+    pub color: ColorMode,
+
+    /// Screen row a search match is shown on: `top`, `center`, or the number
+    /// of lines of context to keep above it
+    #[arg(short = 'j', long, value_name = "TARGET", default_value = "3")]
+    pub jump_target: JumpTarget,
+
+    /// Start reading a file at the first line beginning at or after this byte
+    /// offset; earlier lines are not loaded
+    #[arg(long, value_name = "BYTES", conflicts_with = "seek_percent")]
+    pub seek: Option<u64>,
+
+    /// Like --seek, with the offset given as a percentage of the file size
+    #[arg(long, value_name = "PERCENT", value_parser = clap::value_parser!(u8).range(0..=100))]
+    pub seek_percent: Option<u8>,
+
+    /// Open binary files without asking first
+    #[arg(short, long)]
+    pub force: bool,
+
+    /// Milliseconds to batch read lines for before showing them. Lower values
+    /// show lines of a slow stream sooner, higher values make loading large
+    /// files cheaper. The first screen is always shown promptly
+    #[arg(long, value_name = "MS", default_value_t = 16)]
+    pub flush_ms: u64,
+
+    /// Decode the input from ENCODING, such as latin1, utf-16le or
+    /// shift_jis, instead of UTF-8. `auto` tells UTF-16 and Latin-1 from
+    /// UTF-8 by a byte order mark or the first bytes. UTF-16 files are read
+    /// from the start, without `--seek` or their lines counted ahead
+    #[arg(long, value_name = "ENCODING")]
+    pub encoding: Option<InputEncoding>,
+
+    /// Keep the carriage return of CRLF line endings instead of stripping it
+    #[arg(long)]
+    pub keep_cr: bool,
+
+    /// Split the input into records at NUL bytes instead of lines, as written
+    /// by `find -print0` and the like. Newlines inside a record show as `↵`
+    #[arg(short = '0', long)]
+    pub null: bool,
+
+    /// Keep reading files as they grow, and reopen them by name when they
+    /// are rotated or truncated, like `tail -F`. Files switched away from
+    /// are kept open and read on, and `]` and `[` go to those with new lines
+    #[arg(long)]
+    pub follow_name: bool,
+
+    /// Stop following once the file shown has been read to its end, to
+    /// scroll about what was written without more arriving. Files that
+    /// haven't stopped growing are otherwise polled for more lines for as
+    /// long as they are followed
+    #[arg(long, requires = "follow_name")]
+    pub exit_follow_at_eof: bool,
+
+    /// Show the start of a line as soon as it is written instead of once its
+    /// newline is, e.g. for a prompt printed by a slow producer, updating it
+    /// as the rest arrives
+    #[arg(long)]
+    pub show_partial: bool,
+
+    /// Quit once the last file has been read to its end with that end on
+    /// screen, e.g. when a pipeline feeding stdin is done. Scrolling up keeps
+    /// the pager open to read on
+    #[arg(short = 'e', long)]
+    pub quit_at_eof: bool,
+
+    /// Keep only the last N lines read, dropping older ones, so that an
+    /// endless stream doesn't use up memory. Lines are still numbered from
+    /// the start of the input
+    #[arg(long, value_name = "N")]
+    pub max_lines: Option<NonZeroUsize>,
+
+    /// Also show the byte offset of the top line in the status bar, out of the
+    /// file size when it is known
+    #[arg(long)]
+    pub verbose_status: bool,
+
+    /// Lay the status bar out as FORMAT, with %f for the input's name, %l
+    /// for the top line, %L for the number of lines, %p for the percent
+    /// shown, %b for the top line's byte offset, %B for the size and %% for a %
+    #[arg(short = 'P', long, value_name = "FORMAT")]
+    pub prompt: Option<PromptFormat>,
+
+    /// Log more of what the pager does, for a bug report: `-v` for debug
+    /// messages and `-vv` for everything
+    #[arg(short = 'v', long, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Write the log to PATH instead of the data directory
+    #[arg(long, value_name = "PATH")]
+    pub log_file: Option<PathBuf>,
+
+    /// Draw on the normal screen instead of the alternate one, so the last
+    /// page stays on the terminal after quitting. Scrolling redraws the page
+    /// in place, so earlier pages don't end up in the terminal's scrollback
+    #[arg(short = 'X', long)]
+    pub no_init: bool,
+
+    /// Leave the terminal's cursor keys and keypad in the mode they are in,
+    /// for terminals that send keys the pager can't read in application mode
+    #[arg(long)]
+    pub no_keypad: bool,
+
+    /// Let repeated searches wrap around to the other end of the input when
+    /// they run out of matches
+    #[arg(long)]
+    pub search_wrap: bool,
+
+    /// Let repeated searches that run out of matches go on into the next
+    /// file, or the previous one searching backward, from its first line or
+    /// last, instead of wrapping around within the file
+    #[arg(long)]
+    pub search_across_files: bool,
+
+    /// Also take commands from this open file descriptor, one JSON object per
+    /// line such as {"command": "go_to_line", "line": 42}
+    #[arg(long, value_name = "FD")]
+    pub control_fd: Option<i32>,
+
+    /// Cut lines off after this many columns, marking the cut with an
+    /// ellipsis. Columns are counted from the horizontal scroll position
+    #[arg(long, value_name = "N")]
+    pub max_columns: Option<usize>,
+
+    /// Don't reopen files where they were left last time, nor remember where
+    /// they are left this time
+    #[arg(long)]
+    pub no_remember: bool,
+
+    /// Show tabs as a dim arrow before the blanks up to the next tab stop
+    #[arg(long)]
+    pub show_tabs: bool,
+
+    /// Show the spaces ending a line as dim dots and every tab as an arrow,
+    /// as --show-tabs does; `W` toggles this while paging
+    #[arg(long)]
+    pub show_whitespace: bool,
+
+    /// Show form feeds, the page breaks of text meant for printers, as a
+    /// dim rule, in caret notation as ^L, or as a blank
+    #[arg(long, value_enum, default_value_t = FormFeed::Rule)]
+    pub form_feed: FormFeed,
+
+    /// Show vertical tabs in caret notation as ^K, or as a blank
+    #[arg(long, value_enum, default_value_t = VerticalTab::Caret)]
+    pub vertical_tab: VerticalTab,
+
+    /// Show the other control characters, such as NUL and BEL, in caret
+    /// notation as ^@ and ^G, as their Unicode pictures ␀ and ␇, or in hex
+    /// as <00> and <07>
+    #[arg(long, value_enum, default_value_t = ControlChars::Caret)]
+    pub control_chars: ControlChars,
+
+    /// Start at the first line matching PATTERN, waiting for one to be read
+    /// if needed; the same as +/PATTERN
+    #[arg(short, long, value_name = "PATTERN")]
+    pub pattern: Option<String>,
+
+    /// Highlight the syntax of files in a language known by their extension
+    /// or name
+    #[arg(long)]
+    pub syntax: bool,
+
+    /// Highlight every input as LANG, given by name or extension, e.g. for
+    /// stdin; implies --syntax
+    #[arg(long, value_name = "LANG")]
+    pub syntax_lang: Option<String>,
+
+    /// Colors to highlight syntax with, by default base16-ocean.dark or
+    /// base16-ocean.light to go with --theme
+    #[arg(long, value_name = "THEME")]
+    pub syntax_theme: Option<String>,
+
+    /// Colors for a dark or a light terminal background, taken from
+    /// COLORFGBG when the terminal sets it and dark otherwise; `T` switches
+    /// between them while paging
+    #[arg(long, value_enum, value_name = "BACKGROUND")]
+    pub theme: Option<Background>,
+
+    /// Color the added and removed lines and the headers of unified diffs;
+    /// `auto` only colors inputs whose first lines look like one
+    #[arg(
+        long,
+        value_enum,
+        value_name = "WHEN",
+        num_args = 0..=1,
+        require_equals = true,
+        default_value_t = DiffMode::Never,
+        default_missing_value = "always"
+    )]
+    pub diff: DiffMode,
+
+    /// Have searches match whole words only, so `err` doesn't find `error`;
+    /// `:set nowholeword` turns it off again
+    #[arg(long)]
+    pub whole_word: bool,
+
+    /// Have searches and filters match letters whatever their case, so `err`
+    /// also finds `ERR`
+    #[arg(short = 'i', long, overrides_with = "no_ignore_case")]
+    pub ignore_case: bool,
+
+    /// Match letters in the case they are given in, which is the default
+    #[arg(long)]
+    pub no_ignore_case: bool,
+
+    /// Columns between tab stops, eight unless given
+    #[arg(long, value_name = "N", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    pub tabs: Option<usize>,
+
+    /// Show each run of blank lines as a single one, like `cat -s`;
+    /// `:set nosqueeze` shows them all again
+    #[arg(short = 's', long, visible_alias = "squeeze")]
+    pub squeeze_blank_lines: bool,
+
+    /// Ask before `q` quits; `Q` always quits at once
+    #[arg(
+        long,
+        value_name = "WHEN",
+        value_enum,
+        num_args = 0..=1,
+        require_equals = true,
+        default_value_t = ConfirmQuit::Never,
+        default_missing_value = "always"
+    )]
+    pub confirm_quit: ConfirmQuit,
+
+    /// Write the input to stdout the way it would be shown, with tabs expanded
+    /// and overstrike styling removed, instead of paging it
+    #[arg(long)]
+    pub dump: bool,
+
+    /// Highlight a cursor line that j and k move around the screen, scrolling
+    /// only once it reaches the top or bottom
+    #[arg(long)]
+    pub cursor: bool,
+
+    /// Keep this many lines between the cursor and the top or bottom of the
+    /// screen, scrolling early to do so
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub scrolloff: usize,
+
+    /// Scroll by N lines with `f` and `b`, rather than by a screen less the
+    /// `--overlap`
+    #[arg(long, value_name = "N", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    pub window: Option<usize>,
+
+    /// Keep N lines of a screen in view when `f` and `b` scroll to the next,
+    /// one unless given
+    #[arg(long, value_name = "N")]
+    pub overlap: Option<usize>,
+
+    /// Indent the rows a wrapped line continues on by N columns, or as far as
+    /// the line itself is indented when no N is given
+    #[arg(
+        long,
+        value_name = "N",
+        num_args = 0..=1,
+        require_equals = true,
+        default_value = "0",
+        default_missing_value = "keep"
+    )]
+    pub wrap_indent: WrapIndent,
+
+    /// Start out wrapping long lines, rather than cutting them off at the
+    /// edge of the screen; `w` toggles this while paging
+    #[arg(long, overrides_with = "chop")]
+    pub wrap: bool,
+
+    /// Start out cutting long lines off at the edge of the screen, which is
+    /// the default
+    #[arg(short = 'S', long)]
+    pub chop: bool,
+
+    /// Mark lines cut off at the edges of the screen, with the first of the
+    /// two characters in the first column once scrolled sideways past some
+    /// of a line, and the second in the last column where a line goes on
+    #[arg(
+        long,
+        value_name = "LEFT_RIGHT",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "<>"
+    )]
+    pub chop_marks: Option<ChopMarks>,
+
+    /// Draw a bar filling up with how far into the input the screen is, at
+    /// the top of the screen unless `bottom` is given
+    #[arg(
+        long,
+        value_enum,
+        value_name = "WHERE",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "top"
+    )]
+    pub position_bar: Option<BarPosition>,
+
+    /// Number the lines in a column to their left, by their distance from
+    /// the cursor line when `relative` is given; `#` switches between the
+    /// two while paging
+    #[arg(
+        short = 'N',
+        long,
+        value_enum,
+        value_name = "HOW",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "absolute"
+    )]
+    pub line_numbers: Option<LineNumbers>,
+
+    /// Wrap lines at N columns rather than at the edge of the terminal, when
+    /// it is wider, so that they break the same whatever its size
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u16).range(1..))]
+    pub wrap_width: Option<u16>,
+
+    /// Trust the terminal with the escape sequences of the input, like
+    /// `less -r`. The screen is drawn cell by cell, so they can't be handed
+    /// on as they are; instead colors and other styling are shown whatever
+    /// --color says, and sequences that would move the cursor or the like are
+    /// left out. --dump writes them all unchanged
+    #[arg(short = 'r', long)]
+    pub raw_control_chars: bool,
+
+    /// Save the buffer with its overstrike and escape sequences instead of
+    /// as the plain text it shows
+    #[arg(long)]
+    pub save_raw: bool,
+
+    /// Name to show for stdin in the status bar, e.g. what the piped command
+    /// is doing
+    #[arg(long, value_name = "NAME")]
+    pub label: Option<String>,
+
+    /// Keep the first N lines at the top of the screen while the rest scroll
+    /// beneath them, e.g. the column names of a table. They scroll sideways
+    /// along with the rest, but are cut off rather than wrapped
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub header: usize,
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ConfirmQuit {
+    #[default]
+    Never,
+    Always,
+    /// Only when paging stdin, which can't be read again
+    Stdin,
+}
+
+/// Which edge of the screen `--position-bar` is drawn at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum BarPosition {
+    Top,
+    /// Right above the status bar
+    Bottom,
+}
+
+/// How `--line-numbers` numbers the lines on the screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LineNumbers {
+    /// Each line's number in the input
+    Absolute,
+    /// How many lines each line is from the cursor line, or from the top line
+    /// without `--cursor`, which has its number in the input, like vim's
+    /// `relativenumber`
+    Relative,
+}
+
+/// Where to start, as given by a `+` argument or `--pattern`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum StartCommand {
+    /// A 1-based line number.
+    Line(usize),
+    Search(String),
+}
+
+impl FromStr for StartCommand {
+    type Err = color_eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.strip_prefix('/') {
+            Some(pattern) => Ok(StartCommand::Search(pattern.to_string())),
+            None => s
+                .parse()
+                .map(StartCommand::Line)
+                .map_err(|_| eyre!("invalid start command '+{s}': expected +N or +/PATTERN")),
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ColorMode {
+    /// Show styling sequences as plain text
+    Never,
+    /// Always interpret styling sequences
+    Always,
+    /// Interpret styling sequences when stdout is a terminal, unless
+    /// NO_COLOR is set or CLICOLOR is 0; CLICOLOR_FORCE turns them on anyway
+    #[default]
+    Auto,
+}
+
+impl ColorMode {
+    pub fn enabled(self) -> bool {
+        match self {
+            ColorMode::Never => false,
+            ColorMode::Always => true,
+            ColorMode::Auto => {
+                Self::auto_enabled(|name| env::var_os(name), io::stdout().is_terminal())
+            }
+        }
+    }
+
+    /// Resolves `auto` from the environment as read by `var`, following the
+    /// NO_COLOR and CLICOLOR conventions, with NO_COLOR winning over the
+    /// rest. Otherwise styling is for terminals only.
+    fn auto_enabled(var: impl Fn(&str) -> Option<OsString>, terminal: bool) -> bool {
+        if var("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+            return false;
+        }
+        if var("CLICOLOR_FORCE").is_some_and(|value| !value.is_empty() && value != "0") {
+            return true;
+        }
+        if var("CLICOLOR").is_some_and(|value| value == "0") {
+            return false;
+        }
+        terminal
+    }
+}
+
+/// What is on screen besides the text of its rows, taken before and after
+/// lines are read to tell whether to draw it again.
+#[derive(Debug, PartialEq)]
+struct ScreenState {
+    current_line: usize,
+    skipped_rows: usize,
+    rows: usize,
+    complete_rows: usize,
+    dropped_rows: usize,
+    /// Whether the input is known to end on screen, with `~` past its end.
+    end_shown: bool,
+    header_rows: usize,
+    gutter_width: usize,
+    progress: f64,
+    position: String,
+    message: Option<String>,
+}
+
+/// Scrolls the view down on its own, one line per `interval`.
+#[derive(Debug)]
+struct AutoScroll {
+    interval: Duration,
+    next_tick: Instant,
+}
+
+impl AutoScroll {
+    const DEFAULT_INTERVAL: Duration = Duration::from_millis(500);
+    const MIN_INTERVAL: Duration = Duration::from_millis(10);
+    const MAX_INTERVAL: Duration = Duration::from_secs(10);
+
+    fn new() -> Self {
+        let interval = Self::DEFAULT_INTERVAL;
+        Self {
+            interval,
+            next_tick: Instant::now() + interval,
+        }
+    }
+
+    fn set_interval(&mut self, interval: Duration) {
+        self.interval = interval.clamp(Self::MIN_INTERVAL, Self::MAX_INTERVAL);
+        self.next_tick = Instant::now() + self.interval;
+    }
+}
+
+/// Where on the screen a line jumped to by a search lands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JumpTarget {
+    Top,
+    Center,
+    /// Keep this many lines above the target.
+    Context(usize),
+}
+
+impl Default for JumpTarget {
+    fn default() -> Self {
+        JumpTarget::Context(3)
+    }
+}
+
+impl FromStr for JumpTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "top" => Ok(JumpTarget::Top),
+            "center" | "centre" => Ok(JumpTarget::Center),
+            _ => s
+                .parse()
+                .map(JumpTarget::Context)
+                .map_err(|_| format!("expected `top`, `center` or a number of lines, got `{s}`")),
+        }
+    }
+}
+
+/// Lets the terminal thread be stopped from reading keys while another
+/// program, like the editor `v` runs, has the terminal.
+#[derive(Debug, Default)]
+struct TermReader {
+    paused: AtomicBool,
+    /// Held by the terminal thread while it reads, and by whoever pauses it
+    /// while it is paused.
+    reading: Mutex<()>,
+}
+
+impl TermReader {
+    /// How long the terminal thread waits for input before checking whether
+    /// it has been paused.
+    const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+    /// Stops the terminal thread from reading until the guard is dropped,
+    /// waiting for a read in progress to finish.
+    fn pause(&self) -> TermPause<'_> {
+        self.paused.store(true, Ordering::Relaxed);
+        let guard = self.reading.lock().unwrap_or_else(PoisonError::into_inner);
+        TermPause {
+            reader: self,
+            _guard: guard,
+        }
+    }
+}
+
+struct TermPause<'a> {
+    reader: &'a TermReader,
+    _guard: MutexGuard<'a, ()>,
+}
+
+impl Drop for TermPause<'_> {
+    fn drop(&mut self) {
+        self.reader.paused.store(false, Ordering::Relaxed);
+    }
+}
+
+/// An input switched away from with `--follow-name`, kept open so that its
+/// reader goes on reading, and shown again as it was left.
+#[derive(Debug)]
+struct ParkedInput {
+    opened: OpenedInput,
+    current_line: usize,
+    /// Lines have been read since it was left.
+    unread: bool,
 }
 
 /// The main application which holds the state and logic of the application.
 #[derive(Debug, Default)]
 pub struct App {
     cli: Cli,
+    /// Every input, in the order they are paged in.
+    inputs: Vec<Input>,
+    /// Index in `inputs` of the one being paged.
+    current_input: usize,
+    /// Inputs switched away from while following, still being read.
+    parked: HashMap<Input, ParkedInput>,
+    render_options: RenderOptions,
     mode: AppMode,
     opened_input: Option<RefCell<OpenedInput>>,
     current_line: usize,
+    /// Rows of the top line scrolled off the screen, as `j` and `k` scroll
+    /// through wrapped lines a row at a time. Other actions drop them, and as
+    /// they are kept along with the line they are of, they also no longer
+    /// count once anything else moves `current_line`.
+    skipped_rows: Option<(usize, usize)>,
     key_state: KeyState,
     term_size: Size,
     rx: Option<Receiver<Event>>,
+    /// Where events are sent, for threads started once the pager is running.
+    tx: Option<Sender<Event>>,
+    prompt: Prompt,
+    search_direction: SearchDirection,
+    search: Option<Search>,
+    /// Line of the last search hit, where a repeated search continues from.
+    last_match: Option<usize>,
+    /// The last action `.` can repeat.
+    last_action: Option<Action>,
+    /// Matches of the last search counted so far.
+    match_count: MatchCount,
+    /// Counts the matches of the last search, once there is one.
+    match_counter: Option<MatchCounter>,
+    /// A one-off notice shown in the status bar until the next key press.
+    message: Option<String>,
+    /// Clear the terminal before the next draw instead of relying on
+    /// ratatui's diffing.
+    force_clear: bool,
+    /// Whether the last events changed what is on screen, so it is drawn
+    /// again. Lines read below the screen leave it as it was.
+    needs_redraw: bool,
+    /// Fold long lines onto the following rows instead of cutting them off.
+    wrap: bool,
+    /// How the lines are numbered, when they are.
+    line_numbers: Option<LineNumbers>,
+    /// Canonical path of the file whose position is remembered on quit.
+    remembered: Option<PathBuf>,
+    /// The remembered line to go to once enough of the input is read.
+    restore_line: Option<usize>,
+    /// How many lines before the end to stay at while more input is read,
+    /// after `gN-`.
+    lines_from_end: Option<usize>,
+    /// Whether to stay on the last screen as more input is read, after `G`
+    /// while the input was still coming in.
+    stick_to_tail: bool,
+    /// Lines `j` was pressed for at the end of an input still being read,
+    /// scrolled as soon as the lines are.
+    owed_scrolls: usize,
+    start_command: Option<StartCommand>,
+    /// Screen row of the cursor in `--cursor` mode, counted in lines from the
+    /// top line.
+    cursor: usize,
+    /// Whether the input is stdin, which is gone once the pager quits.
+    paging_stdin: bool,
+    /// The match of the last search, counting from 1, to go to once it has
+    /// been counted: the first for a starting pattern, or that of `:match`.
+    awaited_match: Option<usize>,
+    /// The direction of a search gone on into the input now shown, for
+    /// `--search-across-files`. Its first match, or its last searching
+    /// backward, is gone to once counted.
+    searched_into: Option<SearchDirection>,
+    auto_scroll: Option<AutoScroll>,
+    /// Lines `d` and `u` scroll by once a count has been given to either.
+    scroll_amount: Option<usize>,
+    /// The signal that ended the session, if any.
+    exit_signal: Option<i32>,
+    /// The file and line `v` asked to edit, for the main loop to hand the
+    /// terminal over to the editor.
+    pending_edit: Option<(PathBuf, usize)>,
+    term_reader: Arc<TermReader>,
+    /// Lines of context around matches for the filter being entered.
+    filter_context: usize,
+    /// How inputs are highlighted, with `--syntax` or `--syntax-lang`.
+    syntax: Option<Syntax>,
+    /// The colors of the text, status bar, cursor line and position bar.
+    theme: Theme,
 }
 
 impl App {
-    pub fn new(cli: Cli) -> Self {
-        Self {
+    /// Sets up the application, resolving the inputs to page so that bad
+    /// arguments are reported before the terminal is taken over.
+    pub fn new(cli: Cli) -> Result<Self> {
+        let render_options = RenderOptions {
+            styled: cli.color.enabled() || cli.raw_control_chars,
+            column_offset: 0,
+            max_columns: cli.max_columns,
+            show_tabs: cli.show_tabs,
+            show_whitespace: cli.show_whitespace,
+            form_feed: cli.form_feed,
+            vertical_tab: cli.vertical_tab,
+            control_chars: cli.control_chars,
+            wrap_indent: cli.wrap_indent,
+            tab_width: cli.tabs.unwrap_or(TAB_WIDTH),
+        };
+        let theme = Theme::new(cli.theme.unwrap_or_else(Background::detect));
+        let syntax_theme = cli
+            .syntax_theme
+            .as_deref()
+            .unwrap_or(theme.background.syntax_theme());
+        let syntax = (cli.syntax || cli.syntax_lang.is_some())
+            .then(|| Syntax::new(cli.syntax_lang.as_deref(), syntax_theme))
+            .transpose()?;
+        let mut app = Self {
+            wrap: cli.wrap,
+            line_numbers: cli.line_numbers,
+            syntax,
+            theme,
             cli,
+            render_options,
             ..Default::default()
+        };
+        let inputs = app.inputs()?;
+        app.inputs = app.pageable(inputs)?;
+        app.start_command = app.start_command()?;
+        Ok(app)
+    }
+
+    fn read_options(&self) -> ReadOptions {
+        let start = match (self.cli.seek, self.cli.seek_percent) {
+            (Some(offset), _) => StartPosition::Byte(offset),
+            (_, Some(percent)) => StartPosition::Percent(percent),
+            _ => StartPosition::Beginning,
+        };
+        ReadOptions {
+            start,
+            flush_interval: Duration::from_millis(self.cli.flush_ms),
+            keep_cr: self.cli.keep_cr,
+            delimiter: if self.cli.null { 0 } else { b'\n' },
+            follow_name: self.cli.follow_name,
+            show_partial: self.cli.show_partial,
+            max_lines: self.cli.max_lines.map(NonZeroUsize::get),
+            encoding: self.cli.encoding,
+        }
+    }
+
+    /// The arguments starting with `+`, which are commands rather than files.
+    fn start_arguments(&self) -> impl Iterator<Item = &str> {
+        self.cli
+            .files
+            .iter()
+            .filter_map(|file| file.to_str()?.strip_prefix('+'))
+    }
+
+    /// The last `+` argument, unless `--pattern` is given.
+    fn start_command(&self) -> Result<Option<StartCommand>> {
+        if let Some(pattern) = &self.cli.pattern {
+            return Ok(Some(StartCommand::Search(pattern.clone())));
         }
+        self.start_arguments().map(str::parse).last().transpose()
     }
 
     fn inputs(&self) -> Result<Vec<Input>> {
-        if self.cli.files.is_empty() {
+        let files: Vec<_> = self
+            .cli
+            .files
+            .iter()
+            .filter(|file| !file.to_str().is_some_and(|file| file.starts_with('+')))
+            .collect();
+        if files.is_empty() {
             return Ok(vec![Input::stdin()]);
         }
         let mut file_input = Vec::new();
-        for file in &self.cli.files {
-            file_input.push(Input::ordinary_file(file));
+        for file in files {
+            match file.to_str().and_then(|file| file.strip_prefix('@')) {
+                Some(list) => file_input.extend(Self::read_file_list(list)?),
+                None => file_input.push(Input::ordinary_file(file)),
+            }
         }
         Ok(file_input)
     }
 
-    pub fn run(mut self, mut terminal: DefaultTerminal) -> Result<()> {
-        let mut inputs = self.inputs()?;
-        let input = inputs.pop().unwrap();
+    /// Leaves out the inputs that can't be paged, so that bad arguments are
+    /// reported up front rather than once they are got to. Each is warned
+    /// about on stderr, which is seen again on quitting, and the first also
+    /// on the status bar. Only when none are left is that an error.
+    fn pageable(&mut self, inputs: Vec<Input>) -> Result<Vec<Input>> {
+        let mut problems = Vec::new();
+        let inputs: Vec<_> = inputs
+            .into_iter()
+            .filter(|input| match input.check() {
+                Ok(()) => true,
+                Err(err) => {
+                    eprintln!("least: {err}");
+                    problems.push(err.to_string());
+                    false
+                }
+            })
+            .collect();
+        if inputs.is_empty() {
+            return Err(eyre!("no files to page: {}", problems.join(", ")));
+        }
+        self.message = match problems.len() {
+            0 => None,
+            1 => Some(problems.remove(0)),
+            skipped => Some(format!("{}, and {} more skipped", problems[0], skipped - 1)),
+        };
+        Ok(inputs)
+    }
+
+    /// Reads a list of files to page, one per line. Blank lines and lines
+    /// starting with `#` are skipped.
+    fn read_file_list(list: &str) -> Result<Vec<Input>> {
+        let content = fs::read_to_string(list)
+            .map_err(|e| eyre!("failed to read file list '{}': {}", list, e))?;
+        Ok(content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(Input::ordinary_file)
+            .collect())
+    }
+
+    /// Whether there is a terminal to read keys from. Keys come from stdin
+    /// when it is a terminal, and from `/dev/tty` when stdin is a pipe feeding
+    /// the content being paged: crossterm opens it itself for both raw mode
+    /// and reading events, so `cmd | least` pages like any file.
+    pub fn keyboard_available() -> bool {
+        stdin().is_terminal()
+            || OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open("/dev/tty")
+                .is_ok()
+    }
+
+    /// Copies every input to `out` as it is, for when there is no terminal to
+    /// page on.
+    pub fn copy_inputs(self, out: &mut impl Write) -> Result<()> {
+        for input in self.inputs {
+            input.copy_to(out)?;
+        }
+        Ok(())
+    }
+
+    /// Writes every input to `out` the way it would be paged, with tabs
+    /// expanded and overstrike styling dropped unless `--color never` keeps
+    /// it as text, or with `-r` just as it is. Lines are written as they are
+    /// read.
+    pub fn dump(self, out: &mut impl Write) -> Result<()> {
+        let read_options = self.read_options();
+        let render_options = RenderOptions {
+            styled: self.cli.color != ColorMode::Never,
+            ..self.render_options.clone()
+        };
+        for input in self.inputs {
+            let (tx, rx) = mpsc::channel();
+            let mut opened_input = input.open(tx, &read_options)?;
+            opened_input.set_squeeze(self.cli.squeeze_blank_lines);
+            let mut written = 0;
+            for event in rx {
+                let Event::Input(_, event) = event else {
+                    continue;
+                };
+                match *event {
+                    Event::NewLines(lines, offsets) => {
+                        // written before any of them can be dropped for
+                        // --max-lines
+                        opened_input.push_lines(lines, offsets);
+                        written = if self.cli.raw_control_chars {
+                            opened_input.write_raw_lines(written, out)?
+                        } else {
+                            opened_input.write_plain_lines(written, out, &render_options)?
+                        };
+                        opened_input.evict();
+                        written -= opened_input.take_evicted_rows();
+                    }
+                    Event::Eof => break,
+                    Event::ReaderThreadErrReturned => return opened_input.join_reader(),
+                    _ => {}
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs the pager until the user quits, returning the signal that ended it
+    /// early, if any.
+    pub fn run(mut self, mut terminal: DefaultTerminal) -> Result<Option<i32>> {
+        let Some(input) = self.inputs.first().cloned() else {
+            return Err(eyre!("no files to page"));
+        };
 
         let (tx, rx) = mpsc::channel::<Event>();
         self.rx = Some(rx);
+        self.tx = Some(tx.clone());
 
-        self.opened_input = Some(RefCell::new(input.open(tx.clone())?));
+        self.open_input(input)?;
+        // a position asked for on the command line wins over the remembered one
+        match self.start_command.clone() {
+            Some(StartCommand::Line(number)) => self.restore_line = Some(number.saturating_sub(1)),
+            Some(StartCommand::Search(pattern)) => {
+                self.restore_line = None;
+                self.search = Some(
+                    Search::new(pattern, SearchDirection::Forward)
+                        .whole_word(self.cli.whole_word)
+                        .ignore_case(self.cli.ignore_case),
+                );
+                self.awaited_match = Some(1);
+            }
+            None => {}
+        }
 
-        Self::spawn_term_thread(tx.clone());
+        Self::spawn_term_thread(tx.clone(), self.term_reader.clone());
+        Self::spawn_signal_thread(tx.clone())?;
+        if let Some(fd) = self.cli.control_fd {
+            control::spawn_control_thread(fd, tx.clone())?;
+        }
         self.term_size = terminal.size()?;
+        self.needs_redraw = true;
+
+        let paged = self.page(&mut terminal);
+        // a command piped to would otherwise run on until its next check,
+        // maybe past the pager itself
+        self.close_inputs();
+        paged?;
+        self.remember_position();
+        Ok(self.exit_signal)
+    }
 
+    /// Draws and handles events until the pager quits.
+    fn page(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
         while self.mode != AppMode::Terminated {
-            terminal.draw(|frame| frame.render_widget(&mut self, frame.area()))?;
+            if self.force_clear {
+                terminal.clear()?;
+                self.force_clear = false;
+                self.needs_redraw = true;
+            }
+            if std::mem::take(&mut self.needs_redraw) {
+                terminal.draw(|frame| self.draw(frame))?;
+            }
             self.handle_events()?;
+            if let Some((path, line)) = self.pending_edit.take() {
+                self.run_editor(terminal, &path, line)?;
+                self.needs_redraw = true;
+            }
         }
-
         Ok(())
     }
 
-    fn spawn_term_thread(tx: Sender<Event>) {
-        thread::spawn(move || {
-            loop {
-                match crossterm::event::read() {
-                    Ok(event) => tx.send(Event::Term(event)).unwrap(),
-                    Err(err) => tx.send(Event::Err(err.into())).unwrap(),
-                };
-            }
-        });
+    /// Closes the input shown and those parked, see [`OpenedInput::close`].
+    fn close_inputs(&self) {
+        if let Some(opened_input) = &self.opened_input {
+            opened_input.borrow().close();
+        }
+        for parked in self.parked.values() {
+            parked.opened.close();
+        }
     }
 
-    fn handle_events(&mut self) -> Result<()> {
-        match self.rx.as_ref().unwrap().recv().unwrap() {
-            Event::Term(event) => {
-                self.handle_crossterm_events(event)?;
+    /// Starts paging `input` from its top, or from where it was left last
+    /// time. Whatever was open before is closed, unless it is kept open to
+    /// follow it.
+    fn open_input(&mut self, input: Input) -> Result<()> {
+        let read_options = self.read_options();
+        let path = input.path().map(Path::to_path_buf);
+        let stdin = input.kind == InputKind::StdIn;
+        let (opened_input, parked_line) = match self.parked.remove(&input) {
+            Some(parked) => (parked.opened, Some(parked.current_line)),
+            None => {
+                let mut opened = input.open(self.tx.clone().unwrap(), &read_options)?;
+                opened.set_highlighter(
+                    self.syntax
+                        .and_then(|syntax| syntax.highlighter(path.as_deref())),
+                );
+                opened.set_diff(self.cli.diff);
+                opened.set_squeeze(self.cli.squeeze_blank_lines);
+                (opened, None)
             }
-            e @ (Event::NewLines(_) | Event::EOF) => self.opened_input_mut().handle_event(e)?,
-            Event::Err(error) => return Err(error),
-            Event::NewLines(items) => todo!(),
-            Event::EOF => todo!(),
-            Event::ReaderThreadErrReturned => {
-                let reader_thread = self.opened_input.take().unwrap().into_inner().reader;
-                if reader_thread.is_finished() {
-                    let res = reader_thread.join().unwrap();
-                    match res {
-                        Ok(_) => unreachable!(),
-                        Err(err) => {
-                            return Err(eyre!("reader thread failed {}", err));
-                        }
-                    }
+        };
+        if let Some(old) = self.opened_input.replace(RefCell::new(opened_input)) {
+            let old = old.into_inner();
+            // the input being left is still the current one
+            match self.inputs.get(self.current_input) {
+                Some(left) if self.cli.follow_name => {
+                    let parked = ParkedInput {
+                        opened: old,
+                        current_line: self.current_line,
+                        unread: false,
+                    };
+                    self.parked.insert(left.clone(), parked);
                 }
+                _ => old.close(),
             }
-        };
+        }
+
+        self.paging_stdin = stdin;
+        self.remembered = None;
+        if !self.cli.no_remember && read_options.start == StartPosition::Beginning {
+            self.remembered = path.and_then(|path| fs::canonicalize(path).ok());
+        }
+        self.restore_line = self
+            .remembered
+            .as_deref()
+            .and_then(|path| Positions::load().get(path))
+            .filter(|_| parked_line.is_none());
+        self.current_line = parked_line.unwrap_or(0);
+        self.cursor = 0;
+        self.render_options.column_offset = 0;
+        self.last_match = None;
+        self.match_count = MatchCount::default();
+        self.match_counter = None;
+        self.awaited_match = None;
+        self.searched_into = None;
+        self.lines_from_end = None;
+        self.stick_to_tail = false;
+        self.owed_scrolls = 0;
         Ok(())
     }
 
-    fn handle_crossterm_events(&mut self, event: crossterm::event::Event) -> Result<()> {
-        match event {
-            // it's important to check KeyEventKind::Press to avoid handling key release events
-            crossterm::event::Event::Key(key) if key.kind == KeyEventKind::Press => {
-                self.on_key_event(key)
+    /// Pages `input` in place of the current one, returning whether it could
+    /// be. Files that can't be read and stdin, which is gone once read, are
+    /// reported rather than opened, unless they are still open from before.
+    fn switch_to(&mut self, input: Input) -> bool {
+        if !self.parked.contains_key(&input) {
+            if input.kind == InputKind::StdIn {
+                self.message = Some("stdin can't be read again".to_string());
+                return false;
             }
-            crossterm::event::Event::Mouse(_) => {}
-            crossterm::event::Event::Resize(colomns, rows) => {
-                self.on_term_resize(Size::new(colomns, rows))
+            if let Err(err) = input.check() {
+                self.message = Some(err.to_string());
+                return false;
             }
-            _ => {}
+            let shown = input.name();
+            self.remember_position();
+            if let Err(err) = self.open_input(input) {
+                self.message = Some(format!("Cannot open {shown}: {err}"));
+                return false;
+            }
+            return true;
         }
-        Ok(())
+        self.remember_position();
+        // opening a parked input only takes it back
+        self.open_input(input).is_ok()
     }
 
-    fn on_key_event(&mut self, key: KeyEvent) {
-        let (key_state, action) = self.key_state.next(key);
-        self.key_state = key_state;
-        self.on_action(action);
+    /// Asks for the current file to be opened in `$VISUAL` or `$EDITOR` at
+    /// the top line, for `v`.
+    fn edit_current(&mut self) {
+        let path = self.inputs.get(self.current_input).and_then(Input::path);
+        let Some(path) = path.map(Path::to_path_buf) else {
+            self.message = Some(format!("{} isn't a file to edit", self.input_name()));
+            return;
+        };
+        let line = self.opened_input().line_number(self.current_line);
+        self.pending_edit = Some((path, line.unwrap_or(1)));
     }
 
-    fn on_action(&mut self, action: Action) {
-        match action {
-            Action::GoToMain => {}
-            Action::GoToTop => self.go_to_top(),
-            Action::GoToBottom => self.go_to_bottom(),
-            Action::GoToLine(line) => self.go_to_line(line),
-            Action::ScrollUpOneLine => self.scroll_up_one_line(),
-            Action::ScrollDownOneLine => self.scroll_down_one_line(),
-            Action::ScrollUpHalfScreen => self.scroll_up_half_screen(),
-            Action::ScrollDownHalfScreen => self.scroll_down_half_screen(),
-            Action::ScrollUpScreen => self.scroll_up_screen(),
-            Action::ScrollDownScreen => self.scroll_down_screen(),
-            Action::None => {}
-            Action::Quit => self.quit(),
+    /// Leaves the terminal to the editor until it exits, then reads the
+    /// file again, as it may have changed.
+    fn run_editor(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+        path: &Path,
+        line: usize,
+    ) -> Result<()> {
+        let (alternate_screen, keypad) = (!self.cli.no_init, !self.cli.no_keypad);
+        let mut editor = Self::editor_command(path, line);
+        // the editor reads the terminal even when stdin is something else
+        if !stdin().is_terminal() {
+            editor.stdin(File::open("/dev/tty")?);
         }
+        let status = {
+            let _paused = self.term_reader.pause();
+            crate::restore_terminal(alternate_screen, keypad)?;
+            let status = editor.status();
+            *terminal = crate::init_terminal(alternate_screen, keypad)?;
+            status
+        };
+        self.term_size = terminal.size()?;
+        self.message = match status {
+            Ok(status) if status.success() => None,
+            Ok(status) => Some(format!("The editor failed: {status}")),
+            Err(err) => Some(format!("Cannot run the editor: {err}")),
+        };
+        self.reload_edited(line)
     }
 
-    fn on_term_resize(&mut self, new_size: Size) {
-        self.term_size = new_size;
-        self.current_line = min(self.current_line, self.current_max_line());
+    /// Reads the current file anew after editing it, going back to `line`,
+    /// the 1-based number of the line that was at the top.
+    fn reload_edited(&mut self, line: usize) -> Result<()> {
+        // closed rather than parked, as it is replaced by what is read anew
+        if let Some(old) = self.opened_input.take() {
+            old.into_inner().close();
+        }
+        let input = self.inputs[self.current_input].clone();
+        self.parked.remove(&input);
+        self.open_input(input)?;
+        self.restore_line = Some(line.saturating_sub(1));
+        Ok(())
     }
 
-    fn term_half_height(&self) -> usize {
-        (self.term_size.height / 2) as _
+    /// `$VISUAL`, or `$EDITOR`, or vi, opening `path` at `line` as vi and
+    /// most editors take it. The editor is run by the shell, so it may come
+    /// with arguments of its own.
+    fn editor_command(path: &Path, line: usize) -> process::Command {
+        let editor = env::var("VISUAL")
+            .or_else(|_| env::var("EDITOR"))
+            .unwrap_or_else(|_| "vi".to_string());
+        let mut command = process::Command::new("sh");
+        command
+            .arg("-c")
+            .arg(format!("{editor} +{line} \"$1\""))
+            .arg("sh")
+            .arg(path);
+        command
     }
 
-    fn term_height(&self) -> usize {
-        self.term_size.height as _
+    /// Opens the file at `path` for `:e`.
+    fn edit_file(&mut self, path: &str) {
+        self.add_input(Input::ordinary_file(path));
     }
 
-    fn quit(&mut self) {
-        self.mode = AppMode::Terminated
+    /// Pages what `command` writes when fed the lines read so far, for `|`.
+    fn submit_pipe(&mut self, command: &str) {
+        if command.trim().is_empty() {
+            return;
+        }
+        let mut text = Vec::new();
+        // writing to memory doesn't fail
+        let _ = self
+            .opened_input()
+            .write_lines(&mut text, self.cli.save_raw);
+        self.add_input(Input::piped(command.to_string(), text));
     }
 
-    fn scroll_up_one_line(&mut self) {
-        self.current_line = self.current_line.saturating_sub(1)
+    /// Pages `input`, adding it to the inputs right after the one it was
+    /// opened from.
+    fn add_input(&mut self, input: Input) {
+        if self.switch_to(input.clone()) {
+            self.current_input = min(self.current_input + 1, self.inputs.len());
+            self.inputs.insert(self.current_input, input);
+        }
     }
 
-    fn scroll_down_one_line(&mut self) {
-        self.current_line = min(self.current_line.saturating_add(1), self.current_max_line())
+    /// Pages the input after the current one, or the one before it when
+    /// `back` is set, for `:n` and `:p`.
+    fn next_file(&mut self, back: bool) {
+        let index = if back {
+            self.current_input.checked_sub(1)
+        } else {
+            Some(self.current_input + 1).filter(|&index| index < self.inputs.len())
+        };
+        let Some(index) = index else {
+            let which = if back { "previous" } else { "next" };
+            self.message = Some(format!("No {which} file"));
+            return;
+        };
+        self.go_to_input(index);
     }
 
-    fn scroll_up_half_screen(&mut self) {
-        self.current_line = self.current_line.saturating_sub(self.term_half_height())
+    /// Pages the input at `index` in `inputs`, returning whether it could be.
+    fn go_to_input(&mut self, index: usize) -> bool {
+        let switched = self.switch_to(self.inputs[index].clone());
+        if switched {
+            self.current_input = index;
+        }
+        switched
     }
 
-    fn scroll_down_half_screen(&mut self) {
-        self.current_line = min(
-            self.current_line.saturating_add(self.term_half_height()),
-            self.current_max_line(),
-        )
+    /// Goes on with the last search in the input after the current one, or
+    /// the one before it searching backward, for `--search-across-files`.
+    /// Returns whether it went to one.
+    fn search_next_file(&mut self, direction: SearchDirection) -> bool {
+        let index = match direction {
+            SearchDirection::Forward => {
+                Some(self.current_input + 1).filter(|&index| index < self.inputs.len())
+            }
+            SearchDirection::Backward => self.current_input.checked_sub(1),
+        };
+        let Some(index) = index else {
+            return false;
+        };
+        let went = self.go_to_input(index);
+        if went {
+            self.searched_into = Some(direction);
+            self.message = Some(format!("Search went on into {}", self.input_name()));
+        }
+        went
     }
 
-    fn scroll_up_screen(&mut self) {
-        self.current_line = self.current_line.saturating_sub(self.term_height())
+    /// Pages the next input after the current one, or the one before it when
+    /// `back` is set, that has had lines read since it was left, going round
+    /// past the ends.
+    fn next_unread_file(&mut self, back: bool) {
+        let count = self.inputs.len();
+        let unread = |index: &usize| {
+            self.parked
+                .get(&self.inputs[*index])
+                .is_some_and(|parked| parked.unread)
+        };
+        let index = (1..count)
+            .map(|step| match back {
+                false => (self.current_input + step) % count,
+                true => (self.current_input + count - step) % count,
+            })
+            .find(unread);
+        match index {
+            Some(index) => {
+                self.go_to_input(index);
+            }
+            None => self.message = Some("No other file has new lines".to_string()),
+        }
     }
 
-    fn scroll_down_screen(&mut self) {
-        self.current_line = min(
-            self.current_line.saturating_add(self.term_height()),
-            self.current_max_line(),
-        )
+    /// Moves to the remembered line as far as the input read so far allows.
+    /// The file may have shrunk since, so once it is read completely the last
+    /// line that can be at the top has to do.
+    fn restore_position(&mut self) {
+        let Some(line) = self.restore_line else {
+            return;
+        };
+        self.current_line = min(line, self.current_max_line());
+        if self.current_line == line || self.opened_input().reached_eof() {
+            self.restore_line = None;
+        }
     }
 
-    fn opened_input(&self) -> Ref<OpenedInput> {
-        self.opened_input.as_ref().unwrap().borrow()
+    fn remember_position(&self) {
+        let (Some(path), Some(opened_input)) = (&self.remembered, &self.opened_input) else {
+            return;
+        };
+        let opened_input = opened_input.borrow();
+        // remember the line itself rather than its row in a filtered view
+        let line = (self.current_line < opened_input.current_total_lines())
+            .then(|| opened_input.line_index(self.current_line))
+            .flatten()
+            .unwrap_or(0);
+        if let Err(err) = Positions::remember(path, opened_input.evicted() + line) {
+            log::warn!("failed to remember position in {}: {err}", path.display());
+        }
     }
 
-    fn opened_input_mut(&self) -> RefMut<OpenedInput> {
-        self.opened_input.as_ref().unwrap().borrow_mut()
+    fn spawn_term_thread(tx: Sender<Event>, reader: Arc<TermReader>) {
+        thread::spawn(move || {
+            loop {
+                if reader.paused.load(Ordering::Relaxed) {
+                    thread::sleep(TermReader::POLL_INTERVAL);
+                    continue;
+                }
+                let _reading = reader
+                    .reading
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner);
+                let event = crossterm::event::poll(TermReader::POLL_INTERVAL)
+                    .and_then(|ready| ready.then(crossterm::event::read).transpose());
+                match event {
+                    Ok(Some(event)) => tx.send(Event::Term(event)).unwrap(),
+                    Ok(None) => {}
+                    // the terminal is gone, so reading again would only fail again
+                    Err(err) => {
+                        let _ = tx.send(Event::Err(err.into()));
+                        break;
+                    }
+                };
+            }
+        });
+    }
+
+    /// Turns termination signals into events, so the main loop ends as if the
+    /// user quit and the terminal is restored on the way out.
+    fn spawn_signal_thread(tx: Sender<Event>) -> Result<()> {
+        let mut signals = Signals::new([SIGTERM, SIGHUP, SIGINT])?;
+        thread::spawn(move || {
+            for signal in signals.forever() {
+                if tx.send(Event::Signal(signal)).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(())
+    }
+
+    /// Whether matches of the last search are left to count in what has
+    /// been read so far.
+    fn counting_matches(&self) -> bool {
+        self.search.is_some()
+            && self.opened_input.is_some()
+            && self.match_count.scanned() < self.opened_input().complete_rows()
+    }
+
+    /// Hands the match counter the next step of rows once it is done with
+    /// the last one, starting a counter for the last search if needed.
+    fn count_matches(&mut self) {
+        if !self.counting_matches() {
+            return;
+        }
+        let (Some(search), Some(tx)) = (&self.search, &self.tx) else {
+            return;
+        };
+        let counter = self
+            .match_counter
+            .get_or_insert_with(|| MatchCounter::spawn(search, tx.clone()));
+        if counter.busy() {
+            return;
+        }
+        let start = self.match_count.scanned();
+        let opened_input = self.opened_input.as_ref().unwrap().borrow();
+        let end = min(start + MatchCount::STEP, opened_input.complete_rows());
+        counter.send(start, opened_input.row_lines(start..end));
+    }
+
+    /// Goes to the awaited match as soon as it has been counted, or to the
+    /// last one once all are and there are too few.
+    fn jump_to_awaited_match(&mut self) {
+        let Some(ordinal) = self.awaited_match else {
+            return;
+        };
+        let row = match self.match_count.nth(ordinal) {
+            Some(row) => row,
+            None if self.counting_matches() || self.more_to_come() => return,
+            None => {
+                self.awaited_match = None;
+                let Some(last) = self.match_count.last() else {
+                    self.message = Some("Pattern not found".to_string());
+                    return;
+                };
+                let total = self.match_count.total();
+                self.message = Some(format!("Only {total} matches, went to the last"));
+                last
+            }
+        };
+        self.awaited_match = None;
+        self.show_match(row);
+    }
+
+    /// Goes to the match a search gone on into this input lands on, once it
+    /// has been counted, or on to the input after this one if it has none.
+    fn jump_to_match_searched_into(&mut self) {
+        let Some(direction) = self.searched_into else {
+            return;
+        };
+        let done = !self.counting_matches() && !self.more_to_come();
+        let row = match direction {
+            SearchDirection::Forward => self.match_count.nth(1),
+            SearchDirection::Backward => self.match_count.last().filter(|_| done),
+        };
+        let Some(row) = row else {
+            if done {
+                self.searched_into = None;
+                if !self.search_next_file(direction) {
+                    self.message = Some("Pattern not found in the files left".to_string());
+                }
+            }
+            return;
+        };
+        self.searched_into = None;
+        self.show_match(row);
+    }
+
+    /// The options switched on, one bracketed letter each, e.g. `[W][F]` for
+    /// wrapping and following.
+    fn indicators(&self) -> String {
+        let filtered = self
+            .opened_input
+            .as_ref()
+            .is_some_and(|input| input.borrow().filtered());
+        [
+            (self.wrap, 'W'),
+            (self.cli.follow_name, 'F'),
+            (self.cli.whole_word, 'w'),
+            (self.cli.cursor, 'C'),
+            (self.cli.squeeze_blank_lines, 's'),
+            (filtered, '&'),
+        ]
+        .into_iter()
+        .filter(|&(on, _)| on)
+        .map(|(_, letter)| format!("[{letter}]"))
+        .collect()
+    }
+
+    /// Where the last search hit is among all the matches, e.g. `match 3 of
+    /// 17`, with a `+` while more may turn up.
+    fn match_status(&self) -> Option<String> {
+        let ordinal = self.match_count.ordinal(self.last_match?)?;
+        let more = if self.counting_matches() || !self.opened_input().reached_eof() {
+            "+"
+        } else {
+            ""
+        };
+        Some(format!(
+            "match {ordinal} of {}{more}",
+            self.match_count.total()
+        ))
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        frame.render_widget(&mut *self, frame.area());
+        if let Some(position) = self.cursor_position(frame.area()) {
+            frame.set_cursor_position(position);
+        }
+    }
+
+    /// Waits for the next event and handles it.
+    fn handle_events(&mut self) -> Result<()> {
+        let rx = self.rx.as_ref().unwrap();
+        // while auto-scrolling, wake up in time for the next tick even when no
+        // event arrives
+        let event = match &self.auto_scroll {
+            // with rows left to count, draw again to count more unless there
+            // is something to do first
+            None if self.counting_rows() => match rx.try_recv() {
+                Ok(event) => event,
+                Err(_) => {
+                    self.needs_redraw = true;
+                    return Ok(());
+                }
+            },
+            None => rx.recv().unwrap(),
+            Some(auto_scroll) => {
+                let timeout = auto_scroll
+                    .next_tick
+                    .saturating_duration_since(Instant::now());
+                match rx.recv_timeout(timeout) {
+                    Ok(event) => event,
+                    Err(RecvTimeoutError::Timeout) => {
+                        self.on_auto_scroll_tick();
+                        self.settle();
+                        self.needs_redraw = true;
+                        return Ok(());
+                    }
+                    Err(err) => return Err(err.into()),
+                }
+            }
+        };
+        self.update(event)
+    }
+
+    /// Handles one event and what follows from it, short of drawing the
+    /// screen. The event loop runs on this, and tests feed it their events.
+    fn update(&mut self, event: Event) -> Result<()> {
+        let before = self
+            .lines_only(&event)
+            .then(|| self.screen_state())
+            .flatten();
+        self.handle_event(event)?;
+        self.settle();
+        self.needs_redraw |= match before {
+            Some(before) => self.screen_changed(&before),
+            None => true,
+        };
+        Ok(())
+    }
+
+    /// Whether `event` only brings lines or news of them, which leave the
+    /// screen as it was unless they land on it, see [`Self::screen_changed`].
+    fn lines_only(&self, event: &Event) -> bool {
+        match event {
+            Event::Input(_, event) => self.lines_only(event),
+            Event::NewLines(..)
+            | Event::PartialLine(..)
+            | Event::Eof
+            | Event::LineCount(_)
+            | Event::Encoding(_)
+            | Event::Matches { .. } => true,
+            _ => false,
+        }
+    }
+
+    fn screen_state(&self) -> Option<ScreenState> {
+        let opened_input = self.opened_input.as_ref()?.borrow();
+        Some(ScreenState {
+            current_line: self.current_line,
+            skipped_rows: self.skipped_rows(),
+            rows: opened_input.current_total_lines(),
+            complete_rows: opened_input.complete_rows(),
+            dropped_rows: opened_input.dropped_rows(),
+            end_shown: opened_input.reached_eof()
+                && opened_input.current_total_lines() < self.current_line + self.term_height(),
+            header_rows: self.header_rows(),
+            gutter_width: self.gutter_width(),
+            progress: self.progress(),
+            position: self
+                .expanded_prompt()
+                .unwrap_or_else(|| self.status_position()),
+            message: self.message.clone(),
+        })
+    }
+
+    /// Whether the screen differs from when it was `before`: either rows on
+    /// it were read or replaced, or it moved, or something else shown did.
+    fn screen_changed(&self, before: &ScreenState) -> bool {
+        let Some(after) = self.screen_state() else {
+            return true;
+        };
+        // rows past the complete ones may have been replaced, and a partial
+        // line dropped again
+        let changed = before.complete_rows..max(before.rows, after.rows);
+        let visible = before.current_line..before.current_line + self.term_height();
+        let lines_on_screen = changed.start < visible.end && visible.start < changed.end;
+        lines_on_screen
+            || ScreenState {
+                rows: before.rows,
+                complete_rows: before.complete_rows,
+                ..after
+            } != *before
+    }
+
+    /// Catches up with whatever the last event changed: the header is kept in
+    /// place, and the matches counted and waited for.
+    fn settle(&mut self) {
+        self.skip_header();
+        self.count_matches();
+        self.jump_to_awaited_match();
+        self.jump_to_match_searched_into();
+    }
+
+    fn handle_event(&mut self, event: Event) -> Result<()> {
+        match event {
+            Event::Input(id, event) => {
+                // an input closed since may still have had events on the way
+                let current = self.opened_input.as_ref().map(|input| input.borrow().id());
+                if current == Some(id) {
+                    return self.handle_event(*event);
+                }
+                self.handle_parked_event(id, *event);
+            }
+            Event::Term(event) => {
+                self.handle_crossterm_events(event)?;
+            }
+            e @ (Event::NewLines(..)
+            | Event::PartialLine(..)
+            | Event::Eof
+            | Event::LineCount(_)) => {
+                let eof = matches!(e, Event::Eof);
+                self.opened_input_mut().handle_event(e)?;
+                let evicted = self.opened_input_mut().take_evicted_rows();
+                if evicted > 0 {
+                    self.on_evicted(evicted);
+                }
+                self.restore_position();
+                if let Some(lines) = self.lines_from_end {
+                    self.go_to_line_from_end(lines);
+                }
+                if self.stick_to_tail {
+                    self.go_to_bottom();
+                }
+                for _ in 0..std::mem::take(&mut self.owed_scrolls) {
+                    self.scroll_down_or_wait();
+                }
+                if eof && self.cli.exit_follow_at_eof && self.cli.follow_name {
+                    self.stop_following();
+                }
+                // with following stopped, this end is the end for good
+                if eof {
+                    self.quit_if_at_eof();
+                }
+            }
+            e @ Event::Encoding(_) => self.opened_input_mut().handle_event(e)?,
+            Event::Binary if !self.cli.force => self.mode = AppMode::BinaryWarning,
+            Event::Binary => {}
+            Event::Err(error) => return Err(error),
+            Event::ReadWarning(warning) => self.message = Some(warning),
+            Event::Control(command) => self.on_control_command(command),
+            Event::Matches { id, rows, scanned } => {
+                // a counter for a search since replaced may still be sending
+                if let Some(counter) = self.match_counter.as_mut().filter(|c| c.id() == id) {
+                    counter.done();
+                    self.match_count.extend(rows, scanned);
+                }
+            }
+            Event::Signal(signal) => {
+                self.exit_signal = Some(signal);
+                self.quit();
+            }
+            // the lines read so far stay, like those of a read that fails later
+            Event::ReaderThreadErrReturned => {
+                let joined = self.opened_input_mut().join_reader();
+                if let Err(err) = joined {
+                    self.message = Some(err.to_string());
+                }
+            }
+        };
+        Ok(())
+    }
+
+    /// Moves what points at rows up by the `rows` dropped from the front of
+    /// the view for `--max-lines`. Matches are counted anew, as rows being
+    /// counted have moved.
+    fn on_evicted(&mut self, rows: usize) {
+        self.current_line = self.current_line.saturating_sub(rows);
+        self.last_match = self.last_match.and_then(|row| row.checked_sub(rows));
+        self.restore_line = self.restore_line.map(|line| line.saturating_sub(rows));
+        self.skipped_rows = None;
+        self.match_count = MatchCount::default();
+        self.match_counter = None;
+    }
+
+    /// Keeps a parked input up to date with what its reader sends, noting
+    /// when that is new lines. One whose reader fails is dropped, to be
+    /// opened anew, and the failure reported, when it is shown again.
+    fn handle_parked_event(&mut self, id: u64, event: Event) {
+        let Some(parked) = self.parked.values_mut().find(|p| p.opened.id() == id) else {
+            return;
+        };
+        let new_lines = matches!(event, Event::NewLines(..) | Event::PartialLine(..));
+        match event {
+            Event::NewLines(..)
+            | Event::PartialLine(..)
+            | Event::Eof
+            | Event::LineCount(_)
+            | Event::Encoding(_)
+            | Event::ReadWarning(_) => {
+                if parked.opened.handle_event(event).is_ok() {
+                    parked.unread |= new_lines;
+                    let evicted = parked.opened.take_evicted_rows();
+                    parked.current_line = parked.current_line.saturating_sub(evicted);
+                }
+            }
+            // only sent as the input is opened, before it can have been left
+            Event::Binary => {}
+            _ => {
+                log::warn!("dropping {} after its reader failed", parked.opened.name());
+                parked.opened.close();
+                self.parked.retain(|_, p| p.opened.id() != id);
+            }
+        }
+    }
+
+    fn handle_crossterm_events(&mut self, event: crossterm::event::Event) -> Result<()> {
+        match event {
+            // it's important to check KeyEventKind::Press to avoid handling key release events
+            crossterm::event::Event::Key(key) if key.kind == KeyEventKind::Press => {
+                self.on_key_event(key)
+            }
+            crossterm::event::Event::Mouse(_) => {}
+            crossterm::event::Event::Resize(colomns, rows) => {
+                self.on_term_resize(Size::new(colomns, rows))
+            }
+            crossterm::event::Event::Paste(text) => self.on_paste(&text),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Pastes into the prompt if one is open. Anything pasted while paging
+    /// is dropped, rather than run key by key as commands.
+    fn on_paste(&mut self, text: &str) {
+        if self.prompt_prefix().is_some() {
+            self.prompt.paste(text);
+        }
+    }
+
+    fn on_key_event(&mut self, key: KeyEvent) {
+        self.message = None;
+        match self.mode {
+            AppMode::Search
+            | AppMode::Save
+            | AppMode::Filter
+            | AppMode::Command
+            | AppMode::Pipe => {
+                return self.on_prompt_key_event(key);
+            }
+            AppMode::BinaryWarning => return self.on_binary_warning_key_event(key),
+            AppMode::QuitConfirmation => {
+                // anything but a yes keeps paging
+                match key.code {
+                    _ if keys::is_interrupt(&key) => self.quit(),
+                    KeyCode::Char('y' | 'Y' | 'q' | 'Q') => self.quit(),
+                    _ => self.mode = AppMode::Main,
+                }
+                return;
+            }
+            AppMode::Info => {
+                self.mode = AppMode::Main;
+                return;
+            }
+            _ => {}
+        }
+        let (key_state, action) = self.key_state.next(key);
+        self.key_state = key_state;
+        // any key other than the speed controls stops auto-scrolling
+        if !matches!(
+            action,
+            Action::ToggleAutoScroll | Action::AutoScrollFaster | Action::AutoScrollSlower
+        ) {
+            self.auto_scroll = None;
+        }
+        self.on_action(action);
+    }
+
+    fn on_action(&mut self, action: Action) {
+        // moving on their own means the user is past the remembered line, or
+        // has given up on the match that hasn't turned up yet
+        if !matches!(action, Action::None) {
+            self.restore_line = None;
+            self.awaited_match = None;
+            self.searched_into = None;
+            self.lines_from_end = None;
+            self.stick_to_tail = false;
+        }
+        // anything but scrolling by rows shows the top line in full again
+        if !matches!(
+            action,
+            Action::ScrollUpOneLine
+                | Action::ScrollDownOneLine
+                | Action::ScrollUpScreen
+                | Action::ScrollDownScreen
+                | Action::Repeat(_)
+                | Action::None
+        ) {
+            self.skipped_rows = None;
+        }
+        if !matches!(action, Action::ScrollDownOneLine | Action::None) {
+            self.owed_scrolls = 0;
+        }
+        if action.is_repeatable() {
+            self.last_action = Some(action);
+        }
+        match action {
+            Action::GoToTop => self.go_to_top(),
+            Action::GoToBottom => {
+                self.go_to_bottom();
+                self.stick_to_tail = self.more_to_come();
+            }
+            Action::GoToLine(number) => self.go_to_line_number(number),
+            Action::GoToLineFromEnd(lines) => self.go_to_line_from_end(lines),
+            Action::GoToByte(offset) => self.go_to_byte(offset),
+            Action::NextParagraph => self.go_to_paragraph(SearchDirection::Forward),
+            Action::PrevParagraph => self.go_to_paragraph(SearchDirection::Backward),
+            Action::ScrollUpOneLine if self.cli.cursor => self.move_cursor_up(),
+            Action::ScrollDownOneLine if self.cli.cursor => self.move_cursor_down(),
+            Action::ScrollUpOneLine => self.scroll_up_one_row(),
+            Action::ScrollDownOneLine => self.scroll_down_or_wait(),
+            Action::ScrollUpHalfScreen(amount) => {
+                self.scroll_amount = amount.or(self.scroll_amount);
+                self.scroll_up_half_screen()
+            }
+            Action::ScrollDownHalfScreen(amount) => {
+                self.scroll_amount = amount.or(self.scroll_amount);
+                self.scroll_down_half_screen()
+            }
+            Action::ScrollUpScreen => self.scroll_up_screen(),
+            Action::ScrollDownScreen => self.scroll_down_screen(),
+            Action::StartSearch(direction) => self.start_search(direction),
+            Action::Save => self.start_save(),
+            Action::EnterCommand => self.start_command_line(),
+            Action::StartPipe => {
+                self.prompt.clear();
+                self.mode = AppMode::Pipe;
+            }
+            Action::StartFilter(context) => self.start_filter(context),
+            Action::SearchNext(count) => self.repeat_search(count, false),
+            Action::SearchPrev(count) => self.repeat_search(count, true),
+            Action::Refresh => self.force_clear = true,
+            Action::ToggleWrap => self.toggle_wrap(),
+            Action::ToggleWhitespace => {
+                self.render_options.show_whitespace = !self.render_options.show_whitespace
+            }
+            Action::ToggleTheme => self.theme = self.theme.toggled(),
+            Action::ToggleRelativeNumbers => self.toggle_relative_numbers(),
+            Action::ShowInfo => self.mode = AppMode::Info,
+            Action::EditFile => self.edit_current(),
+            Action::ScrollLeft => self.scroll_horizontally(false),
+            Action::ScrollRight => self.scroll_horizontally(true),
+            Action::ScrollToHorizontalStart => self.render_options.column_offset = 0,
+            Action::ScrollToHorizontalEnd => self.scroll_to_horizontal_end(),
+            Action::ToggleAutoScroll => {
+                self.auto_scroll = match self.auto_scroll {
+                    Some(_) => None,
+                    None => Some(AutoScroll::new()),
+                }
+            }
+            Action::AutoScrollFaster => {
+                if let Some(auto_scroll) = &mut self.auto_scroll {
+                    auto_scroll.set_interval(auto_scroll.interval / 2);
+                }
+            }
+            Action::AutoScrollSlower => {
+                if let Some(auto_scroll) = &mut self.auto_scroll {
+                    auto_scroll.set_interval(auto_scroll.interval * 2);
+                }
+            }
+            Action::NextUnreadFile => self.next_unread_file(false),
+            Action::PrevUnreadFile => self.next_unread_file(true),
+            Action::Repeat(count) => {
+                if let Some(last) = self.last_action {
+                    for _ in 0..count {
+                        self.on_action(last);
+                    }
+                }
+            }
+            Action::None => {}
+            Action::Quit => self.confirm_quit(),
+            Action::ForceQuit => self.quit(),
+        }
+        if matches!(
+            action,
+            Action::GoToBottom
+                | Action::GoToLine(_)
+                | Action::GoToLineFromEnd(_)
+                | Action::GoToByte(_)
+                | Action::NextParagraph
+                | Action::ScrollDownOneLine
+                | Action::ScrollDownHalfScreen(_)
+                | Action::ScrollDownScreen
+                | Action::SearchNext(_)
+                | Action::SearchPrev(_)
+        ) {
+            self.quit_if_at_eof();
+        }
+    }
+
+    /// With `--quit-at-eof`, quits as `q` would, asking first if need be,
+    /// once the end of the last file is on screen.
+    fn quit_if_at_eof(&mut self) {
+        if self.cli.quit_at_eof && self.end_on_screen() {
+            self.confirm_quit();
+        }
+    }
+
+    fn confirm_quit(&mut self) {
+        let confirm = match self.cli.confirm_quit {
+            ConfirmQuit::Never => false,
+            ConfirmQuit::Always => true,
+            ConfirmQuit::Stdin => self.paging_stdin,
+        };
+        if confirm {
+            self.mode = AppMode::QuitConfirmation;
+        } else {
+            self.quit();
+        }
+    }
+
+    fn on_auto_scroll_tick(&mut self) {
+        self.scroll_down_one_row();
+        self.quit_if_at_eof();
+        if let Some(auto_scroll) = &mut self.auto_scroll {
+            auto_scroll.next_tick = Instant::now() + auto_scroll.interval;
+        }
+    }
+
+    fn on_prompt_key_event(&mut self, key: KeyEvent) {
+        match self.prompt.handle_key(key) {
+            PromptResult::Pending => {}
+            PromptResult::Submit(input) => {
+                let mode = std::mem::replace(&mut self.mode, AppMode::Main);
+                match mode {
+                    AppMode::Search => self.submit_search(input),
+                    AppMode::Save => self.save_buffer(&input),
+                    AppMode::Filter => self.submit_filter(input),
+                    AppMode::Command => self.submit_command(&input),
+                    AppMode::Pipe => self.submit_pipe(&input),
+                    _ => unreachable!(),
+                }
+            }
+            PromptResult::Cancel => self.mode = AppMode::Main,
+        }
+    }
+
+    /// The text in front of the prompt input, if a prompt is open.
+    fn prompt_prefix(&self) -> Option<String> {
+        match self.mode {
+            AppMode::Search => Some(self.search_direction.prompt().to_string()),
+            AppMode::Save => Some("Save to: ".to_string()),
+            AppMode::Filter => Some("&".to_string()),
+            AppMode::Command => Some(":".to_string()),
+            AppMode::Pipe => Some("|".to_string()),
+            _ => None,
+        }
+    }
+
+    fn on_control_command(&mut self, command: ControlCommand) {
+        match command.action() {
+            Some(action) => self.on_action(action),
+            None => {
+                if let ControlCommand::Search { pattern, backward } = command {
+                    self.search_direction = if backward {
+                        SearchDirection::Backward
+                    } else {
+                        SearchDirection::Forward
+                    };
+                    self.submit_search(pattern);
+                }
+            }
+        }
+    }
+
+    fn submit_search(&mut self, query: String) {
+        if !query.is_empty() {
+            self.search = Some(
+                Search::new(query, self.search_direction)
+                    .whole_word(self.cli.whole_word)
+                    .ignore_case(self.cli.ignore_case),
+            );
+            self.last_match = None;
+            self.match_count = MatchCount::default();
+            self.match_counter = None;
+        }
+        self.repeat_search(1, false);
+    }
+
+    fn start_filter(&mut self, context: Option<usize>) {
+        self.filter_context = context.unwrap_or(0);
+        self.prompt.clear();
+        self.mode = AppMode::Filter;
+    }
+
+    /// Shows only the lines matching `query`, with `filter_context` lines
+    /// around each. An empty query shows every line again.
+    fn submit_filter(&mut self, query: String) {
+        let filter = (!query.is_empty()).then(|| {
+            let pattern = Pattern {
+                whole_word: self.cli.whole_word,
+                ignore_case: self.cli.ignore_case,
+                ..Pattern::new(query)
+            };
+            FilterView::new(pattern, self.filter_context)
+        });
+        self.opened_input_mut().set_filter(filter);
+        self.current_line = 0;
+        self.last_match = None;
+        // matches are counted by row, and the rows just changed
+        self.match_count = MatchCount::default();
+        self.match_counter = None;
+    }
+
+    /// Squeezes runs of blank lines into one, or shows them all again,
+    /// keeping the top line where it is.
+    fn set_squeeze(&mut self, squeeze: bool) {
+        self.cli.squeeze_blank_lines = squeeze;
+        let top = (self.current_line < self.opened_input().current_total_lines())
+            .then(|| self.opened_input().line_index(self.current_line))
+            .flatten();
+        self.opened_input_mut().set_squeeze(squeeze);
+        self.current_line = top.map_or(0, |top| self.opened_input().row_of_line(top));
+        self.current_line = min(self.current_line, self.current_max_line());
+        self.last_match = None;
+        // matches are counted by row, and the rows just changed
+        self.match_count = MatchCount::default();
+        self.match_counter = None;
+    }
+
+    fn start_command_line(&mut self) {
+        self.prompt.clear();
+        self.mode = AppMode::Command;
+    }
+
+    /// Runs a command entered at the `:` prompt.
+    fn submit_command(&mut self, line: &str) {
+        if line.trim().is_empty() {
+            return;
+        }
+        let command = match line.parse::<Command>() {
+            Ok(command) => command,
+            Err(err) => {
+                self.message = Some(err.to_string());
+                return;
+            }
+        };
+        match command {
+            Command::Quit { force: false } => self.on_action(Action::Quit),
+            Command::Quit { force: true } => self.on_action(Action::ForceQuit),
+            Command::GoToLine(number) => self.on_action(Action::GoToLine(number)),
+            Command::GoToByte(offset) => self.on_action(Action::GoToByte(offset)),
+            Command::NextFile => self.next_file(false),
+            Command::PrevFile => self.next_file(true),
+            Command::Edit(path) => self.edit_file(&path),
+            Command::Info => self.mode = AppMode::Info,
+            Command::GoToMatch(_) if self.search.is_none() => {
+                self.message = Some("No previous search pattern".to_string());
+            }
+            // it may not have been counted yet
+            Command::GoToMatch(ordinal) => self.awaited_match = Some(ordinal),
+            Command::Set(Setting::Wrap(wrap)) => {
+                if wrap != self.wrap {
+                    self.toggle_wrap();
+                }
+            }
+            Command::Set(Setting::Scrolloff(lines)) => self.cli.scrolloff = lines,
+            Command::Set(Setting::Squeeze(squeeze)) => self.set_squeeze(squeeze),
+            Command::Set(Setting::Window(lines)) => self.cli.window = lines,
+            Command::Set(Setting::Overlap(lines)) => self.cli.overlap = Some(lines),
+            Command::Set(Setting::WholeWord(whole_word)) => {
+                self.cli.whole_word = whole_word;
+                // `n` and `N` go on with the last search the new way
+                if let Some(search) = self.search.take() {
+                    self.search = Some(search.whole_word(whole_word));
+                    self.match_count = MatchCount::default();
+                    self.match_counter = None;
+                }
+            }
+        }
+    }
+
+    fn start_save(&mut self) {
+        self.prompt.clear();
+        self.mode = AppMode::Save;
+    }
+
+    /// Writes what has been read of the input to `path`, which for stdin is
+    /// the only way to keep it.
+    fn save_buffer(&mut self, path: &str) {
+        if path.is_empty() {
+            return;
+        }
+        let result = File::create(path).and_then(|file| {
+            let mut writer = BufWriter::new(file);
+            let lines = self
+                .opened_input()
+                .write_lines(&mut writer, self.cli.save_raw)?;
+            writer.flush()?;
+            Ok(lines)
+        });
+        self.message = Some(match result {
+            Ok(lines) => format!("Saved {lines} lines to {path}"),
+            Err(err) => format!("Failed to save to {path}: {err}"),
+        });
+    }
+
+    fn on_binary_warning_key_event(&mut self, key: KeyEvent) {
+        match key.code {
+            _ if keys::is_interrupt(&key) => self.quit(),
+            KeyCode::Char('y' | 'Y') => self.mode = AppMode::Main,
+            KeyCode::Char('n' | 'N' | 'q') | KeyCode::Esc => self.quit(),
+            _ => {}
+        }
+    }
+
+    fn start_search(&mut self, direction: SearchDirection) {
+        self.search_direction = direction;
+        self.prompt.clear();
+        self.mode = AppMode::Search;
+    }
+
+    /// Moves to the `count`-th next match of the last search. `reverse` flips
+    /// the direction the search was entered with, as `N` does.
+    fn repeat_search(&mut self, count: usize, reverse: bool) {
+        let Some(search) = self.search.clone() else {
+            self.message = Some("No previous search pattern".to_string());
+            return;
+        };
+        let direction = if reverse {
+            search.direction.reversed()
+        } else {
+            search.direction
+        };
+
+        // continue from the last hit while it is still on screen, so a match
+        // that can't be scrolled to the top isn't found again
+        let screen = self.current_line..self.current_line + self.term_height();
+        let mut line = self
+            .last_match
+            .filter(|line| screen.contains(line))
+            .unwrap_or(self.current_line);
+        let mut found = 0;
+        let mut wrapped = false;
+        while found < count {
+            // never the line we are on, so every press lands on a new match
+            let from = match direction {
+                SearchDirection::Forward => Some(line + 1),
+                SearchDirection::Backward => line.checked_sub(1),
+            };
+            let mut hit =
+                from.and_then(|from| self.opened_input().find_match(&search, from, direction));
+            if hit.is_none() && self.cli.search_wrap && !self.cli.search_across_files && !wrapped {
+                wrapped = true;
+                let from = match direction {
+                    SearchDirection::Forward => 0,
+                    SearchDirection::Backward => usize::MAX,
+                };
+                hit = self.opened_input().find_match(&search, from, direction);
+            }
+            match hit {
+                Some(hit) => {
+                    line = hit;
+                    found += 1;
+                }
+                None => break,
+            }
+        }
+
+        // past the last match of a file read to its end, the search goes on
+        // in the next one
+        if found == 0
+            && self.cli.search_across_files
+            && !self.more_to_come()
+            && self.search_next_file(direction)
+        {
+            return;
+        }
+        if found == 0 {
+            self.message = Some(format!("Pattern not found: {}", search.pattern.query));
+            return;
+        }
+        if found < count {
+            self.message = Some(format!("Only {found} of {count} matches found"));
+        } else if wrapped {
+            let end = match direction {
+                SearchDirection::Forward => "top",
+                SearchDirection::Backward => "bottom",
+            };
+            self.message = Some(format!("Search wrapped to the {end}"));
+        }
+        self.show_match(line);
+    }
+
+    /// Makes the match at `row` the last one and puts it on screen.
+    fn show_match(&mut self, row: usize) {
+        self.last_match = Some(row);
+        self.current_line = self.top_line_for(row);
+        let Some(search) = &self.search else {
+            return;
+        };
+        let columns = self
+            .opened_input()
+            .match_columns(search, row, &self.render_options);
+        if let Some(columns) = columns {
+            self.scroll_to_columns(columns);
+        }
+    }
+
+    fn on_term_resize(&mut self, new_size: Size) {
+        self.term_size = new_size;
+        self.current_line = min(self.current_line, self.current_max_line());
+    }
+
+    fn term_half_height(&self) -> usize {
+        (self.term_size.height / 2).max(1) as _
+    }
+
+    /// Rows of the status bar and of the position bar. The lines keep at
+    /// least one row, so a terminal too small for all three goes without the
+    /// position bar, and then without the status bar, unless a prompt or a
+    /// question needs it more than the lines do.
+    fn bar_rows(&self) -> (u16, u16) {
+        let height = self.term_size.height;
+        let asking = self.prompt_prefix().is_some()
+            || matches!(
+                self.mode,
+                AppMode::BinaryWarning | AppMode::QuitConfirmation
+            );
+        let status = if height > STATUS_BAR_HEIGHT || asking {
+            min(height, STATUS_BAR_HEIGHT)
+        } else {
+            0
+        };
+        let bar = u16::from(self.cli.position_bar.is_some() && height > status + 1);
+        (status, bar)
+    }
+
+    /// Height of the rows lines scroll in, i.e. the terminal without the
+    /// status bar and the `--header` lines.
+    fn term_height(&self) -> usize {
+        self.text_height() - self.header_rows()
+    }
+
+    /// Height of the text area, i.e. the terminal without the status bar and
+    /// any position bar. Scrolling goes by at least one row even when there
+    /// is no room for any.
+    fn text_height(&self) -> usize {
+        let (status, bar) = self.bar_rows();
+        self.term_size.height.saturating_sub(status + bar).max(1) as _
+    }
+
+    /// Rows taken by the `--header` lines read so far, leaving at least one
+    /// to scroll in.
+    fn header_rows(&self) -> usize {
+        let read = self
+            .opened_input
+            .as_ref()
+            .map_or(0, |input| input.borrow().lines_read());
+        min(self.cli.header, read).min(self.text_height().saturating_sub(1))
+    }
+
+    /// Keeps the `--header` lines from also scrolling by beneath themselves.
+    /// A filtered view has rows of its own, so it scrolls from its first.
+    fn skip_header(&mut self) {
+        if self.opened_input.is_some() && !self.opened_input().filtered() {
+            let header = self.opened_input().row_of_line(self.header_rows());
+            self.current_line = max(self.current_line, header);
+        }
+    }
+
+    fn term_width(&self) -> usize {
+        self.term_size.width as _
+    }
+
+    /// Columns left for the lines beside the line numbers.
+    fn text_width(&self) -> usize {
+        self.term_width().saturating_sub(self.gutter_width()).max(1)
+    }
+
+    /// Columns taken by the line numbers, with room for the number of the
+    /// last line and a space after it. Widening only as the lines read get
+    /// more digits keeps the text from shifting about while scrolling.
+    fn gutter_width(&self) -> usize {
+        let Some(input) = self
+            .opened_input
+            .as_ref()
+            .filter(|_| self.line_numbers.is_some())
+        else {
+            return 0;
+        };
+        let last = input.borrow().last_line_number();
+        let width = max(last.max(1).ilog10() as usize + 1, 3) + 1;
+        // the lines come first on a terminal too narrow for both
+        if width < self.term_width() { width } else { 0 }
+    }
+
+    /// The number of each line on the screen for `--line-numbers`, blank for
+    /// filter separators.
+    fn line_labels(&self) -> Vec<Line<'static>> {
+        let width = self.gutter_width();
+        let Some(numbers) = self.line_numbers.filter(|_| width > 0) else {
+            return Vec::new();
+        };
+        let digits = width - 1;
+        let reference = self.cursor_line().unwrap_or(self.current_line);
+        let opened_input = self.opened_input();
+        let end = min(
+            self.current_line + self.term_height(),
+            opened_input.current_total_lines(),
+        );
+        (self.current_line..end)
+            .map(|row| {
+                let label = match numbers {
+                    LineNumbers::Relative if row != reference => Some(row.abs_diff(reference)),
+                    _ => opened_input.line_number(row),
+                };
+                label.map_or_else(Line::default, |label| {
+                    Line::raw(format!("{label:>digits$}")).dim()
+                })
+            })
+            .collect()
+    }
+
+    fn toggle_relative_numbers(&mut self) {
+        self.line_numbers = match self.line_numbers {
+            Some(LineNumbers::Relative) => Some(LineNumbers::Absolute),
+            _ => Some(LineNumbers::Relative),
+        };
+    }
+
+    /// The width lines are wrapped at, or `None` when they are cut off.
+    fn wrap_width(&self) -> Option<usize> {
+        let width = match self.cli.wrap_width {
+            Some(width) => min(self.text_width(), width.into()),
+            None => self.text_width(),
+        };
+        self.wrap.then_some(width)
+    }
+
+    /// Scrolls sideways by half the screen width, unless lines are wrapped.
+    fn scroll_horizontally(&mut self, right: bool) {
+        if self.wrap {
+            return;
+        }
+        let amount = (self.text_width() / 2).max(1);
+        let offset = &mut self.render_options.column_offset;
+        *offset = if right {
+            offset.saturating_add(amount)
+        } else {
+            offset.saturating_sub(amount)
+        };
+    }
+
+    fn scroll_to_horizontal_end(&mut self) {
+        if self.wrap {
+            return;
+        }
+        let longest = self.opened_input().max_width(
+            self.current_line,
+            self.term_height(),
+            &self.render_options,
+        );
+        self.render_options.column_offset = longest.saturating_sub(self.visible_columns());
+    }
+
+    /// Scrolls sideways just far enough to show `columns`, such as those of a
+    /// search hit, unless lines are wrapped. With `--max-columns` or
+    /// `--chop-marks` the last column is kept clear, as it may be taken by
+    /// the mark of a cut line, and with `--chop-marks` the first one too
+    /// once scrolled.
+    fn scroll_to_columns(&mut self, columns: Range<usize>) {
+        if self.wrap {
+            return;
+        }
+        let marked = self.cli.chop_marks.is_some();
+        let right = usize::from(self.render_options.max_columns.is_some() || marked);
+        let left = usize::from(marked);
+        let visible = self.visible_columns().saturating_sub(right + left).max(1);
+        let offset = &mut self.render_options.column_offset;
+        if *offset > 0 && columns.start < *offset + left {
+            *offset = columns.start.saturating_sub(left);
+        } else if columns.end > *offset + visible + left * usize::from(*offset > 0) {
+            // a hit too wide for the screen shows from its start
+            *offset = min(columns.start, columns.end.saturating_sub(visible)).saturating_sub(left);
+        }
+    }
+
+    /// Columns of a line shown at once, with no wrapping.
+    fn visible_columns(&self) -> usize {
+        self.render_options
+            .max_columns
+            .map_or(self.text_width(), |columns| min(columns, self.text_width()))
+    }
+
+    fn toggle_wrap(&mut self) {
+        self.wrap = !self.wrap;
+        // wrapped lines have nothing off to the side
+        self.render_options.column_offset = 0;
+        self.current_line = min(self.current_line, self.current_max_line());
+    }
+
+    fn quit(&mut self) {
+        self.mode = AppMode::Terminated
+    }
+
+    /// The line the cursor is on in `--cursor` mode. The cursor keeps its row
+    /// on screen as the screen scrolls, but never goes past the last line,
+    /// nor below the screen when the lines above it wrap.
+    fn cursor_line(&self) -> Option<usize> {
+        if !self.cli.cursor {
+            return None;
+        }
+        let total = self.opened_input().current_total_lines();
+        let row = min(self.cursor, self.term_height().saturating_sub(1));
+        let last = total.checked_sub(1)?;
+        let mut line = min(self.current_line + row, last);
+        while line > self.current_line && self.rows_through(line - 1) >= self.term_height() {
+            line -= 1;
+        }
+        Some(line)
+    }
+
+    /// Screen rows from the top of the screen to the end of `line`, which is
+    /// one row a line unless lines wrap.
+    fn rows_through(&self, line: usize) -> usize {
+        let Some(width) = self.wrap_width() else {
+            return line + 1 - self.current_line;
+        };
+        let opened_input = self.opened_input();
+        (self.current_line..=line)
+            .map(|row| opened_input.display_rows(row, width, &self.render_options))
+            .sum::<usize>()
+            .saturating_sub(self.skipped_rows())
+    }
+
+    /// Rows `--scrolloff` keeps between the cursor and the top or bottom of
+    /// the screen, leaving the cursor at least one row to move in.
+    fn scrolloff(&self) -> usize {
+        min(self.cli.scrolloff, self.term_height().saturating_sub(1) / 2)
+    }
+
+    /// Moves the cursor up a line, scrolling instead once it is within the
+    /// scroll margin of the top. At the start of the input the margin can't
+    /// be kept, and the cursor goes on to the first row.
+    fn move_cursor_up(&mut self) {
+        let Some(line) = self.cursor_line() else {
+            return;
+        };
+        self.cursor = line - self.current_line;
+        if self.cursor > self.scrolloff() || self.current_line == 0 {
+            self.cursor = self.cursor.saturating_sub(1);
+        } else {
+            self.scroll_up_one_line();
+        }
+    }
+
+    /// Like [`Self::move_cursor_up`], towards the bottom.
+    fn move_cursor_down(&mut self) {
+        let Some(line) = self.cursor_line() else {
+            return;
+        };
+        self.cursor = line - self.current_line;
+        if line + 1 >= self.opened_input().current_total_lines() {
+            return;
+        }
+        self.cursor += 1;
+        // the screen scrolls until the line and the margin below it are on
+        // it, or the input ends
+        while self.cursor > 0
+            && self.current_line < self.current_max_line()
+            && self.rows_through(line + 1) + self.scrolloff() > self.term_height()
+        {
+            self.scroll_down_one_line();
+            self.cursor -= 1;
+        }
+    }
+
+    /// Rows of the top line scrolled off the screen, see
+    /// [`Self::skipped_rows`](App#structfield.skipped_rows).
+    fn skipped_rows(&self) -> usize {
+        match self.skipped_rows {
+            Some((line, rows)) if line == self.current_line && self.wrap => rows,
+            _ => 0,
+        }
+    }
+
+    /// Scrolls up a screen row, which is only part of a line when it wraps.
+    fn scroll_up_one_row(&mut self) {
+        let Some(width) = self.wrap_width() else {
+            return self.scroll_up_one_line();
+        };
+        let skipped = self.skipped_rows();
+        if skipped > 0 {
+            self.skipped_rows = Some((self.current_line, skipped - 1));
+            return;
+        }
+        if self.current_line == 0 {
+            return;
+        }
+        self.current_line -= 1;
+        let rows = self
+            .opened_input()
+            .display_rows(self.current_line, width, &self.render_options);
+        self.skipped_rows = Some((self.current_line, rows - 1));
+    }
+
+    /// Scrolls down a screen row, for as long as the screen stays filled.
+    fn scroll_down_one_row(&mut self) {
+        let Some(width) = self.wrap_width() else {
+            return self.scroll_down_one_line();
+        };
+        let skipped = self.skipped_rows();
+        let height = self.term_height();
+        let (top_rows, more_below) = {
+            let opened_input = self.opened_input();
+            let total = opened_input.current_total_lines();
+            if self.current_line >= total {
+                return;
+            }
+            let rows = |row| opened_input.display_rows(row, width, &self.render_options);
+            let mut below = 0;
+            for row in self.current_line..total {
+                below += rows(row);
+                if below - skipped > height {
+                    break;
+                }
+            }
+            (rows(self.current_line), below - skipped > height)
+        };
+        if !more_below {
+            return;
+        }
+        if skipped + 1 < top_rows {
+            self.skipped_rows = Some((self.current_line, skipped + 1));
+        } else {
+            self.current_line += 1;
+        }
+    }
+
+    /// Scrolls down a row like [`Self::scroll_down_one_row`], or, at the end
+    /// of an input that is still being read, as soon as the next line is.
+    fn scroll_down_or_wait(&mut self) {
+        let before = (self.current_line, self.skipped_rows());
+        self.scroll_down_one_row();
+        if (self.current_line, self.skipped_rows()) == before && self.more_to_come() {
+            self.owed_scrolls += 1;
+        }
+    }
+
+    /// Turns following off for `--exit-follow-at-eof`, as if `--follow-name`
+    /// had never been given: the readers stop polling for more once at the
+    /// end, and the files kept open to follow them are left as they were.
+    fn stop_following(&mut self) {
+        self.cli.follow_name = false;
+        self.stick_to_tail = false;
+        self.opened_input().stop_following();
+        for parked in self.parked.values() {
+            parked.opened.stop_following();
+        }
+        self.message = Some("Reached the end, stopped following".to_string());
+    }
+
+    /// Whether `--quit-at-eof` is done: the last input is all read and its
+    /// last screen shown, rather than scrolled away from.
+    fn end_on_screen(&self) -> bool {
+        self.mode == AppMode::Main
+            && self.current_input + 1 >= self.inputs.len()
+            && !self.more_to_come()
+            && self.current_line >= self.current_max_line()
+    }
+
+    /// Whether the input may still grow: it hasn't all been read, or is
+    /// followed past its end.
+    fn more_to_come(&self) -> bool {
+        !self.opened_input().reached_eof() || self.cli.follow_name
+    }
+
+    fn scroll_up_one_line(&mut self) {
+        self.current_line = self.current_line.saturating_sub(1)
+    }
+
+    fn scroll_down_one_line(&mut self) {
+        self.current_line = min(self.current_line.saturating_add(1), self.current_max_line())
+    }
+
+    /// Lines scrolled by `d` and `u`.
+    fn half_screen_amount(&self) -> usize {
+        self.scroll_amount
+            .unwrap_or_else(|| self.term_half_height())
+    }
+
+    fn scroll_up_half_screen(&mut self) {
+        self.current_line = self.current_line.saturating_sub(self.half_screen_amount())
+    }
+
+    fn scroll_down_half_screen(&mut self) {
+        self.current_line = min(
+            self.current_line.saturating_add(self.half_screen_amount()),
+            self.current_max_line(),
+        )
+    }
+
+    /// Lines scrolled by `f` and `b`, or screen rows when lines are wrapped.
+    /// The last rows of a screen are kept as the first of the next, so there
+    /// is something to read on from.
+    fn window_amount(&self) -> usize {
+        self.cli
+            .window
+            .unwrap_or_else(|| {
+                let overlap = self.cli.overlap.unwrap_or(DEFAULT_OVERLAP);
+                self.term_height().saturating_sub(overlap)
+            })
+            .max(1)
+    }
+
+    fn scroll_up_screen(&mut self) {
+        if self.wrap_width().is_some() {
+            for _ in 0..self.window_amount() {
+                self.scroll_up_one_row();
+            }
+            return;
+        }
+        self.current_line = self.current_line.saturating_sub(self.window_amount())
+    }
+
+    fn scroll_down_screen(&mut self) {
+        if self.wrap_width().is_some() {
+            for _ in 0..self.window_amount() {
+                self.scroll_down_one_row();
+            }
+            return;
+        }
+        self.current_line = min(
+            self.current_line.saturating_add(self.window_amount()),
+            self.current_max_line(),
+        )
+    }
+
+    fn opened_input(&self) -> Ref<'_, OpenedInput> {
+        self.opened_input.as_ref().unwrap().borrow()
+    }
+
+    fn opened_input_mut(&self) -> RefMut<'_, OpenedInput> {
+        self.opened_input.as_ref().unwrap().borrow_mut()
+    }
+
+    fn current_max_line(&self) -> usize {
+        self.opened_input().max_top_line(
+            self.term_height(),
+            self.wrap_width(),
+            &self.render_options,
+        )
+    }
+
+    fn go_to_top(&mut self) {
+        self.current_line = 0
+    }
+
+    /// Puts the last screen of what has been read so far on screen. `G` also
+    /// stays there while more is read, see `stick_to_tail`.
+    fn go_to_bottom(&mut self) {
+        self.current_line = self.current_max_line()
+    }
+
+    fn go_to_line(&mut self, line: usize) {
+        self.current_line = min(line, self.current_max_line())
+    }
+
+    fn go_to_paragraph(&mut self, direction: SearchDirection) {
+        let boundary = self
+            .opened_input()
+            .find_paragraph_boundary(self.current_line, direction);
+        match (boundary, direction) {
+            (Some(row), _) => self.go_to_line(row),
+            (None, SearchDirection::Forward) => self.go_to_bottom(),
+            (None, SearchDirection::Backward) => self.go_to_top(),
+        }
+    }
+
+    /// Puts the line numbered `number`, counting from 1, at the top. This is
+    /// the one place line numbers typed by the user turn into line indices.
+    /// Lines dropped for `--max-lines` still count.
+    fn go_to_line_number(&mut self, number: usize) {
+        let dropped = self.opened_input().dropped_rows();
+        self.go_to_line(number.saturating_sub(1).saturating_sub(dropped))
+    }
+
+    /// Puts the line with the byte at `offset` at the top, or the nearest
+    /// line read for an offset outside of them.
+    fn go_to_byte(&mut self, offset: u64) {
+        let Some(index) = self.opened_input().line_at_byte(offset) else {
+            self.message = Some("No lines read yet".to_string());
+            return;
+        };
+        let row = self.opened_input().row_of_line(index);
+        self.go_to_line(row);
+    }
+
+    /// Puts the line `lines` before the end at the top, or the first line if
+    /// there aren't that many. Until the whole input is read the end keeps
+    /// moving, so this is redone as lines come in.
+    fn go_to_line_from_end(&mut self, lines: usize) {
+        let (total, reached_eof) = {
+            let opened_input = self.opened_input();
+            (
+                opened_input.current_total_lines(),
+                opened_input.reached_eof(),
+            )
+        };
+        self.go_to_line(total.saturating_sub(lines));
+        self.lines_from_end = (!reached_eof).then_some(lines);
+    }
+
+    /// The top line that puts `target` on the screen row chosen by
+    /// `--jump-target`. With wrapped lines, that is the first line from which
+    /// the rows above `target` fit in those above the chosen row.
+    fn top_line_for(&self, target: usize) -> usize {
+        let rows_above = match self.cli.jump_target {
+            JumpTarget::Top => 0,
+            JumpTarget::Center => self.term_height() / 2,
+            JumpTarget::Context(lines) => min(lines, self.term_height().saturating_sub(1)),
+        };
+        let top = match self.wrap_width() {
+            Some(width) => {
+                let opened_input = self.opened_input();
+                let mut top = target;
+                let mut rows = 0;
+                while top > 0 {
+                    rows += opened_input.display_rows(top - 1, width, &self.render_options);
+                    if rows > rows_above {
+                        break;
+                    }
+                    top -= 1;
+                }
+                top
+            }
+            None => target.saturating_sub(rows_above),
+        };
+        min(top, self.current_max_line())
+    }
+
+    /// Where the terminal cursor should be drawn, if anywhere.
+    fn cursor_position(&self, area: Rect) -> Option<Position> {
+        let prefix = self.prompt_prefix()?;
+        let x = (prefix.width() + self.prompt.cursor()) as u16;
+        Some(Position::new(
+            min(x, area.width.saturating_sub(1)),
+            area.bottom().saturating_sub(1),
+        ))
+    }
+
+    /// What the input is called in the status bar: its file name, or for
+    /// stdin the `--label` if one is given.
+    fn input_name(&self) -> String {
+        match &self.cli.label {
+            Some(label) if self.paging_stdin => label.clone(),
+            _ => self.opened_input().name().to_string(),
+        }
+    }
+
+    /// How far into the input the bottom of the screen is, from 0 to 1, out
+    /// of the whole input when its size is known up front.
+    fn progress(&self) -> f64 {
+        let (_, last, whole) = self.screen_lines();
+        let whole = whole.unwrap_or_else(|| self.lines_so_far());
+        let (last, whole) = self.wrapped_extent().unwrap_or((last, whole));
+        if whole == 0 {
+            return 1.0;
+        }
+        (last as f64 / whole as f64).min(1.0)
+    }
+
+    /// How many screen rows down the bottom of the screen is, out of the
+    /// rows of the whole input, once it has all been read and its lines are
+    /// wrapped. A line wrapping to many rows then counts for as much of the
+    /// input as it takes up on the screen. Until the rows are all counted,
+    /// a chunk more on each call, it is `None` and lines are gone by.
+    fn wrapped_extent(&self) -> Option<(usize, usize)> {
+        let width = self.wrap_width()?;
+        let opened_input = self.opened_input();
+        if !opened_input.reached_eof() || opened_input.dropped_rows() > 0 {
+            return None;
+        }
+        let options = &self.render_options;
+        let total = opened_input.current_total_lines();
+        let whole = opened_input.rows_before_within(total, width, options, ROW_COUNT_CHUNK)?;
+        // all counted by now, the rows before the screen among them
+        let top = opened_input.rows_before(self.current_line, width, options) + self.skipped_rows();
+        Some((min(top + self.term_height(), whole), whole))
+    }
+
+    /// Whether the rows of the input are still being counted for
+    /// [`Self::wrapped_extent`], which each redraw takes further.
+    fn counting_rows(&self) -> bool {
+        self.wrap_width().is_some_and(|width| {
+            let opened_input = self.opened_input();
+            opened_input.reached_eof()
+                && opened_input.dropped_rows() == 0
+                && opened_input
+                    .rows_before_within(
+                        opened_input.current_total_lines(),
+                        width,
+                        &self.render_options,
+                        0,
+                    )
+                    .is_none()
+        })
+    }
+
+    /// What the info overlay shows about the current input: what it is,
+    /// what its metadata says when it is a file, and how much of it has been
+    /// read.
+    fn input_info(&self) -> Vec<(&'static str, String)> {
+        let opened_input = self.opened_input();
+        let mut info = vec![("Input", self.input_name())];
+        let kind = self.inputs.get(self.current_input).map(|input| &input.kind);
+        // nothing is known of what isn't among the inputs
+        if let Some(kind) = kind {
+            let kind = match kind {
+                InputKind::OrdinaryFile(_) => "file",
+                InputKind::StdIn => "standard input",
+                InputKind::Piped(_) => "command output",
+                #[cfg(test)]
+                InputKind::Reader(_) => "reader",
+            };
+            info.push(("Kind", kind.to_string()));
+        }
+        if let Some(InputKind::OrdinaryFile(path)) = kind {
+            match fs::metadata(path) {
+                Ok(metadata) => {
+                    info.push(("Size", format!("{} bytes", metadata.len())));
+                    if let Ok(modified) = metadata.modified() {
+                        info.push(("Modified", format_timestamp(modified)));
+                    }
+                }
+                Err(err) => info.push(("Metadata", err.to_string())),
+            }
+        }
+        let read = opened_input.lines_read() + opened_input.evicted();
+        // a file's lines are counted ahead of reading them
+        let counted = opened_input.last_line_number();
+        let lines = if opened_input.reached_eof() {
+            read.to_string()
+        } else if counted > read {
+            format!("{counted}, {read} read so far")
+        } else {
+            format!("{read} read so far")
+        };
+        info.push(("Lines", lines));
+        let reading = if !opened_input.reached_eof() {
+            "still reading"
+        } else if self.cli.follow_name && matches!(kind, Some(InputKind::OrdinaryFile(_))) {
+            "read to the end, following"
+        } else {
+            "read to the end"
+        };
+        info.push(("Reading", reading.to_string()));
+        info.push(("Encoding", opened_input.encoding().name().to_string()));
+        info
+    }
+
+    /// Draws the info overlay in a box centered over `area`.
+    fn render_info(&self, area: Rect, buf: &mut Buffer) {
+        let info = self.input_info();
+        let key_width = info.iter().map(|(key, _)| key.len()).max().unwrap_or(0);
+        let lines: Vec<_> = info
+            .into_iter()
+            .map(|(key, value)| {
+                Line::from(vec![
+                    Span::raw(format!("{key:>key_width$}  ")).bold(),
+                    Span::raw(value),
+                ])
+            })
+            .collect();
+        let width = lines.iter().map(Line::width).max().unwrap_or(0) + 4;
+        let height = lines.len() + 2;
+        let width = min(width, area.width as usize) as u16;
+        let height = min(height, area.height as usize) as u16;
+        let info_area = Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height,
+        );
+        Clear.render(info_area, buf);
+        Paragraph::new(lines)
+            .style(self.theme.text)
+            .block(
+                Block::bordered()
+                    .title(" Info ")
+                    .padding(Padding::horizontal(1)),
+            )
+            .render(info_area, buf);
+    }
+
+    fn render_status_bar(&self, area: Rect, buf: &mut Buffer) {
+        if let Some(prefix) = self.prompt_prefix() {
+            Line::raw(prefix + self.prompt.input()).render(area, buf);
+            return;
+        }
+        if self.mode == AppMode::BinaryWarning {
+            let warning = format!(
+                "\"{}\" may be a binary file. View it anyway? (y/n)",
+                self.input_name()
+            );
+            Line::styled(warning, self.theme.status).render(area, buf);
+            return;
+        }
+        if self.mode == AppMode::QuitConfirmation {
+            Line::styled("Quit? (y/n)", self.theme.status).render(area, buf);
+            return;
+        }
+        if let Some(message) = &self.message {
+            Line::styled(message.as_str(), self.theme.status).render(area, buf);
+            return;
+        }
+
+        if let Some(prompt) = self.expanded_prompt() {
+            Line::styled(prompt, self.theme.status).render(area, buf);
+            return;
+        }
+        Line::styled(self.input_name(), self.theme.status).render(area, buf);
+        Line::raw(self.status_position())
+            .right_aligned()
+            .render(area, buf);
+    }
+
+    /// The first and last line on screen, counting the lines dropped off the
+    /// top, and the number of lines: known up front for a regular file, as
+    /// its lines are counted ahead of reading them, or else those read.
+    fn screen_lines(&self) -> (usize, usize, Option<usize>) {
+        let opened_input = self.opened_input();
+        let total = opened_input.current_total_lines();
+        let dropped = opened_input.dropped_rows();
+        let first = dropped + min(self.current_line + 1, total);
+        let last = dropped + min(self.current_line + self.term_height(), total);
+        (first, last, opened_input.total_lines())
+    }
+
+    /// How far into the input the bottom of the screen is, in percent.
+    fn percent(&self) -> usize {
+        let (_, last, whole) = self.screen_lines();
+        let whole = whole.unwrap_or_else(|| self.lines_so_far());
+        let (shown, whole) = self.wrapped_extent().unwrap_or((last, whole));
+        (shown * 100).checked_div(whole).unwrap_or(100)
+    }
+
+    /// Lines read so far, counting those dropped off the top.
+    fn lines_so_far(&self) -> usize {
+        let opened_input = self.opened_input();
+        opened_input.dropped_rows() + opened_input.current_total_lines()
+    }
+
+    /// The byte offset of the top line, or the size of the input once it is
+    /// scrolled past the end.
+    fn top_byte_offset(&self) -> u64 {
+        let opened_input = self.opened_input();
+        let offset = if self.current_line < opened_input.current_total_lines() {
+            opened_input.byte_offset(self.current_line)
+        } else {
+            None
+        };
+        offset.or(opened_input.size()).unwrap_or_default()
+    }
+
+    /// The status bar as laid out by `--prompt`, if it is.
+    fn expanded_prompt(&self) -> Option<String> {
+        let prompt = self.cli.prompt.as_ref()?;
+        let (first, _, whole) = self.screen_lines();
+        Some(prompt.expand(|spec| {
+            match spec {
+                PromptSpec::File => self.input_name(),
+                PromptSpec::Line => first.to_string(),
+                PromptSpec::Lines => whole.unwrap_or_else(|| self.lines_so_far()).to_string(),
+                PromptSpec::Percent => self.percent().to_string(),
+                PromptSpec::Byte => self.top_byte_offset().to_string(),
+                PromptSpec::Size => self
+                    .opened_input()
+                    .size()
+                    .map_or("?".to_string(), |size| size.to_string()),
+            }
+        }))
+    }
+
+    /// The right of the status bar: where on the input the screen is, and
+    /// what else is going on.
+    fn status_position(&self) -> String {
+        let (first, last, whole) = self.screen_lines();
+        let lines = whole.unwrap_or_else(|| self.lines_so_far());
+        let opened_input = self.opened_input();
+        let mut position = format!("lines {first}-{last}/{lines}");
+        if let Some(filter) = opened_input.filter().and_then(FilterView::pattern) {
+            position = format!("&{}  {position}", filter.query);
+        }
+        // line numbers are relative to where reading started
+        if opened_input.start() != StartPosition::Beginning {
+            position.push_str(&format!(" from {}", opened_input.start()));
+        }
+        if self.cli.verbose_status {
+            position.push_str(&format!(" byte {}", self.top_byte_offset()));
+            if let Some(size) = opened_input.size() {
+                position.push_str(&format!("/{size}"));
+            }
+        }
+        if whole.is_some() {
+            position.push_str(&format!(" {}%", self.percent()));
+            // a followed file's end moves on as it grows
+            if !self.more_to_come() && last == self.lines_so_far() {
+                position.push_str(" (END)");
+            }
+        } else if !opened_input.reached_eof() {
+            // a stream's end is only known once its writer is done
+            position.push_str(" reading...");
+        }
+        if let Some(matches) = self.match_status() {
+            position = format!("{matches}  {position}");
+        }
+        let indicators = self.indicators();
+        if !indicators.is_empty() {
+            position = format!("{indicators}  {position}");
+        }
+        if self.inputs.len() > 1 {
+            let (current, count) = (self.current_input + 1, self.inputs.len());
+            let unread = self.parked.values().filter(|parked| parked.unread).count();
+            position = match unread {
+                0 => format!("file {current}/{count}  {position}"),
+                _ => format!("file {current}/{count}, {unread} with new lines  {position}"),
+            };
+        }
+        if let Some(auto_scroll) = &self.auto_scroll {
+            position = format!("auto {}ms  {position}", auto_scroll.interval.as_millis());
+        }
+        if let Some(pending) = self.key_state.pending() {
+            position = format!("{pending}  {position}");
+        }
+        position
+    }
+}
+
+#[cfg(test)]
+impl App {
+    /// Presses `code`, as typed on the terminal.
+    fn press(&mut self, code: KeyCode) -> Result<()> {
+        let key = crossterm::event::Event::Key(KeyEvent::from(code));
+        self.update(Event::Term(key))
+    }
+
+    /// Sends `event` as one of the opened input's, as its reader would.
+    fn feed_input(&mut self, event: Event) -> Result<()> {
+        let id = self.opened_input().id();
+        self.update(Event::Input(id, Box::new(event)))
+    }
+
+    /// Draws the screen the way the event loop does, into a buffer the size
+    /// of the terminal.
+    fn draw_to_buffer(&mut self) -> Buffer {
+        let backend =
+            ratatui::backend::TestBackend::new(self.term_size.width, self.term_size.height);
+        let mut terminal = ratatui::Terminal::new(backend).unwrap();
+        terminal.draw(|frame| self.draw(frame)).unwrap();
+        terminal.backend().buffer().clone()
+    }
+}
+
+impl Widget for &mut App {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let (status, bar) = self.bar_rows();
+        let (top_bar, bottom_bar) = match self.cli.position_bar {
+            Some(BarPosition::Top) => (bar, 0),
+            Some(BarPosition::Bottom) => (0, bar),
+            None => (0, 0),
+        };
+        let [
+            top_bar_area,
+            header_area,
+            text_area,
+            bottom_bar_area,
+            status_area,
+        ] = Layout::vertical([
+            Constraint::Length(top_bar),
+            Constraint::Length(self.header_rows() as u16),
+            Constraint::Fill(1),
+            Constraint::Length(bottom_bar),
+            Constraint::Length(status),
+        ])
+        .areas(area);
+        let gutter = Constraint::Length(self.gutter_width() as u16);
+        let [_, header_area] = Layout::horizontal([gutter, Constraint::Fill(1)]).areas(header_area);
+        let [gutter_area, text_area] =
+            Layout::horizontal([gutter, Constraint::Fill(1)]).areas(text_area);
+        if bar > 0 {
+            let bar_area = if top_bar > 0 {
+                top_bar_area
+            } else {
+                bottom_bar_area
+            };
+            // drawn by hand, as ratatui's LineGauge always leaves room for a
+            // label
+            let width = bar_area.width as usize;
+            let filled = (self.progress() * width as f64).round() as usize;
+            Line::from(vec![
+                Span::styled("━".repeat(filled), self.theme.bar_done),
+                Span::styled("─".repeat(width - filled), self.theme.bar_left),
+            ])
+            .render(bar_area, buf);
+        }
+        let current_line = self.current_line;
+        let term_hight = self.term_height();
+        let cursor_line = self.cursor_line();
+        let wrap_width = self.wrap_width();
+        // keep binary garbage off the screen until the user agrees to see it
+        if self.mode != AppMode::BinaryWarning {
+            // the rows of each line, its label and then blanks when wrapped
+            let mut labels: Vec<_> = self
+                .line_labels()
+                .into_iter()
+                .map(|label| vec![label])
+                .collect();
+            let mut opened_input = self.opened_input_mut();
+            let reached_eof = opened_input.reached_eof();
+            let header = opened_input.header(header_area.height as usize, &self.render_options);
+            Paragraph::new(header)
+                .style(self.theme.text)
+                .render(header_area, buf);
+            let mut lines = opened_input
+                .lines(current_line, term_hight, &self.render_options)
+                .unwrap();
+            // rows taken by the cursor line
+            let mut cursor_rows =
+                cursor_line.map(|line| line - current_line..line - current_line + 1);
+            if let Some(width) = wrap_width {
+                let mut wrapped: Vec<_> = lines
+                    .into_iter()
+                    .map(|line| wrap_line(line, width, self.render_options.wrap_indent))
+                    .collect();
+                // a line's number goes on its first row
+                for (label, rows) in labels.iter_mut().zip(&wrapped) {
+                    label.resize(rows.len(), Line::default());
+                }
+                // the top line may be scrolled partway off the screen
+                let skipped = self.skipped_rows();
+                if let Some(top) = wrapped.first_mut() {
+                    top.drain(..min(skipped, top.len().saturating_sub(1)));
+                }
+                if let Some(top) = labels.first_mut() {
+                    top.drain(..min(skipped, top.len().saturating_sub(1)));
+                }
+                cursor_rows = cursor_rows.map(|rows| {
+                    let start = wrapped[..rows.start].iter().map(Vec::len).sum::<usize>();
+                    start..start + wrapped.get(rows.start).map_or(1, Vec::len)
+                });
+                lines = wrapped.into_iter().flatten().collect();
+            } else if let Some(marks) = self.cli.chop_marks {
+                let width = text_area.width as usize;
+                let scrolled = self.render_options.column_offset > 0;
+                lines = lines
+                    .into_iter()
+                    .map(|line| mark_cut_edges(line, width, scrolled, marks))
+                    .collect();
+            }
+            // like vi, mark the rows past the end of the input, but only once
+            // the input is known to have ended
+            if reached_eof && lines.len() < term_hight {
+                lines.resize(term_hight, Line::raw("~").dim());
+            }
+            Paragraph::new(lines)
+                .style(self.theme.text)
+                .render(text_area, buf);
+            Paragraph::new(labels.into_iter().flatten().collect::<Vec<_>>())
+                .style(self.theme.text)
+                .render(gutter_area, buf);
+            if let Some(rows) = cursor_rows {
+                let mut cursor_area = text_area;
+                cursor_area.y += rows.start as u16;
+                cursor_area.height = rows.len() as u16;
+                buf.set_style(cursor_area.intersection(text_area), self.theme.cursor_line);
+            }
+        }
+        self.render_status_bar(status_area, buf);
+        if self.mode == AppMode::Info {
+            let [lines_area, _] =
+                Layout::vertical([Constraint::Fill(1), Constraint::Length(status)]).areas(area);
+            self.render_info(lines_area, buf);
+        }
+        log::trace!("buffer {:?}", buf);
+    }
+}
+
+#[derive(Default, Debug, PartialEq, Eq)]
+pub enum AppMode {
+    #[default]
+    Main,
+    Search,
+    /// Asking for a file to save the buffer to.
+    Save,
+    /// Asking for the pattern to filter lines by.
+    Filter,
+    /// Reading a `:` command.
+    Command,
+    /// Asking for a shell command to pipe the input through.
+    Pipe,
+    /// Asking whether to show an input that looks binary.
+    BinaryWarning,
+    /// Asking whether `q` really meant to quit.
+    QuitConfirmation,
+    /// Showing what is known about the current input, until a key is
+    /// pressed.
+    Info,
+    Terminated,
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        cell::RefCell,
+        ffi::OsString,
+        fs,
+        io::Write,
+        ops::Range,
+        path::Path,
+        sync::{Arc, mpsc},
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use clap::Parser;
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use ratatui::{
+        layout::Size,
+        style::{Color, Modifier},
+    };
+    use signal_hook::consts::SIGTERM;
+
+    use crate::{
+        app::{
+            App, AppMode, BarPosition, Cli, ColorMode, ConfirmQuit, JumpTarget, LineNumbers,
+            StartCommand,
+        },
+        event::Event,
+        input::{Input, OpenedInput},
+        keys::Action,
+        search::{Search, SearchDirection},
+        temp_path::TempPath,
+    };
+
+    fn app_with_lines(lines: &[&str], height: u16) -> App {
+        let (tx, rx) = mpsc::channel();
+        App {
+            opened_input: Some(RefCell::new(OpenedInput::from_lines(lines))),
+            term_size: Size::new(80, height),
+            rx: Some(rx),
+            tx: Some(tx),
+            ..Default::default()
+        }
+    }
+
+    /// Runs the match counter until every row is counted, the way the event
+    /// loop does.
+    fn count_all_matches(app: &mut App) {
+        while app.counting_matches() {
+            app.count_matches();
+            app.handle_events().unwrap();
+            app.jump_to_awaited_match();
+        }
+    }
+
+    fn app(lines: usize, height: u16) -> App {
+        let lines: Vec<String> = (1..=lines).map(|i| i.to_string()).collect();
+        let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+        app_with_lines(&lines, height)
+    }
+
+    /// An app whose input is yet to be read, with the lines sent to it by
+    /// the test.
+    fn streaming_app(height: u16) -> App {
+        App {
+            opened_input: Some(RefCell::new(OpenedInput::pending())),
+            ..app_with_lines(&[], height)
+        }
+    }
+
+    /// The lines at `lines`, each its own number counting from 1, as the
+    /// reader would send them.
+    fn new_lines(lines: Range<usize>) -> Event {
+        let offsets = lines.clone().map(|line| line as u64 * 4).collect();
+        let lines = lines
+            .map(|line| Arc::from((line + 1).to_string()))
+            .collect();
+        Event::NewLines(lines, offsets)
+    }
+
+    /// An app paging the file at `path`, read to its end.
+    fn file_app(path: &Path, cli: Cli, height: u16) -> App {
+        let mut app = App {
+            opened_input: None,
+            inputs: vec![Input::ordinary_file(path)],
+            cli: Cli {
+                no_remember: true,
+                ..cli
+            },
+            ..app(0, height)
+        };
+        app.open_input(app.inputs[0].clone()).unwrap();
+        while !app.opened_input().reached_eof() {
+            app.handle_events().unwrap();
+        }
+        app
+    }
+
+    /// The rows of the screen as drawn, without trailing blanks.
+    fn screen(app: &mut App) -> Vec<String> {
+        let buffer = app.draw_to_buffer();
+        let area = buffer.area;
+        (area.top()..area.bottom())
+            .map(|y| {
+                let row: String = (area.left()..area.right())
+                    .map(|x| buffer[(x, y)].symbol())
+                    .collect();
+                row.trim_end().to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_streamed_input() {
+        let mut app = streaming_app(6);
+        app.feed_input(new_lines(0..2)).unwrap();
+        assert_eq!(screen(&mut app)[..3], ["1", "2", ""]);
+
+        app.feed_input(new_lines(2..100)).unwrap();
+        app.press(KeyCode::Char('G')).unwrap();
+        assert_eq!(app.current_line, 95);
+        assert_eq!(app.mode, AppMode::Main);
+        let rows = screen(&mut app);
+        assert_eq!(rows[..5], ["96", "97", "98", "99", "100"]);
+        assert!(
+            rows[5].ends_with("lines 96-100/100 reading..."),
+            "{}",
+            rows[5]
+        );
+
+        app.feed_input(Event::Eof).unwrap();
+        assert!(screen(&mut app)[5].ends_with("100% (END)"));
+        app.press(KeyCode::Char('q')).unwrap();
+        assert_eq!(app.mode, AppMode::Terminated);
+    }
+
+    #[test]
+    fn test_scroll_while_streaming() {
+        let mut app = streaming_app(6);
+        app.feed_input(new_lines(0..10)).unwrap();
+        // `G` keeps up with the lines read after it
+        app.press(KeyCode::Char('G')).unwrap();
+        assert_eq!(app.current_line, 5);
+        app.feed_input(new_lines(10..20)).unwrap();
+        assert_eq!(app.current_line, 15);
+
+        // `j` at the end waits for the next lines to scroll by
+        app.press(KeyCode::Char('k')).unwrap();
+        app.press(KeyCode::Char('j')).unwrap();
+        app.press(KeyCode::Char('j')).unwrap();
+        app.press(KeyCode::Char('j')).unwrap();
+        assert_eq!(app.current_line, 15);
+        app.feed_input(new_lines(20..21)).unwrap();
+        assert_eq!(app.current_line, 16);
+        app.feed_input(new_lines(21..30)).unwrap();
+        assert_eq!(app.current_line, 17);
+        app.feed_input(new_lines(30..40)).unwrap();
+        assert_eq!(app.current_line, 17);
+
+        // once the input is all read, `G` goes to what is the end for good
+        app.feed_input(Event::Eof).unwrap();
+        app.press(KeyCode::Char('G')).unwrap();
+        assert!(!app.stick_to_tail);
+        app.press(KeyCode::Char('j')).unwrap();
+        assert_eq!((app.current_line, app.owed_scrolls), (35, 0));
+    }
+
+    #[test]
+    fn test_quit_at_eof() {
+        let mut app = streaming_app(6);
+        app.cli.quit_at_eof = true;
+        app.feed_input(new_lines(0..10)).unwrap();
+        app.press(KeyCode::Char('G')).unwrap();
+        app.feed_input(new_lines(10..20)).unwrap();
+        app.feed_input(Event::Eof).unwrap();
+        assert_eq!(app.mode, AppMode::Terminated);
+
+        // not while reading further back
+        let mut app = streaming_app(6);
+        app.cli.quit_at_eof = true;
+        app.feed_input(new_lines(0..10)).unwrap();
+        app.press(KeyCode::Char('G')).unwrap();
+        app.press(KeyCode::Char('k')).unwrap();
+        app.feed_input(Event::Eof).unwrap();
+        assert_eq!(app.mode, AppMode::Main);
+        // until scrolling back down to the end
+        app.press(KeyCode::Char('j')).unwrap();
+        assert_eq!(app.mode, AppMode::Terminated);
+
+        // which asks first, as quitting with `q` would
+        let mut app = streaming_app(6);
+        app.cli.quit_at_eof = true;
+        app.cli.confirm_quit = ConfirmQuit::Always;
+        app.feed_input(new_lines(0..10)).unwrap();
+        app.feed_input(Event::Eof).unwrap();
+        assert_eq!(app.mode, AppMode::Main);
+        app.press(KeyCode::Char('G')).unwrap();
+        assert_eq!(app.mode, AppMode::QuitConfirmation);
+
+        // an input that fits on the screen is on its last screen at once
+        let mut app = streaming_app(6);
+        app.cli.quit_at_eof = true;
+        app.feed_input(new_lines(0..3)).unwrap();
+        app.feed_input(Event::Eof).unwrap();
+        assert_eq!(app.mode, AppMode::Terminated);
+    }
+
+    #[test]
+    fn test_no_end_while_following() {
+        let mut app = streaming_app(6);
+        app.cli.follow_name = true;
+        app.feed_input(Event::LineCount(3)).unwrap();
+        app.feed_input(new_lines(0..3)).unwrap();
+        app.feed_input(Event::Eof).unwrap();
+        assert!(screen(&mut app)[5].ends_with("lines 1-3/3 100%"));
+        app.cli.follow_name = false;
+        assert!(screen(&mut app)[5].ends_with("lines 1-3/3 100% (END)"));
+    }
+
+    #[test]
+    fn test_exit_follow_at_eof() {
+        let follow = |exit_follow_at_eof, quit_at_eof| {
+            let mut app = streaming_app(6);
+            app.cli.follow_name = true;
+            app.cli.exit_follow_at_eof = exit_follow_at_eof;
+            app.cli.quit_at_eof = quit_at_eof;
+            app.feed_input(new_lines(0..10)).unwrap();
+            app.press(KeyCode::Char('G')).unwrap();
+            app.feed_input(Event::Eof).unwrap();
+            app
+        };
+
+        // a followed file is read on past its end, which `G` sticks to
+        let app = follow(false, false);
+        assert!(app.more_to_come());
+        assert!(app.stick_to_tail);
+        assert!(app.indicators().contains("[F]"));
+        // and so doesn't end even with -e
+        assert_eq!(follow(false, true).mode, AppMode::Main);
+
+        let mut app = follow(true, false);
+        assert_eq!(app.mode, AppMode::Main);
+        assert!(!app.more_to_come());
+        assert!(!app.stick_to_tail);
+        assert!(!app.indicators().contains("[F]"));
+        assert_eq!(
+            app.message.take().as_deref(),
+            Some("Reached the end, stopped following")
+        );
+        assert_eq!(follow(true, true).mode, AppMode::Terminated);
+
+        // it only means something while following
+        assert!(Cli::try_parse_from(["least", "--exit-follow-at-eof", "a"]).is_err());
+        assert!(
+            Cli::try_parse_from(["least", "--follow-name", "--exit-follow-at-eof", "a"]).is_ok()
+        );
+    }
+
+    #[test]
+    fn test_max_lines() {
+        let mut app = App {
+            opened_input: Some(RefCell::new(OpenedInput::pending_capped(32))),
+            ..streaming_app(6)
+        };
+        app.feed_input(new_lines(0..30)).unwrap();
+        app.on_action(Action::GoToLine(21));
+        app.submit_search("25".to_string());
+        assert_eq!(app.last_match, Some(24));
+
+        // the oldest lines go a chunk at a time, and the rest move up
+        app.feed_input(new_lines(30..33)).unwrap();
+        assert_eq!(app.opened_input().evicted(), 2);
+        assert_eq!(app.opened_input().lines_read(), 31);
+        assert_eq!((app.current_line, app.last_match), (19, Some(22)));
+        let rows = screen(&mut app);
+        assert_eq!(rows[0], "22");
+        assert!(
+            rows[5].ends_with("lines 22-26/33 reading..."),
+            "{}",
+            rows[5]
+        );
+
+        // lines are numbered as in the whole input
+        app.feed_input(new_lines(33..100)).unwrap();
+        assert_eq!(app.opened_input().evicted(), 68);
+        assert_eq!(app.current_line, 0);
+        app.submit_command("80");
+        assert_eq!(screen(&mut app)[0], "80");
+        app.press(KeyCode::Char('G')).unwrap();
+        app.feed_input(new_lines(100..110)).unwrap();
+        let rows = screen(&mut app);
+        assert_eq!(rows[..5], ["106", "107", "108", "109", "110"]);
+        assert!(
+            rows[5].ends_with("lines 106-110/110 reading..."),
+            "{}",
+            rows[5]
+        );
+    }
+
+    #[test]
+    fn test_toggle_whitespace() {
+        let mut app = app_with_lines(&["a\tb  ", "plain"], 4);
+        assert_eq!(screen(&mut app)[0], format!("a{}b", " ".repeat(7)));
+        app.press(KeyCode::Char('W')).unwrap();
+        assert_eq!(
+            screen(&mut app)[..2],
+            [format!("a→{}b··", " ".repeat(6)), "plain".to_string()]
+        );
+        // the lines themselves are left alone
+        assert_eq!(
+            app.opened_input().row_lines(0..1),
+            [Some(Arc::from("a\tb  "))]
+        );
+        app.press(KeyCode::Char('W')).unwrap();
+        assert_eq!(screen(&mut app)[0], format!("a{}b", " ".repeat(7)));
+    }
+
+    #[test]
+    fn test_toggle_theme() {
+        let mut app = app(2, 3);
+        let buffer = app.draw_to_buffer();
+        assert_eq!(buffer[(0, 0)].fg, Color::White);
+        assert!(buffer[(0, 2)].modifier.contains(Modifier::REVERSED));
+        app.press(KeyCode::Char('T')).unwrap();
+        let buffer = app.draw_to_buffer();
+        assert_eq!(buffer[(0, 0)].fg, Color::Black);
+        assert_eq!(buffer[(0, 2)].fg, Color::Black);
+        assert_ne!(buffer[(0, 2)].bg, Color::Reset);
+    }
+
+    #[test]
+    fn test_draw_past_the_end() {
+        let mut app = app(2, 5);
+        let rows = screen(&mut app);
+        assert_eq!(rows[..4], ["1", "2", "~", "~"]);
+        assert!(rows[4].starts_with("test"));
+    }
+
+    #[test]
+    fn test_end_marks_wait_for_eof() {
+        let mut app = streaming_app(5);
+        app.feed_input(new_lines(0..2)).unwrap();
+        assert_eq!(screen(&mut app)[..4], ["1", "2", "", ""]);
+        app.feed_input(Event::Eof).unwrap();
+        assert_eq!(screen(&mut app)[..4], ["1", "2", "~", "~"]);
+    }
+
+    #[test]
+    fn test_confirm_quit() {
+        let mut pager = app(10, 5);
+        pager.cli.confirm_quit = ConfirmQuit::Always;
+        pager.on_key_event(KeyEvent::from(KeyCode::Char('q')));
+        assert_eq!(pager.mode, AppMode::QuitConfirmation);
+        pager.on_key_event(KeyEvent::from(KeyCode::Char('n')));
+        assert_eq!(pager.mode, AppMode::Main);
+        pager.on_key_event(KeyEvent::from(KeyCode::Char('q')));
+        pager.on_key_event(KeyEvent::from(KeyCode::Char('y')));
+        assert_eq!(pager.mode, AppMode::Terminated);
+
+        let mut pager = app(10, 5);
+        pager.cli.confirm_quit = ConfirmQuit::Stdin;
+        pager.on_key_event(KeyEvent::from(KeyCode::Char('q')));
+        assert_eq!(pager.mode, AppMode::Terminated);
+        let mut pager = app(10, 5);
+        pager.cli.confirm_quit = ConfirmQuit::Always;
+        pager.on_key_event(KeyEvent::from(KeyCode::Char('Q')));
+        assert_eq!(pager.mode, AppMode::Terminated);
+    }
+
+    #[test]
+    fn test_signal_quits() {
+        let mut app = app(10, 5);
+        app.cli.confirm_quit = ConfirmQuit::Always;
+        app.update(Event::Signal(SIGTERM)).unwrap();
+        assert_eq!(app.mode, AppMode::Terminated);
+        assert_eq!(app.exit_signal, Some(SIGTERM));
+    }
+
+    #[test]
+    fn test_cursor() {
+        let mut app = app(10, 5);
+        app.cli.cursor = true;
+        for _ in 0..3 {
+            app.on_action(Action::ScrollDownOneLine);
+        }
+        assert_eq!((app.current_line, app.cursor_line()), (0, Some(3)));
+        // the screen scrolls once the cursor is at the bottom
+        app.on_action(Action::ScrollDownOneLine);
+        assert_eq!((app.current_line, app.cursor_line()), (1, Some(4)));
+        for _ in 0..10 {
+            app.on_action(Action::ScrollDownOneLine);
+        }
+        assert_eq!((app.current_line, app.cursor_line()), (6, Some(9)));
+
+        app.on_action(Action::ScrollUpOneLine);
+        assert_eq!((app.current_line, app.cursor_line()), (6, Some(8)));
+        app.on_action(Action::GoToTop);
+        assert_eq!((app.current_line, app.cursor_line()), (0, Some(2)));
     }
 
-    fn current_max_line(&self) -> usize {
-        let mut opened_input = self.opened_input_mut();
-        opened_input
-            .current_total_lines()
-            .saturating_sub(self.term_height())
+    #[test]
+    fn test_cursor_wrapped() {
+        let long = "x".repeat(160);
+        let mut app = app_with_lines(&["a", &long, &long, "b", "c", "d", "e"], 6);
+        app.wrap = true;
+        app.cli.cursor = true;
+        app.on_action(Action::ScrollDownOneLine);
+        app.on_action(Action::ScrollDownOneLine);
+        // a and two rows for each long line fill the five rows
+        assert_eq!((app.current_line, app.cursor_line()), (0, Some(2)));
+        // so the next line is past the screen, which scrolls to show it
+        app.on_action(Action::ScrollDownOneLine);
+        assert_eq!((app.current_line, app.cursor_line()), (1, Some(3)));
+        assert_eq!(screen(&mut app)[4], "b");
+        // a long line going off the top makes room for more than one
+        app.on_action(Action::ScrollDownOneLine);
+        assert_eq!((app.current_line, app.cursor_line()), (2, Some(4)));
+        assert_eq!(screen(&mut app)[3], "c");
+
+        // the cursor stays on screen as long lines scroll in above it
+        app.on_action(Action::ScrollUpOneLine);
+        app.on_action(Action::ScrollUpOneLine);
+        app.on_action(Action::ScrollUpOneLine);
+        app.on_action(Action::ScrollUpOneLine);
+        assert_eq!((app.current_line, app.cursor_line()), (0, Some(0)));
+        // a row past the screen puts it on the last line that starts on it
+        app.cursor = 4;
+        assert_eq!(app.cursor_line(), Some(2));
     }
 
-    fn go_to_top(&mut self) {
-        self.current_line = 0
+    #[test]
+    fn test_scrolloff() {
+        let mut app = app(20, 7);
+        app.cli.cursor = true;
+        app.cli.scrolloff = 2;
+        for _ in 0..3 {
+            app.on_action(Action::ScrollDownOneLine);
+        }
+        // two of the six rows stay below the cursor
+        assert_eq!((app.current_line, app.cursor_line()), (0, Some(3)));
+        app.on_action(Action::ScrollDownOneLine);
+        assert_eq!((app.current_line, app.cursor_line()), (1, Some(4)));
+
+        // the margin gives way at the end of the input
+        for _ in 0..20 {
+            app.on_action(Action::ScrollDownOneLine);
+        }
+        assert_eq!((app.current_line, app.cursor_line()), (14, Some(19)));
+
+        app.on_action(Action::ScrollUpOneLine);
+        app.on_action(Action::ScrollUpOneLine);
+        app.on_action(Action::ScrollUpOneLine);
+        assert_eq!((app.current_line, app.cursor_line()), (14, Some(16)));
+        app.on_action(Action::ScrollUpOneLine);
+        assert_eq!((app.current_line, app.cursor_line()), (13, Some(15)));
     }
 
-    fn go_to_bottom(&mut self) {
-        self.current_line = self.current_max_line()
+    #[test]
+    fn test_start_command() {
+        assert_eq!(
+            "12".parse::<StartCommand>().unwrap(),
+            StartCommand::Line(12)
+        );
+        assert_eq!(
+            "/a b".parse::<StartCommand>().unwrap(),
+            StartCommand::Search("a b".to_string())
+        );
+        assert!("x".parse::<StartCommand>().is_err());
     }
 
-    fn go_to_line(&mut self, line: usize) {
-        self.current_line = min(line, self.current_max_line())
+    #[test]
+    fn test_awaited_match() {
+        let mut app = app(100, 11);
+        app.search = Some(Search::new("50".to_string(), SearchDirection::Forward));
+        app.awaited_match = Some(1);
+        count_all_matches(&mut app);
+        assert_eq!(app.awaited_match, None);
+        assert_eq!(app.last_match, Some(49));
+        assert_eq!(app.current_line, 46);
     }
-}
 
-impl Widget for &mut App {
-    fn render(self, area: Rect, buf: &mut Buffer) {
-        let current_line = self.current_line;
-        let term_hight = self.term_height();
-        let mut opened_input = self.opened_input_mut();
+    #[test]
+    fn test_search_across_files() {
+        let files = [("a", "x\nhit\ny\n"), ("b", "none\n"), ("c", "1\nhit\n2\n")];
+        let paths: Vec<_> = files
+            .iter()
+            .map(|(name, text)| {
+                let path = TempPath::new(&format!("across-{name}"));
+                fs::write(&path, text).unwrap();
+                path
+            })
+            .collect();
+        let mut app = App {
+            opened_input: None,
+            inputs: paths.iter().map(Input::ordinary_file).collect(),
+            ..app(0, 5)
+        };
+        app.cli.no_remember = true;
+        app.cli.search_across_files = true;
+        app.open_input(app.inputs[0].clone()).unwrap();
+        while !app.opened_input().reached_eof() {
+            app.handle_events().unwrap();
+        }
+        let search = |app: &mut App, key| {
+            app.press(key).unwrap();
+            while app.searched_into.is_some() || !app.opened_input().reached_eof() {
+                app.handle_events().unwrap();
+            }
+            (app.current_input, app.last_match)
+        };
 
-        let lines = opened_input.lines(current_line, term_hight).unwrap();
-        Paragraph::new(lines).white().render(area, buf);
-        log::trace!("buffer {:?}", buf);
+        app.submit_search("hit".to_string());
+        assert_eq!((app.current_input, app.last_match), (0, Some(1)));
+        // on through the file without any
+        assert_eq!(search(&mut app, KeyCode::Char('n')), (2, Some(1)));
+        assert_eq!(
+            app.message.take().as_deref(),
+            Some(&*format!("Search went on into {}", paths[2].display()))
+        );
+        assert_eq!(search(&mut app, KeyCode::Char('n')), (2, Some(1)));
+        assert_eq!(
+            app.message.take().as_deref(),
+            Some("Pattern not found: hit")
+        );
+        // and back to the last match of the first
+        assert_eq!(search(&mut app, KeyCode::Char('N')), (0, Some(1)));
+
+        // a file that can't be opened ends the search there
+        fs::remove_file(&paths[2]).unwrap();
+        assert_eq!(search(&mut app, KeyCode::Char('n')).0, 1);
+        assert_eq!(
+            app.message.take().as_deref(),
+            Some("Pattern not found in the files left")
+        );
     }
-}
 
-#[derive(Default, Debug, PartialEq, Eq)]
-pub enum AppMode {
-    #[default]
-    Main,
-    Search,
-    Help,
-    Terminated,
+    #[test]
+    fn test_go_to_match() {
+        let mut app = app(100, 11);
+        app.submit_command("match 3");
+        assert_eq!(app.message.as_deref(), Some("No previous search pattern"));
+
+        app.search = Some(Search::new("5".to_string(), SearchDirection::Forward));
+        app.submit_command("match 3");
+        count_all_matches(&mut app);
+        app.settle();
+        assert_eq!(app.last_match, Some(24));
+        assert_eq!(app.match_status().unwrap(), "match 3 of 19");
+
+        app.submit_command("match 30");
+        app.settle();
+        assert_eq!(app.last_match, Some(94));
+        assert_eq!(
+            app.message.as_deref(),
+            Some("Only 19 matches, went to the last")
+        );
+    }
+
+    #[test]
+    fn test_scroll_to_horizontal_end() {
+        let long = "x".repeat(30);
+        let mut app = app_with_lines(&["short", &long, "", "y"], 3);
+        app.term_size.width = 10;
+
+        app.on_action(Action::ScrollToHorizontalEnd);
+        assert_eq!(app.render_options.column_offset, 20);
+        app.on_action(Action::ScrollToHorizontalStart);
+        assert_eq!(app.render_options.column_offset, 0);
+
+        // only lines on screen count
+        app.on_action(Action::ScrollDownOneLine);
+        app.on_action(Action::ScrollDownOneLine);
+        app.on_action(Action::ScrollToHorizontalEnd);
+        assert_eq!(app.render_options.column_offset, 0);
+    }
+
+    #[test]
+    fn test_indicators() {
+        let mut app = app(5, 4);
+        assert_eq!(app.indicators(), "");
+        app.on_action(Action::ToggleWrap);
+        app.submit_command("set wholeword");
+        assert_eq!(app.indicators(), "[W][w]");
+        app.submit_filter("3".to_string());
+        assert!(screen(&mut app)[3].ends_with("[W][w][&]  &3  lines 1-1/1 100% (END)"));
+        app.on_action(Action::ToggleWrap);
+        app.submit_filter(String::new());
+        assert_eq!(app.indicators(), "[w]");
+    }
+
+    #[test]
+    fn test_squeeze_blank_lines() {
+        let lines = ["a", "", "", "", "b", "", "c", "d"];
+        let mut app = app_with_lines(&lines, 3);
+        app.current_line = 4;
+        app.submit_command("set squeeze");
+        assert_eq!(app.indicators(), "[s]");
+        assert_eq!(app.current_line, 2);
+        assert_eq!(screen(&mut app)[..2], ["b", ""]);
+        app.on_action(Action::GoToTop);
+        assert_eq!(screen(&mut app)[..2], ["a", ""]);
+        app.on_action(Action::ScrollDownOneLine);
+        assert_eq!(screen(&mut app)[..2], ["", "b"]);
+
+        // filtering squeezes its view too, and clearing the filter keeps
+        // squeezing
+        app.filter_context = 1;
+        app.submit_filter("b".to_string());
+        assert_eq!(app.opened_input().current_total_lines(), 2);
+        app.submit_filter(String::new());
+        assert_eq!(app.opened_input().current_total_lines(), 6);
+
+        app.on_action(Action::GoToBottom);
+        app.submit_command("set nosqueeze");
+        assert_eq!(app.indicators(), "");
+        assert_eq!(app.opened_input().current_total_lines(), 8);
+        assert_eq!(screen(&mut app)[..2], ["c", "d"]);
+    }
+
+    #[test]
+    fn test_whole_word_search() {
+        let lines = ["start", "error", "err: a", "errors", "[err] b", "stderr"];
+        let mut app = app_with_lines(&lines, 3);
+        app.cli.whole_word = true;
+        app.submit_search("err".to_string());
+        assert_eq!(app.last_match, Some(2));
+        app.on_action(Action::SearchNext(1));
+        assert_eq!(app.last_match, Some(4));
+        app.on_action(Action::SearchNext(1));
+        assert_eq!(app.message.as_deref(), Some("Pattern not found: err"));
+        app.on_action(Action::SearchPrev(1));
+        assert_eq!(app.last_match, Some(2));
+        count_all_matches(&mut app);
+        assert_eq!(app.match_status().as_deref(), Some("match 1 of 2"));
+
+        // turning it off counts the longer words again
+        app.submit_command("set nowholeword");
+        app.on_action(Action::SearchNext(1));
+        assert_eq!(app.last_match, Some(3));
+        count_all_matches(&mut app);
+        assert_eq!(app.match_status().as_deref(), Some("match 3 of 5"));
+    }
+
+    #[test]
+    fn test_whole_word_styled_and_filtered() {
+        let lines = ["start", "errors", "\x1b[31merr\x1b[0m: a", "stderr", "err"];
+        let mut app = app_with_lines(&lines, 3);
+        app.cli.whole_word = true;
+        // the escape before the word is no part of it
+        app.submit_search("err".to_string());
+        assert_eq!(app.last_match, Some(2));
+
+        // filtering goes by whole words too
+        app.submit_filter("err".to_string());
+        assert_eq!(app.opened_input().current_total_lines(), 2);
+        assert_eq!(app.opened_input().line_index(1), Some(4));
+    }
+
+    #[test]
+    fn test_search_styled_text() {
+        let lines = [
+            "start",
+            "\x1b[31mre\x1b[0md",
+            "N\x08NA\x08AM\x08ME\x08E",
+            "NAME",
+        ];
+        let mut app = app_with_lines(&lines, 3);
+        // the text is matched as shown, not the bytes styling it
+        app.submit_search("NAME".to_string());
+        assert_eq!(app.last_match, Some(2));
+        app.on_action(Action::GoToTop);
+        app.submit_search("31m".to_string());
+        assert_eq!(app.last_match, None);
+        app.submit_search("red".to_string());
+        assert_eq!(app.last_match, Some(1));
+        count_all_matches(&mut app);
+        assert_eq!(app.match_status().as_deref(), Some("match 1 of 1"));
+
+        app.submit_filter("NAME".to_string());
+        assert_eq!(app.opened_input().current_total_lines(), 2);
+    }
+
+    #[test]
+    fn test_search_scrolls_to_match() {
+        let far = format!("{}needle{}", "x".repeat(100), "y".repeat(50));
+        let near = format!("ab\tneedle{}", "z".repeat(100));
+        let mut app = app_with_lines(&["start", &far, &near, "needle"], 3);
+        app.term_size.width = 20;
+        app.submit_search("needle".to_string());
+        assert_eq!(app.last_match, Some(1));
+        assert_eq!(app.render_options.column_offset, 86);
+        assert_eq!(screen(&mut app)[1], format!("{}needle", "x".repeat(14)));
+
+        // going back left lands on the hit's first column, past the tab
+        app.on_action(Action::SearchNext(1));
+        assert_eq!(app.last_match, Some(2));
+        assert_eq!(app.render_options.column_offset, 8);
+        assert!(screen(&mut app)[1].starts_with("needlez"));
+
+        // a hit already on screen doesn't scroll
+        app.on_action(Action::GoToTop);
+        app.submit_search("needle".to_string());
+        app.render_options.column_offset = 2;
+        app.on_action(Action::SearchNext(1));
+        assert_eq!(app.last_match, Some(2));
+        assert_eq!(app.render_options.column_offset, 2);
+
+        // wrapped lines show the whole hit anyway
+        app.on_action(Action::ToggleWrap);
+        app.on_action(Action::GoToTop);
+        app.submit_search("needle".to_string());
+        assert_eq!(app.render_options.column_offset, 0);
+    }
+
+    #[test]
+    fn test_chop_marks() {
+        let far = format!("{}needle{}", "x".repeat(100), "y".repeat(50));
+        let mut app = app_with_lines(&["start", &far, "short"], 4);
+        app.term_size.width = 20;
+        app.cli.chop_marks = Some("<>".parse().unwrap());
+        assert_eq!(screen(&mut app)[1], format!("{}>", "x".repeat(19)));
+        // a hit scrolled to stays clear of the marks on both sides
+        app.submit_search("needle".to_string());
+        let rows = screen(&mut app);
+        assert_eq!(rows[1], format!("<{}needle>", "x".repeat(12)));
+        // lines scrolled past whole have nothing left to mark
+        assert_eq!(rows[0], "");
+        app.render_options.column_offset = 1;
+        assert_eq!(screen(&mut app)[0], "<art");
+    }
+
+    #[test]
+    fn test_line_numbers() {
+        let mut app = app(20, 4);
+        app.line_numbers = Some(LineNumbers::Absolute);
+        app.current_line = 2;
+        assert_eq!(screen(&mut app)[..3], ["  3 3", "  4 4", "  5 5"]);
+        app.press(KeyCode::Char('#')).unwrap();
+        assert_eq!(screen(&mut app)[..3], ["  3 3", "  1 4", "  2 5"]);
+        // counted from the cursor line, which has its own number
+        app.cli.cursor = true;
+        app.cursor = 1;
+        assert_eq!(screen(&mut app)[..3], ["  1 3", "  4 4", "  1 5"]);
+        app.press(KeyCode::Char('#')).unwrap();
+        assert_eq!(app.line_numbers, Some(LineNumbers::Absolute));
+
+        // only the first row of a wrapped line is numbered, and the text
+        // wraps beside the numbers
+        let long = "x".repeat(20);
+        let mut app = app_with_lines(&[&long, "a"], 4);
+        app.term_size.width = 14;
+        app.wrap = true;
+        app.line_numbers = Some(LineNumbers::Absolute);
+        let rows = screen(&mut app);
+        assert_eq!(
+            rows[..3],
+            [
+                format!("  1 {}", "x".repeat(10)),
+                format!("    {}", "x".repeat(10)),
+                "  2 a".to_string(),
+            ]
+        );
+        let numbers = |args: &[&str]| Cli::try_parse_from(args).unwrap().line_numbers;
+        assert_eq!(numbers(&["least", "a"]), None);
+        assert_eq!(numbers(&["least", "-N", "a"]), Some(LineNumbers::Absolute));
+        assert_eq!(
+            numbers(&["least", "--line-numbers=relative", "a"]),
+            Some(LineNumbers::Relative)
+        );
+    }
+
+    #[test]
+    fn test_tiny_terminals() {
+        let long = "x".repeat(30);
+        let lines = ["head", &long, "b", "c", "d"];
+        let tiny = |width, height| {
+            let mut app = app_with_lines(&lines, height);
+            app.term_size.width = width;
+            app
+        };
+
+        // the one row goes to the lines, without the status bar
+        let mut app = tiny(1, 1);
+        assert_eq!(screen(&mut app), ["h"]);
+        app.press(KeyCode::Char('j')).unwrap();
+        assert_eq!(screen(&mut app), ["x"]);
+        app.press(KeyCode::Char('d')).unwrap();
+        assert_eq!(app.current_line, 2);
+        // unless typing into a prompt
+        app.press(KeyCode::Char('/')).unwrap();
+        assert_eq!(screen(&mut app), ["/"]);
+
+        // and with two the status bar has the second
+        let mut app = tiny(2, 2);
+        assert_eq!(screen(&mut app)[0], "he");
+        // the position bar, header and line numbers don't fit either
+        app.cli.position_bar = Some(BarPosition::Top);
+        app.cli.header = 1;
+        app.line_numbers = Some(LineNumbers::Absolute);
+        app.press(KeyCode::Char('j')).unwrap();
+        assert_eq!(screen(&mut app)[0], "xx");
+        app.wrap = true;
+        app.cli.chop_marks = Some("<>".parse().unwrap());
+        app.press(KeyCode::Char('G')).unwrap();
+        assert_eq!(screen(&mut app)[0], "d");
+        app.wrap = false;
+        app.press(KeyCode::Right).unwrap();
+        // scrolled into the long line, both chop marks take its two columns
+        for _ in 0..3 {
+            app.press(KeyCode::Char('k')).unwrap();
+        }
+        assert_eq!(app.current_line, 1);
+        assert_eq!(screen(&mut app)[0], "<>");
+
+        // nothing at all to draw on in the middle of a resize
+        let mut app = tiny(0, 0);
+        app.cli.cursor = true;
+        for key in ['j', 'k', 'G', 'g', 'd', 'f', 'w'] {
+            app.press(KeyCode::Char(key)).unwrap();
+        }
+        assert!(screen(&mut app).is_empty());
+    }
+
+    #[test]
+    fn test_search_scroll_leaves_cut_mark() {
+        let far = format!("{}needle{}", "x".repeat(100), "y".repeat(50));
+        let mut app = app_with_lines(&["start", &far], 3);
+        app.term_size.width = 30;
+        app.render_options.max_columns = Some(20);
+        app.submit_search("needle".to_string());
+        assert_eq!(app.render_options.column_offset, 87);
+        assert_eq!(screen(&mut app)[1], format!("{}needle…", "x".repeat(13)));
+    }
+
+    #[test]
+    fn test_search_visits_adjacent_matches() {
+        let lines = ["x", "x", "x", "a", "b", "c", "d", "e", "x", "x"];
+        let mut app = app_with_lines(&lines, 4);
+        app.search = Some(Search::new("x".to_string(), SearchDirection::Forward));
+
+        // the top line isn't a hit of its own
+        let mut hits = Vec::new();
+        for _ in 0..4 {
+            app.on_action(Action::SearchNext(1));
+            hits.push(app.last_match.unwrap());
+        }
+        assert_eq!(hits, [1, 2, 8, 9]);
+        app.on_action(Action::SearchNext(1));
+        assert_eq!(app.last_match, Some(9));
+        assert_eq!(app.message.as_deref(), Some("Pattern not found: x"));
+
+        app.on_action(Action::SearchPrev(1));
+        assert_eq!(app.last_match, Some(8));
+
+        count_all_matches(&mut app);
+        assert_eq!(app.match_status().as_deref(), Some("match 4 of 5"));
+
+        app.cli.search_wrap = true;
+        app.on_action(Action::SearchNext(1));
+        app.on_action(Action::SearchNext(1));
+        assert_eq!(app.last_match, Some(0));
+        assert_eq!(app.message.as_deref(), Some("Search wrapped to the top"));
+    }
+
+    #[test]
+    fn test_jump_target() {
+        // 11 rows of text above the status bar
+        let mut app = app(100, 12);
+        app.cli.jump_target = JumpTarget::Center;
+        app.submit_search("50".to_string());
+        assert_eq!(app.current_line, 44);
+        assert_eq!(screen(&mut app)[5], "50");
+
+        app.cli.jump_target = JumpTarget::Top;
+        app.submit_search("60".to_string());
+        assert_eq!(app.current_line, 59);
+        app.cli.jump_target = JumpTarget::Context(2);
+        app.submit_search("70".to_string());
+        assert_eq!(app.current_line, 67);
+    }
+
+    #[test]
+    fn test_jump_target_wrapped() {
+        let long = "x".repeat(400);
+        let mut lines = vec!["a"; 20];
+        lines[8] = &long;
+        lines[9] = &long;
+        lines[10] = "hit";
+        let mut app = app_with_lines(&lines, 12);
+        app.wrap = true;
+        // the two long lines above take 10 rows, so only one fits above
+        // the middle row and the hit stays on screen
+        app.cli.jump_target = JumpTarget::Center;
+        app.submit_search("hit".to_string());
+        assert_eq!(app.current_line, 9);
+        assert_eq!(screen(&mut app)[5], "hit");
+
+        app.cli.jump_target = JumpTarget::Context(2);
+        app.on_action(Action::GoToTop);
+        app.submit_search("hit".to_string());
+        assert_eq!(app.current_line, 10);
+    }
+
+    #[test]
+    fn test_counted_search_repeat() {
+        let lines: Vec<String> = (0..30)
+            .map(|i| {
+                if i % 3 == 0 {
+                    "hit".to_string()
+                } else {
+                    i.to_string()
+                }
+            })
+            .collect();
+        let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+        let mut app = app_with_lines(&lines, 4);
+        app.search = Some(Search::new("hit".to_string(), SearchDirection::Forward));
+
+        // 3n goes three matches on, 12n counts past the digit
+        app.press(KeyCode::Char('3')).unwrap();
+        app.press(KeyCode::Char('n')).unwrap();
+        assert_eq!(app.last_match, Some(9));
+        app.press(KeyCode::Char('2')).unwrap();
+        app.press(KeyCode::Char('N')).unwrap();
+        assert_eq!(app.last_match, Some(3));
+        app.press(KeyCode::Char('1')).unwrap();
+        app.press(KeyCode::Char('2')).unwrap();
+        app.press(KeyCode::Char('n')).unwrap();
+        assert_eq!(app.last_match, Some(27));
+        assert_eq!(app.message.as_deref(), Some("Only 8 of 12 matches found"));
+        app.press(KeyCode::Char('n')).unwrap();
+        assert_eq!(app.last_match, Some(27));
+        assert_eq!(app.message.as_deref(), Some("Pattern not found: hit"));
+    }
+
+    #[test]
+    fn test_esc_cancels_pending_command() {
+        let mut app = app(100, 11);
+        app.press(KeyCode::Char('g')).unwrap();
+        app.press(KeyCode::Char('5')).unwrap();
+        app.press(KeyCode::Esc).unwrap();
+        assert_eq!(app.mode, AppMode::Main);
+        // Enter scrolls again rather than ending the abandoned g5
+        app.press(KeyCode::Enter).unwrap();
+        assert_eq!(app.current_line, 1);
+
+        app.press(KeyCode::Char('3')).unwrap();
+        app.press(KeyCode::Esc).unwrap();
+        app.press(KeyCode::Char('j')).unwrap();
+        assert_eq!(app.current_line, 2);
+        // with nothing pending Esc still quits
+        app.press(KeyCode::Esc).unwrap();
+        assert_eq!(app.mode, AppMode::Terminated);
+    }
+
+    #[test]
+    fn test_pending_keys_in_status_bar() {
+        let mut app = app(100, 5);
+        app.press(KeyCode::Char('1')).unwrap();
+        app.press(KeyCode::Char('2')).unwrap();
+        assert!(screen(&mut app)[4].ends_with(":12  lines 1-4/100 4%"));
+        app.press(KeyCode::Char('g')).unwrap();
+        assert!(screen(&mut app)[4].ends_with(":g  lines 1-4/100 4%"));
+        app.press(KeyCode::Char('7')).unwrap();
+        assert!(screen(&mut app)[4].contains(":g7  "));
+        app.press(KeyCode::Enter).unwrap();
+        assert!(!screen(&mut app)[4].contains(':'));
+    }
+
+    #[test]
+    fn test_refresh() {
+        let mut app = app(100, 5);
+        let ctrl_l = KeyEvent::new(KeyCode::Char('l'), KeyModifiers::CONTROL);
+        app.update(Event::Term(crossterm::event::Event::Key(ctrl_l)))
+            .unwrap();
+        assert!(app.force_clear);
+        assert_eq!(app.current_line, 0);
+
+        app.force_clear = false;
+        app.press(KeyCode::Char('r')).unwrap();
+        assert!(app.force_clear);
+    }
+
+    #[test]
+    fn test_seek() {
+        let path = TempPath::new("seek");
+        fs::write(&path, "aaa\nbbb\nccc\nddd\n").unwrap();
+        let cli = Cli {
+            seek_percent: Some(50),
+            ..Default::default()
+        };
+        let mut app = file_app(&path, cli, 5);
+        let rows = screen(&mut app);
+        assert_eq!(rows[..3], ["ccc", "ddd", "~"]);
+        assert!(rows[4].ends_with("lines 1-2/2 from 50% 100% (END)"));
+
+        // in the middle of a line, reading starts at the next one
+        let cli = Cli {
+            seek: Some(5),
+            ..Default::default()
+        };
+        let mut app = file_app(&path, cli, 5);
+        assert_eq!(screen(&mut app)[0], "ccc");
+    }
+
+    #[test]
+    fn test_auto_scroll() {
+        let mut app = app(100, 5);
+        app.press(KeyCode::Char('a')).unwrap();
+        let interval = |app: &App| app.auto_scroll.as_ref().map(|a| a.interval.as_millis());
+        assert_eq!(interval(&app), Some(500));
+        app.press(KeyCode::Char('+')).unwrap();
+        assert_eq!(interval(&app), Some(250));
+        app.press(KeyCode::Char('-')).unwrap();
+        app.press(KeyCode::Char('-')).unwrap();
+        assert_eq!(interval(&app), Some(1000));
+        assert!(screen(&mut app)[4].contains("auto 1000ms"));
+
+        // a tick due with no other event scrolls a line
+        app.auto_scroll.as_mut().unwrap().next_tick = Instant::now();
+        app.handle_events().unwrap();
+        assert_eq!(app.current_line, 1);
+
+        // any other key stops it
+        app.press(KeyCode::Char('j')).unwrap();
+        assert!(app.auto_scroll.is_none());
+        assert_eq!(app.current_line, 2);
+    }
+
+    #[test]
+    fn test_scroll_amount() {
+        let mut app = app(100, 11);
+        app.press(KeyCode::Char('d')).unwrap();
+        assert_eq!(app.current_line, 5);
+
+        // the count sticks for later d and u
+        app.press(KeyCode::Char('3')).unwrap();
+        app.press(KeyCode::Char('d')).unwrap();
+        assert_eq!(app.current_line, 8);
+        app.press(KeyCode::Char('d')).unwrap();
+        assert_eq!(app.current_line, 11);
+        app.press(KeyCode::Char('u')).unwrap();
+        assert_eq!(app.current_line, 8);
+        app.press(KeyCode::Char('1')).unwrap();
+        app.press(KeyCode::Char('u')).unwrap();
+        app.press(KeyCode::Char('u')).unwrap();
+        assert_eq!(app.current_line, 6);
+    }
+
+    #[test]
+    fn test_go_to_line_is_one_based() {
+        let mut app = app(100, 11);
+
+        app.on_action(Action::GoToLine(1));
+        assert_eq!(app.current_line, 0);
+        app.on_action(Action::GoToLine(5));
+        assert_eq!(app.current_line, 4);
+        app.on_action(Action::GoToLine(0));
+        assert_eq!(app.current_line, 0);
+
+        // the last screen can't scroll past the end
+        app.on_action(Action::GoToLine(100));
+        assert_eq!(app.current_line, 90);
+    }
+
+    #[test]
+    fn test_repeat() {
+        let mut app = app(100, 11);
+
+        // nothing to repeat yet
+        app.on_action(Action::Repeat(1));
+        assert_eq!(app.current_line, 0);
+
+        app.on_action(Action::ScrollDownHalfScreen(Some(3)));
+        app.on_action(Action::Repeat(2));
+        assert_eq!(app.current_line, 9);
+
+        // toggles and jumps aren't repeated
+        app.on_action(Action::ToggleWrap);
+        app.on_action(Action::GoToTop);
+        app.on_action(Action::Repeat(1));
+        assert_eq!(app.current_line, 3);
+    }
+
+    #[test]
+    fn test_edit_file() {
+        let mut app = app(10, 5);
+        app.cli.no_remember = true;
+
+        app.submit_command("e /nonexistent/file");
+        assert!(app.message.take().unwrap().starts_with("Cannot open"));
+        let dir = std::env::temp_dir();
+        app.submit_command(&format!("e {}", dir.display()));
+        assert_eq!(
+            app.message.take(),
+            Some(format!("{} is a directory", dir.display()))
+        );
+        app.submit_command("x");
+        assert_eq!(app.message.take().as_deref(), Some("Unknown command: x"));
+        assert_eq!(app.opened_input().name(), "test");
+
+        let path = TempPath::new("edit");
+        fs::write(&path, "a\nb\n").unwrap();
+        app.current_line = 5;
+        app.submit_command(&format!("e {}", path.display()));
+        while !app.opened_input().reached_eof() {
+            app.handle_events().unwrap();
+        }
+        assert_eq!(app.opened_input().name(), path.to_string_lossy());
+        assert_eq!(app.opened_input().current_total_lines(), 2);
+        assert_eq!(app.current_line, 0);
+        assert_eq!(app.inputs[app.current_input].path(), Some(&*path));
+    }
+
+    #[test]
+    fn test_pageable() {
+        let mut app = app(1, 5);
+        let dir = std::env::temp_dir();
+        let inputs = vec![
+            Input::ordinary_file("/nonexistent/file"),
+            Input::ordinary_file("Cargo.toml"),
+            Input::ordinary_file(&dir),
+        ];
+        let inputs = app.pageable(inputs).unwrap();
+        assert_eq!(inputs.len(), 1);
+        assert_eq!(inputs[0].path(), Some(Path::new("Cargo.toml")));
+        let message = app.message.take().unwrap();
+        assert!(message.starts_with("Cannot open /nonexistent/file"));
+        assert!(message.ends_with("and 1 more skipped"));
+
+        let inputs = vec![Input::ordinary_file(&dir)];
+        let err = app.pageable(inputs).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("no files to page: {} is a directory", dir.display())
+        );
+    }
+
+    #[test]
+    fn test_file_list() {
+        let list = TempPath::new("list");
+        fs::write(&list, "a.txt\n# not a file\n\n  b c.txt  \n").unwrap();
+        let mut app = app(1, 5);
+        app.cli.files = vec!["first".into(), format!("@{}", list.display()).into()];
+        let inputs = app.inputs().unwrap();
+        let paths: Vec<_> = inputs.iter().map(|input| input.path().unwrap()).collect();
+        assert_eq!(
+            paths,
+            [Path::new("first"), Path::new("a.txt"), Path::new("b c.txt")]
+        );
+        fs::remove_file(&list).unwrap();
+
+        let err = app.inputs().unwrap_err().to_string();
+        assert!(err.starts_with(&format!("failed to read file list '{}'", list.display())));
+    }
+
+    #[test]
+    fn test_save() {
+        let path = TempPath::new("save");
+        let mut app = app_with_lines(&["one", "two"], 5);
+        app.press(KeyCode::Char('s')).unwrap();
+        assert!(screen(&mut app)[4].starts_with("Save to:"));
+        for c in path.to_str().unwrap().chars() {
+            app.press(KeyCode::Char(c)).unwrap();
+        }
+        app.press(KeyCode::Enter).unwrap();
+        assert_eq!(
+            app.message,
+            Some(format!("Saved 2 lines to {}", path.display()))
+        );
+        assert_eq!(fs::read_to_string(&path).unwrap(), "one\ntwo\n");
+
+        app.save_buffer("/nonexistent/dir/file");
+        assert!(
+            app.message
+                .unwrap()
+                .starts_with("Failed to save to /nonexistent/dir/file")
+        );
+    }
+
+    #[test]
+    fn test_next_unread_file() {
+        let paths: Vec<_> = ["a", "b"]
+            .map(|name| TempPath::new(&format!("unread-{name}")))
+            .into();
+        for path in &paths {
+            fs::write(path, "1\n2\n").unwrap();
+        }
+        let mut app = App {
+            opened_input: None,
+            inputs: paths.iter().map(Input::ordinary_file).collect(),
+            ..app(0, 5)
+        };
+        app.cli.follow_name = true;
+        app.cli.no_remember = true;
+        app.open_input(app.inputs[0].clone()).unwrap();
+        while !app.opened_input().reached_eof() {
+            app.handle_events().unwrap();
+        }
+        app.current_line = 1;
+        app.on_action(Action::NextUnreadFile);
+        assert_eq!(
+            app.message.take().as_deref(),
+            Some("No other file has new lines")
+        );
+        app.submit_command("n");
+        assert_eq!(app.current_input, 1);
+
+        fs::OpenOptions::new()
+            .append(true)
+            .open(&paths[0])
+            .unwrap()
+            .write_all(b"3\n")
+            .unwrap();
+        while !app.parked.values().any(|parked| parked.unread) {
+            app.handle_events().unwrap();
+        }
+        app.on_action(Action::PrevUnreadFile);
+        assert_eq!(app.current_input, 0);
+        // the file is shown as it was left, and has been read on since
+        assert_eq!(app.current_line, 1);
+        assert_eq!(app.opened_input().current_total_lines(), 3);
+        assert!(app.parked.contains_key(&app.inputs[1]));
+    }
+
+    #[test]
+    fn test_reader_failure() {
+        let path = TempPath::new("missing");
+        let mut app = App {
+            opened_input: None,
+            inputs: vec![Input::ordinary_file(&path)],
+            ..app(0, 5)
+        };
+        app.cli.no_remember = true;
+        app.open_input(app.inputs[0].clone()).unwrap();
+        // the failure is shown, with the input still there to page
+        while app.message.is_none() {
+            app.handle_events().unwrap();
+        }
+        assert!(app.message.as_deref().unwrap().contains("No such file"));
+        assert_eq!(app.opened_input().current_total_lines(), 0);
+        app.press(KeyCode::Char('j')).unwrap();
+        assert_eq!(app.mode, AppMode::Main);
+    }
+
+    #[test]
+    fn test_exit_follow_with_files_parked() {
+        let paths: Vec<_> = ["a", "b"]
+            .map(|name| TempPath::new(&format!("unfollow-{name}")))
+            .into();
+        for path in &paths {
+            fs::write(path, "1\n2\n").unwrap();
+        }
+        let mut app = App {
+            opened_input: None,
+            inputs: paths.iter().map(Input::ordinary_file).collect(),
+            ..app(0, 5)
+        };
+        app.cli.follow_name = true;
+        app.cli.no_remember = true;
+        app.open_input(app.inputs[0].clone()).unwrap();
+        while !app.opened_input().reached_eof() {
+            app.handle_events().unwrap();
+        }
+        app.current_line = 1;
+        app.submit_command("n");
+        assert!(app.parked.contains_key(&app.inputs[0]));
+
+        // the end of the second file stops following both
+        app.cli.exit_follow_at_eof = true;
+        while !app.opened_input().reached_eof() {
+            app.handle_events().unwrap();
+        }
+        assert!(!app.cli.follow_name);
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !app.parked[&app.inputs[0]].opened.reader_finished() {
+            assert!(
+                Instant::now() < deadline,
+                "the parked reader is still following"
+            );
+            thread::sleep(Duration::from_millis(10));
+        }
+        // and the first is still there to go back to as it was left
+        app.submit_command("p");
+        assert_eq!(app.current_input, 0);
+        assert_eq!(app.current_line, 1);
+        assert_eq!(app.opened_input().current_total_lines(), 2);
+    }
+
+    #[test]
+    fn test_commands() {
+        let mut app = app(100, 11);
+
+        app.submit_command("42");
+        assert_eq!(app.current_line, 41);
+        let wrap = app.wrap;
+        app.submit_command("set wrap");
+        assert!(app.wrap);
+        app.submit_command("set nowrap");
+        assert!(!app.wrap);
+        app.submit_command("set wrap=maybe");
+        assert!(app.message.take().is_some());
+        app.submit_command(&format!("set wrap={}", if wrap { "on" } else { "off" }));
+        assert_eq!(app.wrap, wrap);
+        app.submit_command("set scrolloff=3");
+        assert_eq!(app.cli.scrolloff, 3);
+        app.submit_command("n");
+        assert_eq!(app.message.take().as_deref(), Some("No next file"));
+        app.submit_command("p");
+        assert_eq!(app.message.take().as_deref(), Some("No previous file"));
+        app.submit_command("q");
+        assert_eq!(app.mode, AppMode::Terminated);
+    }
+
+    #[test]
+    fn test_scroll_wrapped_rows() {
+        let long = "x".repeat(800);
+        let mut app = app_with_lines(&[&long, "a", "b"], 6);
+        app.wrap = true;
+        let position = |app: &App| (app.current_line, app.skipped_rows());
+
+        // the long line takes 10 rows, and the screen 5
+        for rows in 1..=7 {
+            app.on_action(Action::ScrollDownOneLine);
+            assert_eq!(position(&app), (0, rows));
+        }
+        // the rest of the line and the two after it just fill the screen
+        app.on_action(Action::ScrollDownOneLine);
+        assert_eq!(position(&app), (0, 7));
+        app.on_action(Action::ScrollUpOneLine);
+        assert_eq!(position(&app), (0, 6));
+
+        app.on_action(Action::GoToLine(2));
+        assert_eq!(position(&app), (1, 0));
+        app.on_action(Action::ScrollUpOneLine);
+        assert_eq!(position(&app), (0, 9));
+        app.on_action(Action::Repeat(2));
+        assert_eq!(position(&app), (0, 7));
+        // other moves show the top line in full
+        app.on_action(Action::GoToTop);
+        assert_eq!(position(&app), (0, 0));
+    }
+
+    #[test]
+    fn test_scroll_wrapped_screens() {
+        let long = "x".repeat(800);
+        let mut app = app_with_lines(&["a", &long, "b", "c", "d", "e", "f"], 6);
+        app.wrap = true;
+        let position = |app: &App| (app.current_line, app.skipped_rows());
+
+        // the screen of 5 rows moves by 4, into the long line's 10 rows
+        app.on_action(Action::ScrollDownScreen);
+        assert_eq!(position(&app), (1, 3));
+        assert_eq!(screen(&mut app)[0], "x".repeat(80));
+        // the last row of one screen is the first of the next
+        let last = screen(&mut app)[4].clone();
+        app.on_action(Action::ScrollDownScreen);
+        assert_eq!(position(&app), (1, 7));
+        assert_eq!(screen(&mut app)[0], last);
+        // the end of the input stops it short
+        app.on_action(Action::ScrollDownScreen);
+        assert_eq!(position(&app), (2, 0));
+        assert_eq!(screen(&mut app)[..5], ["b", "c", "d", "e", "f"]);
+
+        app.on_action(Action::ScrollUpScreen);
+        assert_eq!(position(&app), (1, 6));
+        app.on_action(Action::ScrollUpScreen);
+        app.on_action(Action::ScrollUpScreen);
+        assert_eq!(position(&app), (0, 0));
+    }
+
+    #[test]
+    fn test_wrapped_progress() {
+        let long = "x".repeat(800);
+        let mut app = app_with_lines(&[&long, "a", "b"], 6);
+        assert_eq!(app.progress(), 1.0);
+        // 5 of the 12 rows the lines wrap to are on the screen
+        app.wrap = true;
+        assert_eq!(app.progress(), 5.0 / 12.0);
+        app.on_action(Action::ScrollDownOneLine);
+        assert_eq!(app.progress(), 6.0 / 12.0);
+        app.on_action(Action::GoToBottom);
+        assert_eq!(app.progress(), 1.0);
+
+        // a long input is counted a chunk at a time, by lines until done
+        let long = "x".repeat(160);
+        let mut lines = vec!["a"; super::ROW_COUNT_CHUNK + 10];
+        lines[0] = &long;
+        let mut app = app_with_lines(&lines, 6);
+        app.wrap = true;
+        let total = lines.len() as f64;
+        assert_eq!(app.progress(), 5.0 / total);
+        assert!(app.counting_rows());
+        assert_eq!(app.progress(), 5.0 / (total + 1.0));
+        assert!(!app.counting_rows());
+    }
+
+    #[test]
+    fn test_wrap_width() {
+        let long = "x".repeat(100);
+        let lines: Vec<&str> = std::iter::repeat_n(long.as_str(), 10).collect();
+        let mut app = app_with_lines(&lines, 11);
+        app.wrap = true;
+        // each line takes 2 rows at the width of the terminal
+        assert_eq!(app.current_max_line(), 5);
+        app.cli.wrap_width = Some(20);
+        assert_eq!(app.wrap_width(), Some(20));
+        assert_eq!(app.current_max_line(), 8);
+        assert_eq!(screen(&mut app)[..2], ["x".repeat(20), "x".repeat(20)]);
+        // a narrower terminal still wins
+        app.term_size.width = 10;
+        assert_eq!(app.wrap_width(), Some(10));
+    }
+
+    #[test]
+    fn test_color() {
+        let new = |color: &str| {
+            let cli = Cli::try_parse_from(["least", "--color", color, "Cargo.toml"]).unwrap();
+            App {
+                term_size: Size::new(80, 3),
+                ..App::new(cli).unwrap()
+            }
+        };
+        let mut app = App {
+            opened_input: Some(RefCell::new(OpenedInput::from_lines(&[
+                "\x1b[31mred\x1b[0m",
+            ]))),
+            ..new("always")
+        };
+        assert_eq!(screen(&mut app)[0], "red");
+        assert_eq!(app.draw_to_buffer()[(0, 0)].fg, Color::Red);
+
+        let mut app = App {
+            opened_input: Some(RefCell::new(OpenedInput::from_lines(&[
+                "\x1b[31mred\x1b[0m",
+            ]))),
+            ..new("never")
+        };
+        assert_eq!(screen(&mut app)[0], "^[[31mred^[[0m");
+    }
+
+    #[test]
+    fn test_color_env() {
+        let auto = |vars: &[(&str, &str)], terminal| {
+            let var = |name: &str| {
+                vars.iter()
+                    .find(|(var, _)| *var == name)
+                    .map(|(_, value)| OsString::from(value))
+            };
+            ColorMode::auto_enabled(var, terminal)
+        };
+        assert!(auto(&[], true));
+        assert!(!auto(&[], false));
+        assert!(!auto(&[("NO_COLOR", "1")], true));
+        // an empty NO_COLOR doesn't count
+        assert!(auto(&[("NO_COLOR", "")], true));
+        assert!(!auto(&[("CLICOLOR", "0")], true));
+        assert!(auto(&[("CLICOLOR", "1")], true));
+        assert!(auto(&[("CLICOLOR_FORCE", "1")], false));
+        assert!(auto(&[("CLICOLOR_FORCE", "1"), ("CLICOLOR", "0")], false));
+        assert!(!auto(&[("CLICOLOR_FORCE", "0")], false));
+        assert!(!auto(&[("CLICOLOR_FORCE", "1"), ("NO_COLOR", "1")], true));
+    }
+
+    #[test]
+    fn test_wrap_flags() {
+        let new = |args: &[&str]| {
+            let args = ["least"].iter().chain(args).chain(&["Cargo.toml"]);
+            Cli::try_parse_from(args).map(|cli| App::new(cli).unwrap().wrap)
+        };
+        assert!(!new(&[]).unwrap());
+        assert!(new(&["--wrap"]).unwrap());
+        assert!(!new(&["-S"]).unwrap());
+        // the last one given wins, so the command line can undo LEAST_OPTS
+        assert!(!new(&["--wrap", "--chop"]).unwrap());
+        assert!(new(&["-S", "--wrap"]).unwrap());
+    }
+
+    #[test]
+    fn test_ignore_case_and_tabs_flags() {
+        let parse = |args: &[&str]| {
+            let args = ["least"].iter().chain(args).chain(&["Cargo.toml"]);
+            let app = App::new(Cli::try_parse_from(args).unwrap()).unwrap();
+            (app.cli.ignore_case, app.render_options.tab_width)
+        };
+        assert_eq!(parse(&[]), (false, 8));
+        assert_eq!(parse(&["-i", "--tabs", "4"]), (true, 4));
+        assert!(Cli::try_parse_from(["least", "--tabs", "0"]).is_err());
+        // the command line, given after LEAST_OPTS, overrides it
+        assert_eq!(
+            parse(&["-i", "--tabs", "4", "--no-ignore-case"]),
+            (false, 4)
+        );
+        assert_eq!(parse(&["--tabs", "4", "--tabs", "2"]), (false, 2));
+        assert_eq!(parse(&["--no-ignore-case", "--ignore-case"]), (true, 8));
+    }
+
+    #[test]
+    fn test_ignore_case() {
+        let mut app = app_with_lines(&["start", "Error", "errand", "ERR"], 3);
+        app.cli.ignore_case = true;
+        app.submit_search("err".to_string());
+        assert_eq!(app.last_match, Some(1));
+        count_all_matches(&mut app);
+        assert_eq!(app.match_status().as_deref(), Some("match 1 of 3"));
+
+        app.submit_filter("ERR".to_string());
+        assert_eq!(app.opened_input().current_total_lines(), 3);
+    }
+
+    #[test]
+    fn test_tabs() {
+        let mut app = app_with_lines(&["a\tb"], 3);
+        assert_eq!(screen(&mut app)[0], format!("a{}b", " ".repeat(7)));
+        // the lines drawn with the old tab stops aren't reused
+        app.render_options.tab_width = 4;
+        assert_eq!(screen(&mut app)[0], format!("a{}b", " ".repeat(3)));
+    }
+
+    #[test]
+    fn test_no_init() {
+        let parse = |args: &[&str]| {
+            let args = ["least"].iter().chain(args).chain(&["Cargo.toml"]);
+            Cli::try_parse_from(args).unwrap()
+        };
+        assert!(!parse(&[]).no_init);
+        assert!(parse(&["-X"]).no_init);
+        assert!(parse(&["--no-init"]).no_init);
+        // given twice, as with LEAST_OPTS and the command line both asking
+        assert!(parse(&["-X", "--no-init"]).no_init);
+    }
+
+    #[test]
+    fn test_dump() {
+        let first = TempPath::new("dump-a");
+        let second = TempPath::new("dump-b");
+        fs::write(&first, "a\tb\nB\x08Bold\n").unwrap();
+        fs::write(&second, "last").unwrap();
+        let cli = Cli::try_parse_from([
+            "least",
+            "--dump",
+            first.to_str().unwrap(),
+            second.to_str().unwrap(),
+        ])
+        .unwrap();
+        let mut out = Vec::new();
+        App::new(cli).unwrap().dump(&mut out).unwrap();
+        // tabs expanded and the overstrike bold taken as styling, one input
+        // after the other
+        assert_eq!(String::from_utf8(out).unwrap(), "a       b\nBold\nlast\n");
+    }
+
+    #[test]
+    fn test_dump_max_lines() {
+        let path = TempPath::new("dump-max-lines");
+        let text: String = (1..=2000).map(|i| format!("{i}\n")).collect();
+        fs::write(&path, &text).unwrap();
+        for raw in [false, true] {
+            let mut args = vec!["least", "--dump", "--max-lines", "100"];
+            args.extend(raw.then_some("-r"));
+            args.push(path.to_str().unwrap());
+            let mut out = Vec::new();
+            App::new(Cli::try_parse_from(args).unwrap())
+                .unwrap()
+                .dump(&mut out)
+                .unwrap();
+            // every line is written, though only the last ones are kept
+            assert_eq!(String::from_utf8(out).unwrap(), text);
+        }
+    }
+
+    #[test]
+    fn test_dump_squeeze() {
+        let path = TempPath::new("dump-squeeze");
+        fs::write(&path, "a\n\n\n\nb\n\nc\n").unwrap();
+        let cli = Cli::try_parse_from(["least", "--dump", "-s", path.to_str().unwrap()]).unwrap();
+        let mut out = Vec::new();
+        App::new(cli).unwrap().dump(&mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "a\n\nb\n\nc\n");
+    }
+
+    #[test]
+    fn test_label() {
+        let mut app = app(3, 5);
+        app.cli.label = Some("build log".to_string());
+        // only stdin goes by the label
+        assert!(screen(&mut app)[4].starts_with("test "));
+        app.paging_stdin = true;
+        assert!(screen(&mut app)[4].starts_with("build log "));
+    }
+
+    #[test]
+    fn test_raw_control_chars() {
+        let path = TempPath::new("raw");
+        std::fs::write(&path, "\x1b[31mred\x1b[0m \x1b[2Jcleared\n").unwrap();
+        let new = |args: &[&str]| {
+            let path = [path.to_str().unwrap()];
+            let args = ["least"].iter().chain(args).chain(&path);
+            App::new(Cli::try_parse_from(args).unwrap()).unwrap()
+        };
+        // styling is shown even where --color would have it as text
+        assert!(new(&["-r", "--color", "never"]).render_options.styled);
+        assert!(!new(&["--color", "never"]).render_options.styled);
+
+        let dump = |app: App| {
+            let mut out = Vec::new();
+            app.dump(&mut out).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        assert_eq!(dump(new(&["--color", "always"])), "red cleared\n");
+        assert_eq!(dump(new(&["-r"])), "\x1b[31mred\x1b[0m \x1b[2Jcleared\n");
+    }
+
+    #[test]
+    fn test_position_bar() {
+        let mut app = app(100, 12);
+        app.cli.position_bar = Some(BarPosition::Top);
+        assert_eq!(app.term_height(), 10);
+        assert_eq!(app.progress(), 0.1);
+        let rows = screen(&mut app);
+        assert_eq!(rows[1], "1");
+        assert_eq!(rows[0], format!("{}{}", "━".repeat(8), "─".repeat(72)));
+
+        app.cli.position_bar = Some(BarPosition::Bottom);
+        app.on_action(Action::GoToBottom);
+        assert_eq!(app.progress(), 1.0);
+        let rows = screen(&mut app);
+        assert_eq!(rows[0], "91");
+        assert_eq!(rows[9], "100");
+        assert!(rows[11].starts_with("test"));
+    }
+
+    #[test]
+    fn test_header() {
+        let mut app = app(100, 11);
+        app.cli.header = 2;
+        assert_eq!(app.term_height(), 8);
+        app.skip_header();
+        assert_eq!(app.current_line, 2);
+        app.on_action(Action::GoToBottom);
+        app.skip_header();
+        assert_eq!(app.current_line, 92);
+        app.on_action(Action::GoToTop);
+        app.skip_header();
+        assert_eq!(app.current_line, 2);
+
+        // there is always a row left to scroll in
+        app.cli.header = 20;
+        assert_eq!(app.term_height(), 1);
+    }
+
+    #[test]
+    fn test_go_to_line_from_end() {
+        let mut app = app(100, 11);
+
+        app.on_action(Action::GoToLineFromEnd(30));
+        assert_eq!(app.current_line, 70);
+        app.on_action(Action::GoToLineFromEnd(500));
+        assert_eq!(app.current_line, 0);
+        // the whole input is read, so there is nothing to keep up with
+        assert_eq!(app.lines_from_end, None);
+    }
+
+    #[test]
+    fn test_paste() {
+        let mut app = app_with_lines(&["one", "two words", "three"], 5);
+        let paste = |app: &mut App, text: &str| {
+            app.update(Event::Term(crossterm::event::Event::Paste(
+                text.to_string(),
+            )))
+        };
+        // pasted while paging, it isn't taken for keys
+        paste(&mut app, "G").unwrap();
+        assert_eq!(app.current_line, 0);
+
+        app.press(KeyCode::Char('/')).unwrap();
+        app.press(KeyCode::Char('t')).unwrap();
+        paste(&mut app, "wo\r\n wor").unwrap();
+        assert_eq!(app.prompt.input(), "two wor");
+        assert_eq!(app.prompt.cursor(), 7);
+        app.press(KeyCode::Enter).unwrap();
+        assert_eq!(app.last_match, Some(1));
+    }
+
+    #[test]
+    fn test_window() {
+        // four rows of lines, the last of each screen kept as the first of the
+        // next
+        let mut app = app(20, 5);
+        app.on_action(Action::ScrollDownScreen);
+        assert_eq!(app.current_line, 3);
+        app.on_action(Action::ScrollUpScreen);
+        assert_eq!(app.current_line, 0);
+
+        app.submit_command("set window=2");
+        app.on_action(Action::ScrollDownScreen);
+        app.on_action(Action::ScrollDownScreen);
+        assert_eq!(app.current_line, 4);
+        app.submit_command("set nowindow");
+        app.on_action(Action::ScrollUpScreen);
+        assert_eq!(app.current_line, 1);
+
+        // a single row still scrolls
+        app.term_size.height = 2;
+        app.on_action(Action::GoToTop);
+        app.on_action(Action::ScrollDownScreen);
+        assert_eq!(app.current_line, 1);
+    }
+
+    #[test]
+    fn test_overlap() {
+        let mut app = app(20, 5);
+        let before = screen(&mut app);
+        app.on_action(Action::ScrollDownScreen);
+        let after = screen(&mut app);
+        assert_eq!(before[3], after[0]);
+        app.on_action(Action::ScrollUpScreen);
+        assert_eq!(screen(&mut app), before);
+
+        app.submit_command("set overlap=2");
+        app.on_action(Action::ScrollDownScreen);
+        assert_eq!(screen(&mut app)[..2], before[2..4]);
+        // scrolling goes on however much is kept
+        app.submit_command("set overlap=9");
+        app.on_action(Action::ScrollDownScreen);
+        assert_eq!(app.current_line, 3);
+    }
+
+    #[test]
+    fn test_info() {
+        let path = TempPath::new("info");
+        fs::write(&path, "one\ntwo\nthree\n").unwrap();
+        let mut app = file_app(&path, Cli::default(), 10);
+        let info = app.input_info();
+        assert!(info.contains(&("Kind", "file".to_string())));
+        assert!(info.contains(&("Size", "14 bytes".to_string())));
+        assert!(info.contains(&("Lines", "3".to_string())));
+        assert!(info.iter().any(|(key, _)| *key == "Modified"));
+
+        app.press(KeyCode::Char('=')).unwrap();
+        let rows = screen(&mut app);
+        assert!(rows.iter().any(|row| row.contains("Size  14 bytes")));
+        // any key puts it away
+        app.press(KeyCode::Char('j')).unwrap();
+        assert_eq!(app.mode, AppMode::Main);
+        assert_eq!(app.current_line, 0);
+        assert_eq!(screen(&mut app)[0], "one");
+
+        // lines paged without an input to go with them are of no known kind
+        let app = app_with_lines(&["a"], 5);
+        assert!(app.input_info().iter().all(|(key, _)| *key != "Kind"));
+    }
+
+    #[test]
+    fn test_edit() {
+        let path = TempPath::new("edit");
+        fs::write(&path, "1\n2\n3\n4\n").unwrap();
+        let mut app = file_app(&path, Cli::default(), 3);
+        app.press(KeyCode::Char('j')).unwrap();
+        app.press(KeyCode::Char('v')).unwrap();
+        assert_eq!(app.pending_edit, Some((path.to_path_buf(), 2)));
+        let editor = App::editor_command(&path, 2);
+        let args: Vec<_> = editor.get_args().skip(2).collect();
+        assert_eq!(args, [OsString::from("sh"), path.as_os_str().into()]);
+
+        // what the editor left is read anew, from the same line
+        fs::write(&path, "0\n1\n2\n3\n4\n").unwrap();
+        app.pending_edit = None;
+        app.reload_edited(2).unwrap();
+        while !app.opened_input().reached_eof() {
+            app.handle_events().unwrap();
+        }
+        assert_eq!(app.opened_input().lines_read(), 5);
+        assert_eq!(screen(&mut app)[0], "1");
+
+        // stdin is no file to edit
+        app.inputs = vec![Input::stdin()];
+        app.opened_input = Some(RefCell::new(OpenedInput::from_lines(&["a"])));
+        app.on_action(Action::EditFile);
+        assert_eq!(app.pending_edit, None);
+        assert_eq!(app.message.as_deref(), Some("test isn't a file to edit"));
+    }
+
+    #[test]
+    fn test_needs_redraw() {
+        let mut app = streaming_app(6);
+        app.feed_input(Event::LineCount(100)).unwrap();
+        app.feed_input(new_lines(0..3)).unwrap();
+        assert!(app.needs_redraw);
+
+        // the rest of the screen fills up
+        app.needs_redraw = false;
+        app.feed_input(new_lines(3..8)).unwrap();
+        assert!(app.needs_redraw);
+
+        // lines below the screen, with the count of them known already
+        app.needs_redraw = false;
+        app.feed_input(new_lines(8..20)).unwrap();
+        assert!(!app.needs_redraw);
+        assert_eq!(app.opened_input().current_total_lines(), 20);
+
+        // scrolling down brings them on screen
+        app.press(KeyCode::Char('j')).unwrap();
+        assert!(app.needs_redraw);
+
+        // the rest of the lines below the screen change nothing on it
+        app.needs_redraw = false;
+        app.feed_input(new_lines(20..100)).unwrap();
+        assert!(!app.needs_redraw);
+
+        // the end, once on screen, adds (END) to the status bar
+        app.press(KeyCode::Char('G')).unwrap();
+        app.needs_redraw = false;
+        app.feed_input(Event::Eof).unwrap();
+        assert!(app.needs_redraw);
+        assert!(screen(&mut app)[5].ends_with("(END)"));
+        // which news of it again leaves as it is
+        app.needs_redraw = false;
+        app.feed_input(Event::Eof).unwrap();
+        assert!(!app.needs_redraw);
+
+        // as does an end off the screen, with the count of lines known
+        let mut app = streaming_app(6);
+        app.feed_input(Event::LineCount(100)).unwrap();
+        app.feed_input(new_lines(0..100)).unwrap();
+        app.needs_redraw = false;
+        app.feed_input(Event::Eof).unwrap();
+        assert!(!app.needs_redraw);
+    }
+
+    #[test]
+    fn test_prompt() {
+        let mut app = app(40, 6);
+        app.cli.prompt = Some("%f: %l/%L %p%% at %b of %B".parse().unwrap());
+        app.press(KeyCode::Char('j')).unwrap();
+        assert_eq!(screen(&mut app)[5], "test: 2/40 15% at 2 of 111");
+
+        // messages still take the status bar
+        app.message = Some("hello".to_string());
+        assert_eq!(screen(&mut app)[5], "hello");
+    }
+
+    #[test]
+    fn test_go_to_byte() {
+        let mut app = app(40, 6);
+        // 1 to 9 take two bytes each with their newlines
+        app.submit_command("goto-byte 5");
+        assert_eq!(app.current_line, 2);
+        app.submit_command("goto-byte 18");
+        assert_eq!(screen(&mut app)[0], "10");
+        app.submit_command("goto-byte 20");
+        assert_eq!(screen(&mut app)[0], "10");
+        app.submit_command("goto-byte 100000");
+        assert_eq!(app.current_line, app.current_max_line());
+
+        let mut app = streaming_app(6);
+        app.submit_command("goto-byte 5");
+        assert_eq!(app.message.as_deref(), Some("No lines read yet"));
+    }
 }
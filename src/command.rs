@@ -0,0 +1,176 @@
+//! Commands typed at the `:` prompt, e.g. `:42` or `:set nowrap`.
+
+use std::str::FromStr;
+
+use color_eyre::eyre::eyre;
+
+use crate::error::*;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// `:q`, or `:q!` to quit without asking.
+    Quit { force: bool },
+    /// `:N`, going to a line by its 1-based number.
+    GoToLine(usize),
+    /// `:match N`, going to the Nth match of the last search.
+    GoToMatch(usize),
+    /// `:goto-byte N`, going to the line with the byte at offset N.
+    GoToByte(u64),
+    /// `:n`, the next file.
+    NextFile,
+    /// `:p`, the previous file.
+    PrevFile,
+    /// `:e FILE`, opening another file.
+    Edit(String),
+    /// `:info`, showing what is known about the current input.
+    Info,
+    /// `:set OPTION`, changing an option while paging.
+    Set(Setting),
+}
+
+/// An option `:set` can change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Setting {
+    /// `wrap`, `nowrap`, `wrap=on` or `wrap off`.
+    Wrap(bool),
+    /// `scrolloff=N` or `scrolloff N`.
+    Scrolloff(usize),
+    /// `wholeword` or `nowholeword`, whether searches match whole words only.
+    WholeWord(bool),
+    /// `squeeze` or `nosqueeze`, whether runs of blank lines show as one.
+    Squeeze(bool),
+    /// `window=N`, or `nowindow` to scroll by a screen less the overlap
+    /// again.
+    Window(Option<usize>),
+    /// `overlap=N`, the lines a screen shares with the next one.
+    Overlap(usize),
+}
+
+impl FromStr for Command {
+    type Err = color_eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let (name, argument) = s.split_once(' ').unwrap_or((s, ""));
+        let argument = argument.trim();
+        match name {
+            "q" | "quit" => Ok(Command::Quit { force: false }),
+            "q!" | "quit!" => Ok(Command::Quit { force: true }),
+            "n" | "next" => Ok(Command::NextFile),
+            "p" | "prev" => Ok(Command::PrevFile),
+            "e" | "edit" if argument.is_empty() => Err(eyre!("No file name given")),
+            "e" | "edit" => Ok(Command::Edit(argument.to_string())),
+            "info" => Ok(Command::Info),
+            "set" => argument.parse().map(Command::Set),
+            "match" => argument
+                .parse()
+                .ok()
+                .filter(|&ordinal| ordinal > 0)
+                .map(Command::GoToMatch)
+                .ok_or_else(|| eyre!("Expected a match number, counting from 1")),
+            "goto-byte" => argument
+                .parse()
+                .map(Command::GoToByte)
+                .map_err(|_| eyre!("Expected a byte offset")),
+            _ => name
+                .parse()
+                .map(Command::GoToLine)
+                .map_err(|_| eyre!("Unknown command: {name}")),
+        }
+    }
+}
+
+impl FromStr for Setting {
+    type Err = color_eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (name, value) = match s.split_once(['=', ' ']) {
+            Some((name, value)) => (name.trim(), Some(value.trim())),
+            None => (s, None),
+        };
+        let switch = |value: Option<&str>| match value {
+            None | Some("on" | "true" | "yes") => Ok(true),
+            Some("off" | "false" | "no") => Ok(false),
+            Some(value) => Err(eyre!("Expected on or off for {name}, got {value}")),
+        };
+        match name {
+            "" => Err(eyre!("No option given")),
+            "wrap" => switch(value).map(Setting::Wrap),
+            "nowrap" if value.is_none() => Ok(Setting::Wrap(false)),
+            "wholeword" => switch(value).map(Setting::WholeWord),
+            "nowholeword" if value.is_none() => Ok(Setting::WholeWord(false)),
+            "squeeze" => switch(value).map(Setting::Squeeze),
+            "nosqueeze" if value.is_none() => Ok(Setting::Squeeze(false)),
+            "scrolloff" => value
+                .and_then(|value| value.parse().ok())
+                .map(Setting::Scrolloff)
+                .ok_or_else(|| eyre!("Expected a number of lines for scrolloff")),
+            "window" => value
+                .and_then(|value| value.parse().ok())
+                .filter(|&lines| lines > 0)
+                .map(|lines| Setting::Window(Some(lines)))
+                .ok_or_else(|| eyre!("Expected a number of lines for window")),
+            "nowindow" if value.is_none() => Ok(Setting::Window(None)),
+            "overlap" => value
+                .and_then(|value| value.parse().ok())
+                .map(Setting::Overlap)
+                .ok_or_else(|| eyre!("Expected a number of lines for overlap")),
+            _ => Err(eyre!("Unknown option: {name}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::command::{Command, Setting};
+
+    #[test]
+    fn test_parse_command() {
+        let parse = |s: &str| s.parse::<Command>().map_err(|e| e.to_string());
+        assert_eq!(parse("q"), Ok(Command::Quit { force: false }));
+        assert_eq!(parse("q!"), Ok(Command::Quit { force: true }));
+        assert_eq!(parse(" 42 "), Ok(Command::GoToLine(42)));
+        assert_eq!(parse("e a b.txt"), Ok(Command::Edit("a b.txt".to_string())));
+        assert_eq!(parse("e").unwrap_err(), "No file name given");
+        assert_eq!(parse("info"), Ok(Command::Info));
+        assert_eq!(parse("set nowrap"), Ok(Command::Set(Setting::Wrap(false))));
+        assert_eq!(parse("set wrap=on"), Ok(Command::Set(Setting::Wrap(true))));
+        assert_eq!(
+            parse("set wrap off"),
+            Ok(Command::Set(Setting::Wrap(false)))
+        );
+        assert_eq!(
+            parse("set scrolloff=3"),
+            Ok(Command::Set(Setting::Scrolloff(3)))
+        );
+        assert_eq!(
+            parse("set nowholeword"),
+            Ok(Command::Set(Setting::WholeWord(false)))
+        );
+        assert_eq!(
+            parse("set window=10"),
+            Ok(Command::Set(Setting::Window(Some(10))))
+        );
+        assert!(parse("set window=0").is_err());
+        assert_eq!(
+            parse("set overlap 2"),
+            Ok(Command::Set(Setting::Overlap(2)))
+        );
+        assert_eq!(
+            parse("set nosqueeze"),
+            Ok(Command::Set(Setting::Squeeze(false)))
+        );
+        assert_eq!(
+            parse("set wrap=maybe").unwrap_err(),
+            "Expected on or off for wrap, got maybe"
+        );
+        assert_eq!(parse("match 12"), Ok(Command::GoToMatch(12)));
+        assert_eq!(
+            parse("match 0").unwrap_err(),
+            "Expected a match number, counting from 1"
+        );
+        assert_eq!(parse("goto-byte 1024"), Ok(Command::GoToByte(1024)));
+        assert_eq!(parse("goto-byte").unwrap_err(), "Expected a byte offset");
+        assert_eq!(parse("x").unwrap_err(), "Unknown command: x");
+    }
+}
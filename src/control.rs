@@ -0,0 +1,118 @@
+//! Commands sent by another program over `--control-fd`, one JSON object per
+//! line, e.g. `{"command": "go_to_line", "line": 42}`.
+
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    sync::mpsc::Sender,
+    thread,
+};
+
+use color_eyre::eyre::eyre;
+use serde::Deserialize;
+
+use crate::{error::*, event::Event, keys::Action};
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum ControlCommand {
+    GoToTop,
+    GoToBottom,
+    /// Go to a line by its 1-based number.
+    GoToLine {
+        line: usize,
+    },
+    /// Search for a pattern as if it were typed at the `/` or `?` prompt.
+    Search {
+        pattern: String,
+        #[serde(default)]
+        backward: bool,
+    },
+    SearchNext {
+        #[serde(default = "one")]
+        count: usize,
+    },
+    SearchPrev {
+        #[serde(default = "one")]
+        count: usize,
+    },
+    ToggleWrap,
+    Quit,
+}
+
+fn one() -> usize {
+    1
+}
+
+impl ControlCommand {
+    pub fn parse(line: &str) -> Result<Self> {
+        serde_json::from_str(line).map_err(|e| eyre!("bad control command {line:?}: {e}"))
+    }
+
+    /// The key action doing the same, or `None` for commands that need more
+    /// than a key press.
+    pub fn action(&self) -> Option<Action> {
+        match *self {
+            ControlCommand::GoToTop => Some(Action::GoToTop),
+            ControlCommand::GoToBottom => Some(Action::GoToBottom),
+            ControlCommand::GoToLine { line } => Some(Action::GoToLine(line)),
+            ControlCommand::Search { .. } => None,
+            ControlCommand::SearchNext { count } => Some(Action::SearchNext(count)),
+            ControlCommand::SearchPrev { count } => Some(Action::SearchPrev(count)),
+            ControlCommand::ToggleWrap => Some(Action::ToggleWrap),
+            // whoever sends it has already made up their mind
+            ControlCommand::Quit => Some(Action::ForceQuit),
+        }
+    }
+}
+
+/// Reads commands from the already open file descriptor `fd` until it is
+/// closed. Lines that aren't valid commands are logged and skipped.
+pub fn spawn_control_thread(fd: i32, tx: Sender<Event>) -> Result<()> {
+    // going through /dev/fd keeps the descriptor's ownership out of our hands
+    let file = File::open(format!("/dev/fd/{fd}"))
+        .map_err(|e| eyre!("failed to open control fd {fd}: {e}"))?;
+    thread::spawn(move || {
+        for line in BufReader::new(file).lines() {
+            let Ok(line) = line else { break };
+            if line.trim().is_empty() {
+                continue;
+            }
+            match ControlCommand::parse(&line) {
+                Ok(command) => {
+                    if tx.send(Event::Control(command)).is_err() {
+                        break;
+                    }
+                }
+                Err(err) => log::warn!("{err}"),
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::control::ControlCommand;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            ControlCommand::parse(r#"{"command": "go_to_line", "line": 42}"#).unwrap(),
+            ControlCommand::GoToLine { line: 42 }
+        );
+        assert_eq!(
+            ControlCommand::parse(r#"{"command": "search", "pattern": "fn main"}"#).unwrap(),
+            ControlCommand::Search {
+                pattern: "fn main".to_string(),
+                backward: false
+            }
+        );
+        assert_eq!(
+            ControlCommand::parse(r#"{"command": "search_next"}"#).unwrap(),
+            ControlCommand::SearchNext { count: 1 }
+        );
+        assert!(ControlCommand::parse(r#"{"command": "go_to_line"}"#).is_err());
+        assert!(ControlCommand::parse("quit").is_err());
+    }
+}
@@ -0,0 +1,85 @@
+//! Coloring of unified diffs, as written by `git diff` and `diff -u`, by how
+//! each line starts.
+
+use std::sync::Arc;
+
+use clap::ValueEnum;
+use ratatui::style::{Style, Stylize};
+
+/// Which inputs `--diff` colors.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DiffMode {
+    #[default]
+    Never,
+    /// Color every input as a diff
+    Always,
+    /// Color the inputs whose first lines look like a diff
+    Auto,
+}
+
+/// Lines read before `auto` decides whether an input is a diff.
+pub const SNIFF_LINES: usize = 20;
+
+/// Whether the first lines have the file or hunk headers of a diff.
+pub fn looks_like_diff(lines: &[Arc<str>]) -> bool {
+    let lines = &lines[..lines.len().min(SNIFF_LINES)];
+    lines
+        .iter()
+        .any(|line| line.starts_with("diff --git ") || line.starts_with("@@ "))
+        || lines
+            .windows(2)
+            .any(|pair| pair[0].starts_with("--- ") && pair[1].starts_with("+++ "))
+}
+
+/// The style of a diff line: file headers bold, hunk headers cyan, and added
+/// and removed lines green and red. Lines git already colored start with an
+/// escape sequence instead, and are left to their own colors.
+pub fn line_style(line: &str) -> Option<Style> {
+    let style = Style::new();
+    if ["diff ", "--- ", "+++ "]
+        .iter()
+        .any(|header| line.starts_with(header))
+    {
+        Some(style.bold())
+    } else if line.starts_with("@@") {
+        Some(style.cyan())
+    } else if line.starts_with('+') {
+        Some(style.green())
+    } else if line.starts_with('-') {
+        Some(style.red())
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use ratatui::style::{Style, Stylize};
+
+    use crate::diff::{line_style, looks_like_diff};
+
+    #[test]
+    fn test_diff() {
+        let lines = |lines: &[&str]| -> Vec<Arc<str>> { lines.iter().map(|&l| l.into()).collect() };
+        assert!(looks_like_diff(&lines(&[
+            "--- a.txt",
+            "+++ b.txt",
+            "@@ -1 +1 @@"
+        ])));
+        assert!(looks_like_diff(&lines(&[
+            "commit 1234",
+            "",
+            "diff --git a/x b/x"
+        ])));
+        assert!(!looks_like_diff(&lines(&["- a list", "- of things"])));
+
+        assert_eq!(line_style("+++ b.txt"), Some(Style::new().bold()));
+        assert_eq!(line_style("@@ -1 +1 @@ fn main"), Some(Style::new().cyan()));
+        assert_eq!(line_style("+added"), Some(Style::new().green()));
+        assert_eq!(line_style("-removed"), Some(Style::new().red()));
+        assert_eq!(line_style(" context"), None);
+        assert_eq!(line_style("\x1b[32m+added\x1b[m"), None);
+    }
+}
@@ -0,0 +1,251 @@
+//! Decoding of inputs that aren't UTF-8, like Latin-1 logs and the UTF-16
+//! files Windows writes, with `--encoding`.
+
+use std::{
+    borrow::Cow,
+    fs::File,
+    io::{self, BufRead, Read},
+    path::Path,
+    str::FromStr,
+};
+
+use encoding_rs::{Decoder, Encoding, UTF_8, UTF_16BE, UTF_16LE, WINDOWS_1252};
+
+/// The encoding `--encoding` reads inputs in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputEncoding {
+    /// Told apart by a byte order mark or the first bytes, see [`detect`].
+    Auto,
+    Fixed(&'static Encoding),
+}
+
+impl FromStr for InputEncoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        if s == "auto" {
+            return Ok(InputEncoding::Auto);
+        }
+        Encoding::for_label(s.as_bytes())
+            .map(InputEncoding::Fixed)
+            .ok_or_else(|| format!("unknown encoding `{s}`"))
+    }
+}
+
+impl InputEncoding {
+    /// The encoding of an input starting with `head`.
+    pub fn resolve(self, head: &[u8]) -> &'static Encoding {
+        match self {
+            InputEncoding::Auto => detect(head),
+            InputEncoding::Fixed(encoding) => encoding,
+        }
+    }
+}
+
+/// Bytes looked at to guess an input's encoding.
+const SNIFF_BYTES: usize = 4096;
+
+/// Guesses the encoding of an input from its first bytes: a byte order mark
+/// says, then UTF-16 text that is mostly ASCII has a NUL in every other
+/// byte, and anything else that isn't UTF-8 is taken for Latin-1, or rather
+/// the Windows-1252 that browsers read it as.
+pub fn detect(head: &[u8]) -> &'static Encoding {
+    if let Some((encoding, _)) = Encoding::for_bom(head) {
+        return encoding;
+    }
+    let head = &head[..head.len().min(SNIFF_BYTES)];
+    let nuls = |parity: usize| {
+        head.iter()
+            .skip(parity)
+            .step_by(2)
+            .filter(|&&b| b == 0)
+            .count()
+    };
+    let pairs = head.len() / 2;
+    if pairs > 0 {
+        if nuls(1) * 2 > pairs && nuls(0) * 10 < pairs {
+            return UTF_16LE;
+        }
+        if nuls(0) * 2 > pairs && nuls(1) * 10 < pairs {
+            return UTF_16BE;
+        }
+    }
+    match std::str::from_utf8(head) {
+        Ok(_) => UTF_8,
+        // cut off in the middle of a character, which the rest completes
+        Err(err) if err.error_len().is_none() => UTF_8,
+        Err(_) => WINDOWS_1252,
+    }
+}
+
+/// Guesses the encoding of the file at `path`, see [`detect`].
+pub fn detect_file(path: &Path) -> &'static Encoding {
+    let mut head = Vec::new();
+    if let Ok(file) = File::open(path) {
+        let _ = file.take(SNIFF_BYTES as u64).read_to_end(&mut head);
+    }
+    detect(&head)
+}
+
+/// Whether lines of `encoding` can be split at their delimiter byte before
+/// being decoded, which is the case when ASCII is encoded as itself.
+/// UTF-16 text is decoded first, as its newlines are two bytes, one of which
+/// may be a newline byte anyway.
+pub fn splits_as_bytes(encoding: &'static Encoding) -> bool {
+    encoding.is_ascii_compatible()
+}
+
+/// Whether where decoded text was in the input can be told from the text:
+/// it is split as bytes, or each of its characters was one or two UTF-16
+/// code units. Stateful encodings like ISO-2022-JP have no such offsets.
+pub fn has_byte_offsets(encoding: &'static Encoding) -> bool {
+    splits_as_bytes(encoding) || encoding == UTF_16LE || encoding == UTF_16BE
+}
+
+/// How many bytes of the input `decoded`, text decoded from `encoding` into
+/// UTF-8, was read from. Where [`has_byte_offsets`] says this can't be told,
+/// the decoded bytes are counted instead.
+pub fn encoded_len(encoding: &'static Encoding, decoded: &[u8]) -> u64 {
+    if encoding == UTF_16LE || encoding == UTF_16BE {
+        let units = String::from_utf8_lossy(decoded).encode_utf16().count();
+        return 2 * units as u64;
+    }
+    decoded.len() as u64
+}
+
+/// The length of the byte order mark of `encoding` that `head` starts
+/// with, which decoding drops.
+pub fn bom_len(encoding: &'static Encoding, head: &[u8]) -> u64 {
+    match Encoding::for_bom(head) {
+        Some((found, len)) if found == encoding => len as u64,
+        _ => 0,
+    }
+}
+
+/// Decodes the bytes of a line, with U+FFFD for any that are invalid.
+pub fn decode_line<'a>(encoding: &'static Encoding, bytes: &'a [u8]) -> Cow<'a, str> {
+    if encoding == UTF_8 {
+        return String::from_utf8_lossy(bytes);
+    }
+    encoding.decode_without_bom_handling(bytes).0
+}
+
+/// Reads the text of `inner` decoded from `encoding` as UTF-8, dropping a
+/// byte order mark, so it can be split into lines like UTF-8 input. The end
+/// of `inner` isn't taken for the end of the text, as a followed file may
+/// go on, so a character cut off at the very end is left out.
+pub struct DecodingReader<R> {
+    inner: R,
+    decoder: Decoder,
+    /// Decoded text not read yet, from `pos` on.
+    decoded: Vec<u8>,
+    pos: usize,
+}
+
+impl<R: BufRead> DecodingReader<R> {
+    pub fn new(inner: R, encoding: &'static Encoding) -> Self {
+        Self {
+            inner,
+            decoder: encoding.new_decoder_with_bom_removal(),
+            decoded: Vec::new(),
+            pos: 0,
+        }
+    }
+}
+
+impl<R: BufRead> Read for DecodingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let size = available.len().min(buf.len());
+        buf[..size].copy_from_slice(&available[..size]);
+        self.consume(size);
+        Ok(size)
+    }
+}
+
+impl<R: BufRead> BufRead for DecodingReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        // half a UTF-16 code unit decodes to nothing until the rest is read
+        while self.pos == self.decoded.len() {
+            let raw = self.inner.fill_buf()?;
+            if raw.is_empty() {
+                break;
+            }
+            let max = self
+                .decoder
+                .max_utf8_buffer_length(raw.len())
+                .unwrap_or(raw.len() * 3);
+            self.decoded.resize(max, 0);
+            let (_, read, written, _) = self.decoder.decode_to_utf8(raw, &mut self.decoded, false);
+            self.decoded.truncate(written);
+            self.pos = 0;
+            self.inner.consume(read);
+        }
+        Ok(&self.decoded[self.pos..])
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.pos = (self.pos + amount).min(self.decoded.len());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Read;
+
+    use encoding_rs::{UTF_8, UTF_16BE, UTF_16LE, WINDOWS_1252};
+
+    use crate::encoding::{
+        DecodingReader, InputEncoding, bom_len, decode_line, detect, encoded_len,
+    };
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(detect(b"\xff\xfea\x00"), UTF_16LE);
+        assert_eq!(detect(b"a\x00b\x00\n\x00"), UTF_16LE);
+        assert_eq!(detect(b"\x00a\x00b\x00\n"), UTF_16BE);
+        assert_eq!(detect("plain café\n".as_bytes()), UTF_8);
+        // cut off halfway through the é
+        assert_eq!(detect(&"café".as_bytes()[..4]), UTF_8);
+        assert_eq!(detect(b"caf\xe9\n"), WINDOWS_1252);
+        assert_eq!(detect(b""), UTF_8);
+
+        assert_eq!(
+            "latin1".parse::<InputEncoding>(),
+            Ok(InputEncoding::Fixed(WINDOWS_1252))
+        );
+        assert_eq!("auto".parse::<InputEncoding>(), Ok(InputEncoding::Auto));
+        assert!("klingon".parse::<InputEncoding>().is_err());
+    }
+
+    #[test]
+    fn test_decode_latin1() {
+        assert_eq!(decode_line(WINDOWS_1252, b"caf\xe9 \xa34"), "café £4");
+        assert_eq!(decode_line(UTF_8, b"caf\xe9"), "caf\u{fffd}");
+    }
+
+    #[test]
+    fn test_decode_utf16le() {
+        let text: Vec<u8> = "\u{feff}one\r\nДва\n"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        // the first byte comes on its own, splitting the byte order mark
+        let mut reader = DecodingReader::new(text.as_slice().take(1).chain(&text[1..]), UTF_16LE);
+        let mut decoded = String::new();
+        reader.read_to_string(&mut decoded).unwrap();
+        assert_eq!(decoded, "one\r\nДва\n");
+
+        let mut reader = DecodingReader::new(&text[..7], UTF_16LE);
+        let mut decoded = String::new();
+        reader.read_to_string(&mut decoded).unwrap();
+        // the e is cut off halfway
+        assert_eq!(decoded, "on");
+
+        // and lines are as long as they were before decoding
+        assert_eq!(bom_len(UTF_16LE, &text), 2);
+        assert_eq!(bom_len(UTF_16BE, &text), 0);
+        assert_eq!(encoded_len(UTF_16LE, "Два\n".as_bytes()), 8);
+        assert_eq!(encoded_len(UTF_16LE, "𝄞".as_bytes()), 4);
+    }
+}
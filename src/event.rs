@@ -1,9 +1,42 @@
+use std::sync::Arc;
+
 use color_eyre::eyre::Report;
 
+use crate::control::ControlCommand;
+
 pub enum Event {
     Term(crossterm::event::Event),
-    NewLines(Vec<String>),
-    EOF,
+    /// An event of the opened input with the given id. The input events below
+    /// only ever arrive wrapped in this.
+    Input(u64, Box<Event>),
+    /// Lines read, along with the byte offset in the input where each starts.
+    NewLines(Vec<Arc<str>>, Vec<u64>),
+    /// The start of a line whose end hasn't been read yet, and where it
+    /// starts. It stands in for the last line until the next lines arrive,
+    /// the first of which is this one read in full.
+    PartialLine(Arc<str>, u64),
+    Eof,
+    /// The number of lines in the whole input, counted ahead of reading them.
+    LineCount(usize),
+    /// The start of the input doesn't look like text.
+    Binary,
+    /// The encoding the input is read in, sent before its lines unless it is
+    /// UTF-8.
+    Encoding(&'static encoding_rs::Encoding),
     Err(Report),
+    /// Reading the input ran into trouble that it kept going past, or
+    /// stopped at, keeping the lines read before.
+    ReadWarning(String),
+    /// A command read from the control file descriptor.
+    Control(ControlCommand),
+    /// The rows up to `scanned` that match the search being counted by the
+    /// match counter `id`.
+    Matches {
+        id: u64,
+        rows: Vec<usize>,
+        scanned: usize,
+    },
+    /// A termination signal was received.
+    Signal(i32),
     ReaderThreadErrReturned,
 }
@@ -0,0 +1,240 @@
+use std::sync::Arc;
+
+use crate::{search::Pattern, utils::strip_styling};
+
+/// A row of a [`FilterView`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterRow {
+    /// An input line matching the filter.
+    Match(usize),
+    /// An input line shown around a match.
+    Context(usize),
+    /// Stands for the lines skipped between two groups of rows.
+    Separator,
+}
+
+/// The input lines matching a pattern along with `context` lines before and
+/// after each match, like `grep -C`, or all of them without a pattern. When
+/// squeezing, a blank line right after another is left out, like `cat -s`
+/// does.
+#[derive(Debug)]
+pub struct FilterView {
+    pattern: Option<Pattern>,
+    context: usize,
+    squeeze: bool,
+    rows: Vec<FilterRow>,
+    /// Number of input lines already looked at.
+    scanned: usize,
+    /// The last input line in `rows`.
+    last_included: Option<usize>,
+    /// Lines after the last match still to be shown as context.
+    trailing: usize,
+}
+
+impl FilterView {
+    pub fn new(pattern: Pattern, context: usize) -> Self {
+        Self::with_pattern(Some(pattern), context)
+    }
+
+    /// Every line, as a view to squeeze without filtering.
+    pub fn all() -> Self {
+        Self::with_pattern(None, 0)
+    }
+
+    fn with_pattern(pattern: Option<Pattern>, context: usize) -> Self {
+        Self {
+            pattern,
+            context,
+            squeeze: false,
+            rows: Vec::new(),
+            scanned: 0,
+            last_included: None,
+            trailing: 0,
+        }
+    }
+
+    /// Squeezes runs of blank lines into one, or stops doing so. The lines
+    /// are looked at again from the start.
+    pub fn squeezed(self, squeeze: bool) -> Self {
+        Self {
+            squeeze,
+            ..Self::with_pattern(self.pattern, self.context)
+        }
+    }
+
+    pub fn pattern(&self) -> Option<&Pattern> {
+        self.pattern.as_ref()
+    }
+
+    pub fn rows(&self) -> &[FilterRow] {
+        &self.rows
+    }
+
+    /// Filters the lines added to `lines` since the last call. Only new lines
+    /// are looked at, so this is cheap to call for every batch read.
+    pub fn extend(&mut self, lines: &[Arc<str>]) {
+        let blank = |i: usize| lines[i].trim().is_empty();
+        let squeeze = self.squeeze;
+        let squeezed = |i: usize| squeeze && i > 0 && blank(i) && blank(i - 1);
+        for (i, line) in lines.iter().enumerate().skip(self.scanned) {
+            if squeezed(i) {
+                continue;
+            }
+            if self
+                .pattern
+                .as_ref()
+                .is_none_or(|pattern| pattern.is_match(&strip_styling(line)))
+            {
+                let next = self.last_included.map_or(0, |last| last + 1);
+                let first = next.max(i.saturating_sub(self.context));
+                // plain filtering shows no separators, as there is no context
+                // to tell apart, and squeezed lines are no gap either
+                let skipped = (next..first).any(|i| !squeezed(i));
+                if self.context > 0 && self.last_included.is_some() && skipped {
+                    self.rows.push(FilterRow::Separator);
+                }
+                let context = (first..i).filter(|&i| !squeezed(i));
+                self.rows.extend(context.map(FilterRow::Context));
+                self.rows.push(FilterRow::Match(i));
+                self.last_included = Some(i);
+                self.trailing = self.context;
+            } else if self.trailing > 0 {
+                self.rows.push(FilterRow::Context(i));
+                self.last_included = Some(i);
+                self.trailing -= 1;
+            }
+        }
+        self.scanned = lines.len();
+    }
+
+    /// Forgets the first `count` input lines, which are no longer kept,
+    /// numbering the rest from 0 again. Returns how many rows went with them.
+    pub fn evict(&mut self, count: usize) -> usize {
+        let removed = self
+            .rows
+            .iter()
+            .position(|row| match row {
+                FilterRow::Match(index) | FilterRow::Context(index) => *index >= count,
+                FilterRow::Separator => false,
+            })
+            .unwrap_or(self.rows.len());
+        self.rows.drain(..removed);
+        for row in &mut self.rows {
+            if let FilterRow::Match(index) | FilterRow::Context(index) = row {
+                *index -= count;
+            }
+        }
+        self.scanned = self.scanned.saturating_sub(count);
+        self.last_included = self.last_included.and_then(|last| last.checked_sub(count));
+        removed
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use crate::{
+        filter::{FilterRow::*, FilterView},
+        search::Pattern,
+    };
+
+    fn lines(lines: &[&str]) -> Vec<Arc<str>> {
+        lines.iter().map(|&line| Arc::from(line)).collect()
+    }
+
+    #[test]
+    fn test_filter_without_context() {
+        let mut view = FilterView::new(Pattern::new("x".to_string()), 0);
+        view.extend(&lines(&["x", "a", "x", "x", "b"]));
+        assert_eq!(view.rows(), [Match(0), Match(2), Match(3)]);
+    }
+
+    #[test]
+    fn test_filter_with_context() {
+        let input = lines(&["a", "x", "b", "c", "d", "e", "x", "f", "x", "g"]);
+        let mut view = FilterView::new(Pattern::new("x".to_string()), 1);
+        view.extend(&input);
+        assert_eq!(
+            view.rows(),
+            [
+                Context(0),
+                Match(1),
+                Context(2),
+                Separator,
+                Context(5),
+                Match(6),
+                Context(7),
+                Match(8),
+                Context(9),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_filter_streaming() {
+        let mut input = lines(&["a", "x", "b"]);
+        let mut view = FilterView::new(Pattern::new("x".to_string()), 2);
+        view.extend(&input);
+        assert_eq!(view.rows(), [Context(0), Match(1), Context(2)]);
+
+        // trailing context and a new group carry on across batches
+        input.extend(lines(&["c", "d", "e", "f", "g", "x"]));
+        view.extend(&input);
+        assert_eq!(
+            view.rows(),
+            [
+                Context(0),
+                Match(1),
+                Context(2),
+                Context(3),
+                Separator,
+                Context(6),
+                Context(7),
+                Match(8),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_filter_eviction() {
+        let mut input = lines(&["a", "x", "b", "c", "d", "x", "e"]);
+        let mut view = FilterView::new(Pattern::new("x".to_string()), 1);
+        view.extend(&input);
+        // the separator goes along with the group before it
+        input.drain(..3);
+        assert_eq!(view.evict(3), 4);
+        assert_eq!(view.rows(), [Context(1), Match(2), Context(3)]);
+
+        input.extend(lines(&["f", "x"]));
+        view.extend(&input);
+        assert_eq!(
+            view.rows(),
+            [Context(1), Match(2), Context(3), Context(4), Match(5)]
+        );
+    }
+
+    #[test]
+    fn test_filter_squeeze() {
+        let input = lines(&["a", "", "", " ", "b", "", "x", "", "", "", "x"]);
+        let mut view = FilterView::all().squeezed(true);
+        view.extend(&input);
+        assert_eq!(
+            view.rows(),
+            [
+                Match(0),
+                Match(1),
+                Match(4),
+                Match(5),
+                Match(6),
+                Match(7),
+                Match(10)
+            ]
+        );
+
+        // the blank lines squeezed away leave no gap between the groups
+        let mut view = FilterView::new(Pattern::new("x".to_string()), 1).squeezed(true);
+        view.extend(&input);
+        assert_eq!(view.rows(), [Context(5), Match(6), Context(7), Match(10)]);
+    }
+}
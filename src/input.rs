@@ -1,26 +1,363 @@
 use std::{
+    cell::RefCell,
     cmp,
+    collections::HashMap,
     fmt::{self},
-    fs::File,
-    io::{BufRead, BufReader, stdin},
+    fs::{self, File},
+    hash::{Hash, Hasher},
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write, stdin},
+    ops::Range,
+    os::unix::{fs::MetadataExt, process::CommandExt},
     path::{Path, PathBuf},
-    sync::mpsc::Sender,
+    process::{self, Child, ChildStderr, ChildStdout, Stdio},
+    sync::{
+        Arc, Mutex, PoisonError,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc::{SendError, Sender},
+    },
     thread::{self, JoinHandle},
     time::{Duration, Instant},
 };
 
-use color_eyre::eyre::eyre;
-use ratatui::text::Line;
+use color_eyre::eyre::{Report, eyre};
+use encoding_rs::{Encoding, UTF_8};
+use ratatui::{
+    style::{Style, Stylize},
+    text::{Line, Span},
+};
+
+use crate::{
+    diff::{self, DiffMode},
+    encoding::{self, DecodingReader, InputEncoding, decode_line},
+    error::*,
+    event::Event,
+    filter::{FilterRow, FilterView},
+    search::{Search, SearchDirection},
+    syntax::Highlighter,
+    utils::{
+        ControlChars, FormFeed, TAB_WIDTH, VerticalTab, WrapIndent, count_lines,
+        expand_control_chars, expand_page_controls, expand_tabs, highlight_ranges, looks_binary,
+        mark_trailing_spaces, newline_offsets, parse_styled_spans, slice_columns, strip_styling,
+        trim_line_ending, wrap_line,
+    },
+    watch::FileWatch,
+};
+
+/// How the lines of an [`OpenedInput`] are turned into text on screen.
+#[derive(Debug, Clone)]
+pub struct RenderOptions {
+    /// Interpret overstrike sequences as styling rather than showing them as
+    /// they are.
+    pub styled: bool,
+    /// Columns scrolled off the left edge.
+    pub column_offset: usize,
+    /// Cut lines off after this many columns, counted from the offset.
+    pub max_columns: Option<usize>,
+    /// Mark tabs instead of showing them as blanks.
+    pub show_tabs: bool,
+    /// Mark the spaces ending a line, along with all tabs.
+    pub show_whitespace: bool,
+    /// How form feeds and vertical tabs are shown.
+    pub form_feed: FormFeed,
+    pub vertical_tab: VerticalTab,
+    pub control_chars: ControlChars,
+    /// Indentation of the rows a wrapped line continues on.
+    pub wrap_indent: WrapIndent,
+    /// Columns between tab stops.
+    pub tab_width: usize,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            styled: false,
+            column_offset: 0,
+            max_columns: None,
+            show_tabs: false,
+            show_whitespace: false,
+            form_feed: FormFeed::default(),
+            vertical_tab: VerticalTab::default(),
+            control_chars: ControlChars::default(),
+            wrap_indent: WrapIndent::default(),
+            tab_width: TAB_WIDTH,
+        }
+    }
+}
+
+/// How an [`Input`] is read by its reader thread.
+#[derive(Debug, Clone)]
+pub struct ReadOptions {
+    pub start: StartPosition,
+    /// How long read lines are batched before being sent to the app.
+    pub flush_interval: Duration,
+    /// Keep the `\r` of CRLF line endings.
+    pub keep_cr: bool,
+    /// The byte lines end with, `\n` unless records are split at NULs.
+    pub delimiter: u8,
+    /// Keep reading a regular file as it grows, reopening it by name when it
+    /// is replaced or truncated, like `tail -F`.
+    pub follow_name: bool,
+    /// Send the start of a line that is still being written whenever the
+    /// input stops short of its newline, see [`Event::PartialLine`].
+    pub show_partial: bool,
+    /// Keep at most this many lines, dropping the oldest to make room.
+    pub max_lines: Option<usize>,
+    /// What the bytes read are decoded from, UTF-8 with invalid sequences
+    /// replaced unless given.
+    pub encoding: Option<InputEncoding>,
+}
+
+impl ReadOptions {
+    pub const DEFAULT_FLUSH_INTERVAL: Duration = Duration::from_millis(16);
+    /// Lines sent before the configured interval applies, so a long interval
+    /// doesn't hold back the first screen.
+    const EAGER_LINES: usize = 256;
+}
 
-use crate::{error::*, event::Event, utils::parse_styled_spans};
+impl Default for ReadOptions {
+    fn default() -> Self {
+        Self {
+            start: StartPosition::default(),
+            flush_interval: Self::DEFAULT_FLUSH_INTERVAL,
+            keep_cr: false,
+            delimiter: b'\n',
+            follow_name: false,
+            show_partial: false,
+            max_lines: None,
+            encoding: None,
+        }
+    }
+}
+
+/// Where reading a regular file starts. Lines before it are never loaded, so
+/// line numbers count from this point.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum StartPosition {
+    #[default]
+    Beginning,
+    Byte(u64),
+    /// A percentage of the file size.
+    Percent(u8),
+}
+
+impl StartPosition {
+    fn offset(self, file_len: u64) -> u64 {
+        match self {
+            StartPosition::Beginning => 0,
+            StartPosition::Byte(offset) => offset,
+            StartPosition::Percent(percent) => file_len * u64::from(percent) / 100,
+        }
+    }
+}
 
-#[derive(Debug)]
+impl fmt::Display for StartPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StartPosition::Beginning => write!(f, "start"),
+            StartPosition::Byte(offset) => write!(f, "byte {offset}"),
+            StartPosition::Percent(percent) => write!(f, "{percent}%"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum InputKind {
     OrdinaryFile(PathBuf),
     StdIn,
+    /// The output of a shell command fed the lines of another input, for `|`.
+    Piped(Piped),
+    /// A reader opened by whoever made the input, see [`Input::reader`].
+    #[cfg(test)]
+    Reader(Reader),
+}
+
+/// A reader handed over already open, like one over bytes in memory. It is
+/// read once, like stdin, and each one is an input of its own.
+#[cfg(test)]
+#[derive(Clone)]
+pub struct Reader {
+    name: String,
+    inner: Arc<Mutex<Option<Box<dyn BufRead + Send>>>>,
+}
+
+#[cfg(test)]
+impl Reader {
+    /// Takes the reader for the one reader thread that gets to read it.
+    fn take(&self) -> Result<Box<dyn BufRead + Send>> {
+        let taken = self.inner.lock().ok().and_then(|mut inner| inner.take());
+        taken.ok_or_else(|| eyre!("{} can't be read again", self.name))
+    }
+}
+
+#[cfg(test)]
+impl fmt::Debug for Reader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Reader").field("name", &self.name).finish()
+    }
+}
+
+#[cfg(test)]
+impl PartialEq for Reader {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+
+#[cfg(test)]
+impl Eq for Reader {}
+
+#[cfg(test)]
+impl Hash for Reader {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Arc::as_ptr(&self.inner).hash(state);
+    }
+}
+
+/// A shell command and the text fed to it on stdin. Each run of a command is
+/// an input of its own, even on the same text.
+#[derive(Debug, Clone)]
+pub struct Piped {
+    command: String,
+    text: Arc<[u8]>,
+}
+
+impl PartialEq for Piped {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.text, &other.text)
+    }
+}
+
+impl Eq for Piped {}
+
+impl Hash for Piped {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Arc::as_ptr(&self.text).cast::<u8>().hash(state);
+    }
+}
+
+impl Piped {
+    /// How long the command may go without writing anything before it is
+    /// taken to hang and stopped.
+    #[cfg(not(test))]
+    const TIMEOUT: Duration = Duration::from_secs(30);
+    #[cfg(test)]
+    const TIMEOUT: Duration = Duration::from_secs(1);
+    /// How often the running command is checked on.
+    const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+    /// Starts the command under `sh` in a process group of its own, so that
+    /// whatever it starts can be stopped along with it. The text is fed on a
+    /// thread, as the command may write some output before reading it all,
+    /// or never read it all, like `head`.
+    fn spawn(&self) -> io::Result<(Child, ChildStdout, ChildStderr)> {
+        let mut child = process::Command::new("sh")
+            .arg("-c")
+            .arg(&self.command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .process_group(0)
+            .spawn()?;
+        let mut stdin = child.stdin.take().unwrap();
+        let text = self.text.clone();
+        thread::spawn(move || {
+            let _ = stdin.write_all(&text);
+        });
+        let stdout = child.stdout.take().unwrap();
+        let stderr = child.stderr.take().unwrap();
+        Ok((child, stdout, stderr))
+    }
+
+    /// Waits for the command to finish, stopping it once the input is closed
+    /// or it goes too long without output, and warns about what it wrote to
+    /// stderr or how it failed. `group` holds the command's process group
+    /// while it runs, for [`OpenedInput::close`] to stop it at once, and
+    /// `last_output` when it last wrote to stdout.
+    fn supervise(
+        &self,
+        mut child: Child,
+        mut stderr: ChildStderr,
+        tx: InputSender,
+        group: Arc<Mutex<Option<i32>>>,
+        last_output: Arc<Mutex<Instant>>,
+    ) {
+        let command = self.command.clone();
+        *group.lock().unwrap_or_else(PoisonError::into_inner) = Some(child.id() as i32);
+        thread::spawn(move || {
+            let errors = thread::spawn(move || {
+                let mut errors = String::new();
+                let _ = stderr.read_to_string(&mut errors);
+                errors
+            });
+            let status = loop {
+                let deadline =
+                    *last_output.lock().unwrap_or_else(PoisonError::into_inner) + Self::TIMEOUT;
+                // the group is given up under the lock once the child is
+                // reaped, so that close() never stops a group reused since
+                let mut running = group.lock().unwrap_or_else(PoisonError::into_inner);
+                let status = match child.try_wait() {
+                    Ok(Some(status)) => Some(status),
+                    Ok(None) if tx.is_closed() || Instant::now() >= deadline => {
+                        // SAFETY: kill takes no pointers; the group is the
+                        // one the child leads
+                        unsafe { libc::kill(-(child.id() as i32), libc::SIGKILL) };
+                        let _ = child.wait();
+                        None
+                    }
+                    Ok(None) => {
+                        drop(running);
+                        thread::sleep(Self::POLL_INTERVAL);
+                        continue;
+                    }
+                    Err(err) => {
+                        *running = None;
+                        log::warn!("waiting for {command}: {err}");
+                        return;
+                    }
+                };
+                *running = None;
+                break status;
+            };
+            let errors = errors.join().unwrap_or_default();
+            if !errors.is_empty() {
+                log::warn!("{command}: {errors}");
+            }
+            let warning = match (status, errors.trim().lines().last()) {
+                _ if tx.is_closed() => return,
+                (None, _) => format!(
+                    "Stopped {command} after {}s without output",
+                    Self::TIMEOUT.as_secs()
+                ),
+                (Some(_), Some(error)) => format!("{command}: {error}"),
+                (Some(status), None) if !status.success() => format!("{command} failed: {status}"),
+                (Some(_), None) => return,
+            };
+            let _ = tx.send(Event::ReadWarning(warning));
+        });
+    }
+}
+
+/// The stdout of a [`Piped`] command, noting when the command last wrote to
+/// it.
+struct PipedOutput {
+    stdout: ChildStdout,
+    last_output: Arc<Mutex<Instant>>,
 }
 
-#[derive(Debug)]
+impl Read for PipedOutput {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.stdout.read(buf)?;
+        if read > 0 {
+            *self
+                .last_output
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner) = Instant::now();
+        }
+        Ok(read)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Input {
     pub kind: InputKind,
 }
@@ -36,82 +373,603 @@ impl Input {
         Input { kind }
     }
 
-    pub fn open(self, tx: Sender<Event>) -> Result<OpenedInput> {
+    /// The output of `command` when fed `text`.
+    pub fn piped(command: String, text: Vec<u8>) -> Self {
+        let text = Arc::from(text);
+        let kind = InputKind::Piped(Piped { command, text });
+        Input { kind }
+    }
+
+    /// Reads `reader`, which is shown as `name`, through the same line
+    /// splitting and events as a file, for tests.
+    #[cfg(test)]
+    pub fn reader(name: impl Into<String>, reader: impl BufRead + Send + 'static) -> Self {
+        let inner = Arc::new(Mutex::new(
+            Some(Box::new(reader) as Box<dyn BufRead + Send>),
+        ));
+        let kind = InputKind::Reader(Reader {
+            name: name.into(),
+            inner,
+        });
+        Input { kind }
+    }
+
+    /// Makes sure the input can be paged before it is opened: a file has to
+    /// be there to read, and not be a directory.
+    pub fn check(&self) -> Result<()> {
+        let Some(path) = self.path() else {
+            return Ok(());
+        };
+        let cannot_open = |err| eyre!("Cannot open {}: {err}", path.display());
+        let metadata = fs::metadata(path).map_err(cannot_open)?;
+        if metadata.is_dir() {
+            return Err(eyre!("{} is a directory", path.display()));
+        }
+        // opening a named pipe waits for a writer, and closing it again may
+        // leave the writer with no one to write to
+        if metadata.is_file() {
+            File::open(path).map_err(cannot_open)?;
+        }
+        Ok(())
+    }
+
+    /// The path of a file input.
+    pub fn path(&self) -> Option<&Path> {
+        match &self.kind {
+            InputKind::OrdinaryFile(path) => Some(path),
+            InputKind::StdIn | InputKind::Piped(_) => None,
+            #[cfg(test)]
+            InputKind::Reader(_) => None,
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match &self.kind {
+            InputKind::OrdinaryFile(path) => path.to_string_lossy().into_owned(),
+            InputKind::StdIn => "stdin".to_string(),
+            InputKind::Piped(piped) => format!("|{}", piped.command),
+            #[cfg(test)]
+            InputKind::Reader(reader) => reader.name.clone(),
+        }
+    }
+
+    /// Copies the whole input to `out` without paging it.
+    pub fn copy_to(self, out: &mut impl Write) -> Result<u64> {
+        match self.kind {
+            InputKind::StdIn => Ok(io::copy(&mut stdin().lock(), out)?),
+            InputKind::OrdinaryFile(path) => File::open(&path)
+                .and_then(|mut file| io::copy(&mut file, out))
+                .map_err(|e| eyre!("'{}': {}", path.to_string_lossy(), e)),
+            InputKind::Piped(piped) => {
+                let (mut child, mut stdout, _) = piped.spawn()?;
+                let copied = io::copy(&mut stdout, out)?;
+                child.wait()?;
+                Ok(copied)
+            }
+            #[cfg(test)]
+            InputKind::Reader(reader) => Ok(io::copy(&mut reader.take()?, out)?),
+        }
+    }
+
+    /// Counts the lines of the file at `path` from the line `offset` starts
+    /// reading at, so the total is known long before the reader gets to the
+    /// end. A file that can't be counted is just read without one.
+    fn spawn_line_counter(path: PathBuf, offset: u64, delimiter: u8, tx: InputSender) {
+        thread::spawn(move || {
+            let count = File::open(&path).map_err(Report::from).and_then(|file| {
+                let mut file = BufReader::new(file);
+                seek_to_line(&mut file, offset, delimiter)?;
+                count_lines(&mut file, delimiter)
+            });
+            match count {
+                Ok(count) => {
+                    let _ = tx.send(Event::LineCount(count));
+                }
+                Err(err) => log::warn!("could not count the lines of '{}': {err}", path.display()),
+            }
+        });
+    }
+
+    pub fn open(self, tx: Sender<Event>, options: &ReadOptions) -> Result<OpenedInput> {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let closed = Arc::new(AtomicBool::new(false));
+        let tx = InputSender {
+            id,
+            tx,
+            closed: closed.clone(),
+        };
+        let name = self.name();
+        // a path may name a pipe, like the `/dev/fd/63` of `least <(cmd)`,
+        // which is streamed like stdin: it has no size to count lines or
+        // seek in, and nothing to reopen when following
+        let size = self
+            .path()
+            .and_then(|path| fs::metadata(path).ok())
+            .filter(|metadata| metadata.is_file())
+            .map(|metadata| metadata.len());
+        let mut encoding = options.encoding.unwrap_or(InputEncoding::Fixed(UTF_8));
+        // a file is looked at first, as where its lines are depends on it;
+        // a stream is looked at as it is read
+        if let (InputEncoding::Auto, Some(path), Some(_)) = (encoding, self.path(), size) {
+            encoding = InputEncoding::Fixed(encoding::detect_file(path));
+        }
+        // UTF-16's lines can only be found by decoding it from the start
+        let splits_as_bytes = match encoding {
+            InputEncoding::Fixed(encoding) => encoding::splits_as_bytes(encoding),
+            InputEncoding::Auto => true,
+        };
+        let start = match size {
+            Some(_) if splits_as_bytes => options.start,
+            _ => StartPosition::Beginning,
+        };
+        let flush_interval = options.flush_interval;
+        let keep_cr = options.keep_cr;
+        let delimiter = options.delimiter;
+        let show_partial = options.show_partial;
+        let follow = self
+            .path()
+            .filter(|_| options.follow_name && size.is_some())
+            .map(Path::to_path_buf);
+        let unfollowed = Arc::new(AtomicBool::new(false));
+        let unfollowed_reader = Arc::clone(&unfollowed);
+        // started here so that a command that can't be is reported right away
+        let mut stdout = None;
+        let command_group = Arc::default();
+        if let InputKind::Piped(piped) = &self.kind {
+            let (child, out, stderr) = piped.spawn()?;
+            let last_output = Arc::new(Mutex::new(Instant::now()));
+            piped.supervise(
+                child,
+                stderr,
+                tx.clone(),
+                Arc::clone(&command_group),
+                Arc::clone(&last_output),
+            );
+            stdout = Some(PipedOutput {
+                stdout: out,
+                last_output,
+            });
+        }
+        #[cfg(test)]
+        let handed_over = match &self.kind {
+            InputKind::Reader(reader) => Some(reader.take()?),
+            _ => None,
+        };
+        if let (InputKind::OrdinaryFile(path), Some(size), true) =
+            (&self.kind, size, splits_as_bytes)
+        {
+            Self::spawn_line_counter(path.clone(), start.offset(size), delimiter, tx.clone());
+        }
         let reader = thread::spawn(move || {
-            let mut reader = match self.kind {
-                InputKind::StdIn => InputReader::new(stdin().lock(), tx),
+            let (mut reader, mut position, mut identity) = match self.kind {
+                InputKind::StdIn => (InputReader::new(stdin().lock(), tx), 0, None),
+                InputKind::Piped(_) => {
+                    let stdout = BufReader::new(stdout.unwrap());
+                    (InputReader::new(stdout, tx), 0, None)
+                }
+                #[cfg(test)]
+                InputKind::Reader(_) => (InputReader::new(handed_over.unwrap(), tx), 0, None),
                 InputKind::OrdinaryFile(path) => {
                     let file = File::open(&path).map_err(|e| {
                         let e = eyre!("'{}': {}", path.to_string_lossy(), e);
                         tx.send(Event::ReaderThreadErrReturned).unwrap();
                         e
                     })?;
-                    let metadata = file.metadata().map_err(|e| {
+                    let metadata = file.metadata().inspect_err(|_| {
                         tx.send(Event::ReaderThreadErrReturned).unwrap();
-                        e
                     })?;
                     if metadata.is_dir() {
                         tx.send(Event::ReaderThreadErrReturned).unwrap();
                         return Err(eyre!("'{}' is a directory.", path.to_string_lossy()));
                     }
-                    InputReader::new(BufReader::new(file), tx)
+                    let mut file = BufReader::new(file);
+                    let offset = seek_to_line(&mut file, start.offset(metadata.len()), delimiter)
+                        .inspect_err(|_| {
+                        tx.send(Event::ReaderThreadErrReturned).unwrap();
+                    })?;
+                    (
+                        InputReader::new(file, tx),
+                        offset,
+                        Some(FileIdentity::of(&metadata)),
+                    )
                 }
             };
 
-            let mut lines_batch = Vec::new();
-            let mut line = String::new();
-            let flush_interval = Duration::from_millis(16);
-            let mut last_flush = Instant::now();
+            let encoding = match reader.sample() {
+                Ok(head) => encoding.resolve(head),
+                Err(err) => return reader.give_up(err),
+            };
+            if encoding != UTF_8 {
+                let _ = reader.tx.send(Event::Encoding(encoding));
+            }
+            // lines that can't be split as bytes are split once decoded, and
+            // are then UTF-8
+            let decode = (!encoding::splits_as_bytes(encoding)).then_some(encoding);
+            if let Some(encoding) = decode {
+                position += reader.decode(encoding);
+            }
+            // decoded lines are as long as they were before decoding
+            let raw_len = |bytes: &[u8]| match decode {
+                Some(encoding) => encoding::encoded_len(encoding, bytes),
+                None => bytes.len() as u64,
+            };
+            // a file is only seen to be cut short when it is known how much
+            // of it has been read
+            let offsets_known = encoding::has_byte_offsets(encoding);
+            let encoding = if decode.is_some() { UTF_8 } else { encoding };
+
+            // the NULs between records don't make the input binary
+            let sniff = |sample: &[u8]| match delimiter {
+                0 => looks_binary(
+                    &sample
+                        .iter()
+                        .map(|&b| if b == 0 { b'\n' } else { b })
+                        .collect::<Vec<_>>(),
+                ),
+                _ => looks_binary(sample),
+            };
+            let binary = match reader.sample() {
+                Ok(sample) => sniff(sample),
+                Err(err) => return reader.give_up(err),
+            };
+            if binary {
+                let _ = reader.tx.send(Event::Binary);
+            }
+
+            let mut batch = LineBatch::new(reader.tx.clone(), flush_interval);
+            // most inputs are small or come through pipes a little at a time,
+            // so a block only grows while reads keep filling it
+            let mut block = vec![0; InputReader::MIN_BLOCK_SIZE];
+            // the start of a line whose end hasn't been read yet
+            let mut partial = Vec::new();
+            let mut sent_eof = false;
+            let mut watch = follow.as_deref().and_then(FileWatch::new);
             loop {
-                let reached_eof =
-                    reader
-                        .read_line(&mut line)
-                        .map(|size| size == 0)
-                        .map_err(|e| {
-                            reader.tx.send(Event::ReaderThreadErrReturned).unwrap();
-                            e
-                        })?;
-                if reached_eof {
-                    break;
+                let size = match reader.read_block(&mut block) {
+                    Ok(size) => size,
+                    // what has been read so far is still worth paging
+                    Err(err) => {
+                        let _ = reader
+                            .tx
+                            .send(Event::ReadWarning(format!("Stopped reading: {err}")));
+                        break;
+                    }
+                };
+                if size == 0 {
+                    let (Some(path), Some(current)) = (&follow, identity) else {
+                        break;
+                    };
+                    // what has been written so far is all there is for now
+                    batch.flush();
+                    if !sent_eof {
+                        let _ = reader.tx.send(Event::Eof);
+                        sent_eof = true;
+                    }
+                    if unfollowed_reader.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    match &mut watch {
+                        Some(watch) => watch.wait(InputReader::WATCH_TIMEOUT),
+                        None => thread::sleep(InputReader::FOLLOW_INTERVAL),
+                    }
+                    if reader.tx.is_closed() {
+                        return Ok(());
+                    }
+                    let read = offsets_known.then(|| position + raw_len(&partial));
+                    if let Some((file, marker)) = reopen_if_replaced(path, current, read) {
+                        if !partial.is_empty() {
+                            let line = decode_line(encoding, &partial);
+                            batch.push(
+                                Arc::from(trim_line_ending(&line, delimiter, keep_cr)),
+                                position,
+                            );
+                            partial.clear();
+                        }
+                        batch.push(Arc::from(marker), 0);
+                        identity = file.metadata().ok().map(|m| FileIdentity::of(&m));
+                        reader.inner = Box::new(BufReader::new(file));
+                        position = 0;
+                        if let Some(encoding) = decode {
+                            position += reader.decode(encoding);
+                        }
+                    }
+                    continue;
                 }
-                lines_batch.push(line.clone());
-                line.clear();
-
-                if last_flush.elapsed() >= flush_interval && !lines_batch.is_empty() {
-                    let _ = reader
-                        .tx
-                        .send(Event::NewLines(std::mem::take(&mut lines_batch)));
-                    last_flush = Instant::now();
+                // nobody is looking at the lines of a closed input
+                if reader.tx.is_closed() {
+                    return Ok(());
+                }
+                let data = &block[..size];
+                let mut start = 0;
+                for newline in newline_offsets(data, delimiter) {
+                    let bytes = &data[start..=newline];
+                    let (line, len) = if partial.is_empty() {
+                        (decode_line(encoding, bytes).into_owned(), raw_len(bytes))
+                    } else {
+                        partial.extend_from_slice(bytes);
+                        let line = decode_line(encoding, &partial).into_owned();
+                        let len = raw_len(&partial);
+                        partial.clear();
+                        (line, len)
+                    };
+                    batch.push(
+                        Arc::from(trim_line_ending(&line, delimiter, keep_cr)),
+                        position,
+                    );
+                    position += len;
+                    start = newline + 1;
+                }
+                partial.extend_from_slice(&data[start..]);
+                // a short read used up what was there, and the next one may
+                // block until a slow writer gets going again
+                if size < block.len() {
+                    batch.flush();
+                    if show_partial && !partial.is_empty() {
+                        let line = decode_line(encoding, &partial);
+                        let line = Arc::from(trim_line_ending(&line, delimiter, keep_cr));
+                        let _ = reader.tx.send(Event::PartialLine(line, position));
+                    }
+                } else if block.len() < InputReader::BLOCK_SIZE {
+                    block.resize(block.len() * 2, 0);
                 }
             }
-            if !lines_batch.is_empty() {
-                let _ = reader
-                    .tx
-                    .send(Event::NewLines(std::mem::take(&mut lines_batch)));
+            if !partial.is_empty() {
+                let line = decode_line(encoding, &partial);
+                batch.push(
+                    Arc::from(trim_line_ending(&line, delimiter, keep_cr)),
+                    position,
+                );
             }
-            let _ = reader.tx.send(Event::EOF);
+            batch.flush();
+            let _ = reader.tx.send(Event::Eof);
             Ok(())
         });
 
         Ok(OpenedInput {
-            reader,
+            reader: Some(reader),
+            id,
+            closed,
+            unfollowed,
+            command_group,
+            name,
+            start,
             lines: Vec::new(),
+            offsets: Vec::new(),
+            size,
             reached_eof: false,
             current_total_lines: 0,
+            counted_lines: None,
+            partial: false,
+            filter: None,
+            squeeze: false,
+            span_cache: RefCell::default(),
+            row_cache: RefCell::default(),
+            highlighter: None,
+            diff: DiffMode::Never,
+            max_lines: options.max_lines,
+            evicted: 0,
+            evicted_rows: 0,
+            encoding: UTF_8,
         })
     }
 }
 
+/// Tells files apart regardless of their names, to notice a followed file
+/// being replaced by a new one of the same name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileIdentity {
+    dev: u64,
+    ino: u64,
+}
+
+impl FileIdentity {
+    fn of(metadata: &fs::Metadata) -> Self {
+        Self {
+            dev: metadata.dev(),
+            ino: metadata.ino(),
+        }
+    }
+}
+
+/// Opens the file at `path` anew if it is no longer the file `current` that
+/// `read` bytes were read from: it has been rotated away, or, when `read` is
+/// known, truncated. Also returns the line that marks the break in the
+/// input. While nothing is at `path`, as between a rotation and the new file
+/// being created, the old file is kept.
+fn reopen_if_replaced(
+    path: &Path,
+    current: FileIdentity,
+    read: Option<u64>,
+) -> Option<(File, &'static str)> {
+    let metadata = fs::metadata(path).ok()?;
+    let marker = if FileIdentity::of(&metadata) != current {
+        "[file rotated]"
+    } else if read.is_some_and(|read| metadata.len() < read) {
+        "[file truncated]"
+    } else {
+        return None;
+    };
+    Some((File::open(path).ok()?, marker))
+}
+
+/// Moves `file` to the first line that begins at or after `offset`, returning
+/// where that line starts.
+fn seek_to_line(file: &mut BufReader<File>, offset: u64, delimiter: u8) -> Result<u64> {
+    if offset == 0 {
+        return Ok(0);
+    }
+    file.seek(SeekFrom::Start(offset - 1))?;
+    let skipped = file.skip_until(delimiter)?;
+    Ok(offset - 1 + skipped as u64)
+}
+
+/// Sends the events of one opened input, tagged with its id so that those of
+/// an input closed since can be told apart.
+#[derive(Clone)]
+struct InputSender {
+    id: u64,
+    tx: Sender<Event>,
+    closed: Arc<AtomicBool>,
+}
+
+impl InputSender {
+    fn send(&self, event: Event) -> std::result::Result<(), SendError<Event>> {
+        self.tx.send(Event::Input(self.id, Box::new(event)))
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::Relaxed)
+    }
+}
+
 pub struct OpenedInput {
-    pub reader: JoinHandle<Result<()>>,
-    lines: Vec<String>,
+    /// Until it is joined, see [`Self::join_reader`].
+    reader: Option<JoinHandle<Result<()>>>,
+    id: u64,
+    /// Set once the input is closed, so its reader can stop early.
+    closed: Arc<AtomicBool>,
+    /// Set once a followed input is to be read only to its end.
+    unfollowed: Arc<AtomicBool>,
+    /// The process group of a command piped to, while it runs.
+    command_group: Arc<Mutex<Option<i32>>>,
+    name: String,
+    start: StartPosition,
+    /// Shared rather than owned so the match counter can be handed lines
+    /// without copying them.
+    lines: Vec<Arc<str>>,
+    /// Byte offset in the input where each line starts.
+    offsets: Vec<u64>,
+    /// Size in bytes of a regular file.
+    size: Option<u64>,
     reached_eof: bool,
     current_total_lines: usize,
+    /// Lines in the whole input as counted up front, before they are read.
+    counted_lines: Option<usize>,
+    /// The last line is only the start of one, see [`Event::PartialLine`].
+    partial: bool,
+    /// The rows shown, when they aren't just the lines read.
+    filter: Option<FilterView>,
+    /// Whether runs of blank lines are squeezed into one.
+    squeeze: bool,
+    span_cache: RefCell<SpanCache>,
+    row_cache: RefCell<RowCache>,
+    highlighter: Option<RefCell<Highlighter>>,
+    /// Whether lines are colored as a diff, `Auto` until enough have been
+    /// read to tell.
+    diff: DiffMode,
+    max_lines: Option<usize>,
+    /// Lines dropped from the front to keep within `max_lines`.
+    evicted: usize,
+    /// Rows of the current view dropped along with them since the app last
+    /// asked, see [`Self::take_evicted_rows`].
+    evicted_rows: usize,
+    /// What the lines were decoded from, UTF-8 until the reader says.
+    encoding: &'static Encoding,
+}
+
+/// The spans of recently rendered lines, so that redrawing a screen doesn't
+/// parse its lines all over again. Read lines never change, so entries only
+/// go stale when the options they were made with do.
+#[derive(Default)]
+struct SpanCache {
+    /// The options the entries were made with that change spans.
+    key: SpanKey,
+    spans: HashMap<usize, Vec<Span<'static>>>,
+}
+
+/// `styled`, `show_tabs`, `show_whitespace`, `form_feed`, `vertical_tab`,
+/// `control_chars` and `tab_width` of [`RenderOptions`].
+type SpanKey = (bool, bool, bool, FormFeed, VerticalTab, ControlChars, usize);
+
+impl SpanCache {
+    /// Lines kept, many screens' worth. The cache starts over once full.
+    const CAPACITY: usize = 4096;
+
+    fn key(options: &RenderOptions) -> SpanKey {
+        (
+            options.styled,
+            options.show_tabs,
+            options.show_whitespace,
+            options.form_feed,
+            options.vertical_tab,
+            options.control_chars,
+            options.tab_width,
+        )
+    }
+
+    fn get(&self, index: usize, options: &RenderOptions) -> Option<&Vec<Span<'static>>> {
+        if self.key != Self::key(options) {
+            return None;
+        }
+        self.spans.get(&index)
+    }
+
+    fn insert(&mut self, index: usize, options: &RenderOptions, spans: Vec<Span<'static>>) {
+        if self.key != Self::key(options) || self.spans.len() >= Self::CAPACITY {
+            self.key = Self::key(options);
+            self.spans.clear();
+        }
+        self.spans.insert(index, spans);
+    }
+
+    fn remove(&mut self, index: usize) {
+        self.spans.remove(&index);
+    }
+}
+
+/// How many screen rows each row of the view wraps to, and how many come
+/// before it, so that counting the rows down to a line is a lookup once they
+/// have been counted the first time. Entries go stale when the width or the
+/// options they were counted with change, or when the rows themselves do.
+#[derive(Default)]
+struct RowCache {
+    /// The width and options the entries were counted with.
+    key: Option<RowKey>,
+    /// Screen rows of each view row, 0 for those not counted yet.
+    counts: Vec<u32>,
+    /// Screen rows before each of the first view rows, starting with 0
+    /// before the first.
+    starts: Vec<usize>,
+}
+
+/// The wrap width, the [`SpanKey`], and `column_offset`, `max_columns` and
+/// `wrap_indent` of [`RenderOptions`].
+type RowKey = (usize, SpanKey, usize, Option<usize>, WrapIndent);
+
+impl RowCache {
+    /// Starts over unless the entries were counted for `width` and
+    /// `options`.
+    fn check(&mut self, width: usize, options: &RenderOptions) {
+        let key = (
+            width,
+            SpanCache::key(options),
+            options.column_offset,
+            options.max_columns,
+            options.wrap_indent,
+        );
+        if self.key != Some(key) {
+            self.key = Some(key);
+            self.clear();
+        }
+    }
+
+    fn clear(&mut self) {
+        self.counts.clear();
+        self.starts.clear();
+    }
+
+    /// Forgets the rows from `row` on.
+    fn truncate(&mut self, row: usize) {
+        self.counts.truncate(row);
+        self.starts.truncate(row + 1);
+    }
 }
 
 impl fmt::Debug for OpenedInput {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("OpenedInput")
+            .field("name", &self.name)
             .field("lines", &self.lines)
             .field("total_lines", &self.current_total_lines)
             .finish()
@@ -119,62 +977,1483 @@ impl fmt::Debug for OpenedInput {
 }
 
 impl OpenedInput {
-    fn reached_eof(&self) -> bool {
+    /// Tags the events of this input, see [`Event::Input`].
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Waits for the reader to return, as it does once it has sent
+    /// [`Event::ReaderThreadErrReturned`], and gives its error.
+    pub fn join_reader(&mut self) -> Result<()> {
+        match self.reader.take().map(JoinHandle::join) {
+            Some(Ok(res)) => res,
+            Some(Err(_)) => Err(eyre!("reader thread panicked")),
+            None => Ok(()),
+        }
+    }
+
+    /// Tells the reader to stop, as the input is no longer shown, and stops
+    /// a command piped to right away rather than at its next check.
+    pub fn close(&self) {
+        self.closed.store(true, Ordering::Relaxed);
+        let group = self
+            .command_group
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if let Some(group) = *group {
+            // SAFETY: kill takes no pointers; the group is only set while the
+            // command it belongs to hasn't been reaped
+            unsafe { libc::kill(-group, libc::SIGKILL) };
+        }
+    }
+
+    /// Tells the reader of a followed input to stop at its end rather than
+    /// wait there for more, keeping what it has read.
+    pub fn stop_following(&self) {
+        self.unfollowed.store(true, Ordering::Relaxed);
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn start(&self) -> StartPosition {
+        self.start
+    }
+
+    pub fn reached_eof(&self) -> bool {
         self.reached_eof
     }
 
-    pub fn current_total_lines(&mut self) -> usize {
+    pub fn encoding(&self) -> &'static Encoding {
+        self.encoding
+    }
+
+    /// Number of lines read so far, or of rows in the filtered view while a
+    /// filter is set. Every other line index taken by `OpenedInput` means a
+    /// row of the filtered view in that case.
+    pub fn current_total_lines(&self) -> usize {
+        match &self.filter {
+            Some(filter) => filter.rows().len(),
+            None => self.current_total_lines,
+        }
+    }
+
+    /// Number of lines read so far, regardless of any filter.
+    pub fn lines_read(&self) -> usize {
         self.current_total_lines
     }
 
+    /// Number of lines in the whole input, once it is read or counted. A file
+    /// that grew since it was counted has at least the lines read from it.
+    /// Unknown while a filter is set, as the filtered view only grows as the
+    /// lines are read.
+    pub fn total_lines(&self) -> Option<usize> {
+        if self.filter.is_some() {
+            return self.reached_eof.then_some(self.current_total_lines());
+        }
+        let read = self.evicted + self.current_total_lines;
+        if self.reached_eof {
+            return Some(read);
+        }
+        self.counted_lines.map(|count| cmp::max(count, read))
+    }
+
+    /// The number of the line shown at `row`, counting from 1 in the whole
+    /// input, unless the row is a filter separator.
+    pub fn line_number(&self, row: usize) -> Option<usize> {
+        self.line_index(row).map(|index| self.evicted + index + 1)
+    }
+
+    /// The number of the last line read, or of the last one counted up front.
+    pub fn last_line_number(&self) -> usize {
+        cmp::max(
+            self.counted_lines.unwrap_or(0),
+            self.evicted + self.lines.len(),
+        )
+    }
+
+    /// Lines dropped from the front for `--max-lines`. Line indices count
+    /// from the first line kept.
+    pub fn evicted(&self) -> usize {
+        self.evicted
+    }
+
+    /// Rows before row 0 in numbering rows as in the whole input: the lines
+    /// dropped, unless a filter is set, whose view is numbered on its own.
+    pub fn dropped_rows(&self) -> usize {
+        match self.filter {
+            Some(_) => 0,
+            None => self.evicted,
+        }
+    }
+
+    /// Rows of the current view dropped since the last call, by which rows
+    /// kept by the caller have moved up.
+    pub fn take_evicted_rows(&mut self) -> usize {
+        std::mem::take(&mut self.evicted_rows)
+    }
+
+    /// Byte offset in the input of the line shown at `row`, or of the next
+    /// line for a filter separator.
+    pub fn byte_offset(&self, row: usize) -> Option<u64> {
+        if !encoding::has_byte_offsets(self.encoding) {
+            return None;
+        }
+        let index = match &self.filter {
+            Some(filter) => filter.rows()[row..].iter().find_map(|row| match row {
+                FilterRow::Match(index) | FilterRow::Context(index) => Some(*index),
+                FilterRow::Separator => None,
+            })?,
+            None => row,
+        };
+        self.offsets.get(index).copied()
+    }
+
+    /// Index of the line read that the byte at `offset` is in: the last one
+    /// starting at or before it, so the first line for an offset before
+    /// where reading started and the last for one past those read. Only the
+    /// lines since a followed file was last rotated or truncated are of the
+    /// file there now, as the marker of that goes back to offset 0.
+    pub fn line_at_byte(&self, offset: u64) -> Option<usize> {
+        if self.offsets.is_empty() || !encoding::has_byte_offsets(self.encoding) {
+            return None;
+        }
+        let reopened = self
+            .offsets
+            .windows(2)
+            .rposition(|pair| pair[1] < pair[0])
+            .map_or(0, |before| before + 1);
+        let after = self.offsets[reopened..].partition_point(|&start| start <= offset);
+        Some(reopened + after.saturating_sub(1))
+    }
+
+    /// Size in bytes of the whole input, when it is known up front.
+    pub fn size(&self) -> Option<u64> {
+        self.size
+    }
+
+    pub fn filter(&self) -> Option<&FilterView> {
+        self.filter.as_ref()
+    }
+
+    /// Rows that are there to stay, leaving out a partial last line. It is
+    /// only ever shown unfiltered, as whether it matches may still change.
+    pub fn complete_rows(&self) -> usize {
+        match &self.filter {
+            Some(filter) => filter.rows().len(),
+            None => self.current_total_lines - usize::from(self.partial),
+        }
+    }
+
+    /// Shows only the lines `filter` keeps, or all of them again, runs of
+    /// blank lines squeezed into one either way while squeezing.
+    pub fn set_filter(&mut self, filter: Option<FilterView>) {
+        self.filter = filter
+            .or_else(|| self.squeeze.then(FilterView::all))
+            .map(|filter| filter.squeezed(self.squeeze));
+        self.row_cache.borrow_mut().clear();
+        self.extend_filter();
+    }
+
+    /// Squeezes runs of blank lines into one, or stops doing so, for the
+    /// filter set if there is one. The lines are kept as read either way,
+    /// only the view over them changes.
+    pub fn set_squeeze(&mut self, squeeze: bool) {
+        self.squeeze = squeeze;
+        let filter = self
+            .filter
+            .take()
+            .filter(|filter| filter.pattern().is_some());
+        self.set_filter(filter);
+    }
+
+    /// Whether a filter is set, rather than just a view squeezing blank
+    /// lines.
+    pub fn filtered(&self) -> bool {
+        self.filter()
+            .is_some_and(|filter| filter.pattern().is_some())
+    }
+
+    /// Forgets how lines were rendered, as they now render differently.
+    fn clear_caches(&self) {
+        self.span_cache.borrow_mut().spans.clear();
+        self.row_cache.borrow_mut().clear();
+    }
+
+    fn extend_filter(&mut self) {
+        let complete = self.lines.len() - usize::from(self.partial);
+        if let Some(filter) = &mut self.filter {
+            filter.extend(&self.lines[..complete]);
+        }
+    }
+
+    /// Drops the oldest lines once more than `max_lines` are kept. They go a
+    /// sixteenth of the limit at a time, so the lines kept aren't moved for
+    /// every line read.
+    pub fn evict(&mut self) {
+        let Some(max) = self.max_lines else {
+            return;
+        };
+        let len = self.lines.len();
+        if len <= max {
+            return;
+        }
+        let chunk = (max / 16).max(1);
+        let count = (len - max).div_ceil(chunk) * chunk;
+        self.lines.drain(..count);
+        self.offsets.drain(..count);
+        self.evicted += count;
+        self.current_total_lines = self.lines.len();
+        self.clear_caches();
+        if let Some(highlighter) = &self.highlighter {
+            highlighter.borrow_mut().forget(0);
+        }
+        self.evicted_rows += match &mut self.filter {
+            Some(filter) => filter.evict(count),
+            None => count,
+        };
+    }
+
+    /// Drops the partial last line, as the next lines read include it in
+    /// full.
+    fn drop_partial(&mut self) {
+        if !self.partial {
+            return;
+        }
+        self.partial = false;
+        self.lines.pop();
+        self.offsets.pop();
+        self.span_cache.borrow_mut().remove(self.lines.len());
+        // a filter doesn't show partial lines
+        if self.filter.is_none() {
+            self.row_cache.borrow_mut().truncate(self.lines.len());
+        }
+        if let Some(highlighter) = &self.highlighter {
+            highlighter.borrow_mut().forget(self.lines.len());
+        }
+    }
+
+    /// Colors the lines as `highlighter` says, in place of their own styling.
+    pub fn set_highlighter(&mut self, highlighter: Option<Highlighter>) {
+        self.highlighter = highlighter.map(RefCell::new);
+        self.clear_caches();
+    }
+
+    pub fn set_diff(&mut self, diff: DiffMode) {
+        self.diff = diff;
+        self.detect_diff();
+        self.clear_caches();
+    }
+
+    /// Settles whether an input is a diff for `--diff=auto` once enough of it
+    /// has been read to tell.
+    fn detect_diff(&mut self) {
+        if self.diff != DiffMode::Auto
+            || (self.lines.len() < diff::SNIFF_LINES && !self.reached_eof)
+        {
+            return;
+        }
+        if diff::looks_like_diff(&self.lines) {
+            self.diff = DiffMode::Always;
+            self.clear_caches();
+        } else {
+            self.diff = DiffMode::Never;
+        }
+    }
+
+    /// The input line shown at `row`, unless the row is a filter separator.
+    pub fn line_index(&self, row: usize) -> Option<usize> {
+        match &self.filter {
+            Some(filter) => match filter.rows()[row] {
+                FilterRow::Match(index) | FilterRow::Context(index) => Some(index),
+                FilterRow::Separator => None,
+            },
+            None => Some(row),
+        }
+    }
+
+    /// The first row showing line `index` or one after it.
+    pub fn row_of_line(&self, index: usize) -> usize {
+        let Some(filter) = &self.filter else {
+            return index;
+        };
+        let rows = filter.rows();
+        rows.iter()
+            .position(|row| match row {
+                FilterRow::Match(line) | FilterRow::Context(line) => *line >= index,
+                FilterRow::Separator => false,
+            })
+            .unwrap_or(rows.len())
+    }
+
     pub fn handle_event(&mut self, event: Event) -> Result<()> {
         match event {
-            Event::NewLines(lines) => {
-                log::debug!("received new lines {}", lines.len());
-                self.lines.extend(lines);
+            Event::Input(id, event) if id == self.id => return self.handle_event(*event),
+            // left over from an input closed since
+            Event::Input(..) => {}
+            Event::NewLines(lines, offsets) => {
+                self.push_lines(lines, offsets);
+                self.evict();
+            }
+            Event::PartialLine(line, offset) => {
+                self.drop_partial();
+                self.lines.push(line);
+                self.offsets.push(offset);
+                self.partial = true;
                 self.current_total_lines = self.lines.len();
             }
-            Event::EOF => self.reached_eof = true,
+            Event::Eof => {
+                self.reached_eof = true;
+                self.detect_diff();
+            }
+            Event::LineCount(count) => self.counted_lines = Some(count),
+            Event::Encoding(encoding) => self.encoding = encoding,
             Event::Err(err) => return Err(err),
+            Event::ReadWarning(warning) => log::warn!("{}: {warning}", self.name),
             _ => unreachable!(),
         }
         Ok(())
     }
 
-    pub fn lines(&mut self, line_number_start: usize, line_size: usize) -> Result<Vec<Line<'_>>> {
+    /// Adds newly read lines, keeping all of them until [`Self::evict`] is
+    /// called, even past `max_lines`.
+    pub fn push_lines(&mut self, lines: Vec<Arc<str>>, offsets: Vec<u64>) {
+        log::debug!("received new lines {}", lines.len());
+        self.drop_partial();
+        self.lines.extend(lines);
+        self.offsets.extend(offsets);
+        self.current_total_lines = self.lines.len();
+        self.extend_filter();
+        self.detect_diff();
+    }
+
+    /// Returns the index of the first line matching `search`, scanning from
+    /// `from` (inclusive) in the given direction.
+    pub fn find_match(
+        &self,
+        search: &Search,
+        from: usize,
+        direction: SearchDirection,
+    ) -> Option<usize> {
+        let is_match = |row: usize| {
+            self.line_index(row)
+                .is_some_and(|index| search.is_match(&strip_styling(&self.lines[index])))
+        };
+        let total = self.current_total_lines();
+        match direction {
+            SearchDirection::Forward => (from..total).find(|&row| is_match(row)),
+            SearchDirection::Backward => {
+                let from = cmp::min(from, total.checked_sub(1)?);
+                (0..=from).rev().find(|&row| is_match(row))
+            }
+        }
+    }
+
+    /// The screen columns of the first match of `search` at `row`, counted
+    /// before any horizontal scrolling, if the line as shown has one.
+    pub fn match_columns(
+        &self,
+        search: &Search,
+        row: usize,
+        options: &RenderOptions,
+    ) -> Option<Range<usize>> {
+        let index = self.line_index(row)?;
+        let text = Line::from(self.spans(index, options)).to_string();
+        let range = search.pattern.match_ranges(&text).into_iter().next()?;
+        let start = Line::raw(&text[..range.start]).width();
+        Some(start..start + Line::raw(&text[range]).width())
+    }
+
+    /// The lines shown at the rows in `rows`, with `None` for filter
+    /// separators.
+    pub fn row_lines(&self, rows: Range<usize>) -> Vec<Option<Arc<str>>> {
+        let end = cmp::min(rows.end, self.current_total_lines());
+        (rows.start..end)
+            .map(|row| self.line_index(row).map(|index| self.lines[index].clone()))
+            .collect()
+    }
+
+    /// Returns the row of the blank line ending the paragraph after `from`, or
+    /// starting the one before it, like vi's `}` and `{`. Runs of blank lines
+    /// count as one, and lines of only whitespace count as blank.
+    pub fn find_paragraph_boundary(
+        &self,
+        from: usize,
+        direction: SearchDirection,
+    ) -> Option<usize> {
+        let is_blank = |row: usize| {
+            self.line_index(row)
+                .is_some_and(|index| self.lines[index].trim().is_empty())
+        };
+        let total = self.current_total_lines();
+        match direction {
+            SearchDirection::Forward => {
+                (from + 1..total).find(|&row| is_blank(row) && !is_blank(row - 1))
+            }
+            SearchDirection::Backward => {
+                let from = cmp::min(from, total.checked_sub(1)?);
+                (0..from)
+                    .rev()
+                    .find(|&row| is_blank(row) && !is_blank(row + 1))
+            }
+        }
+    }
+
+    /// Writes every line read so far to `writer`, returning how many were
+    /// written. Styling is stripped from the lines unless `raw` is set.
+    pub fn write_lines(&self, writer: &mut impl Write, raw: bool) -> io::Result<usize> {
+        for line in &self.lines {
+            if raw {
+                writeln!(writer, "{line}")?;
+            } else {
+                writeln!(writer, "{}", strip_styling(line))?;
+            }
+        }
+        Ok(self.lines.len())
+    }
+
+    /// Writes the lines shown from row `start` on as they were read,
+    /// returning the row after the last one written.
+    pub fn write_raw_lines(&self, start: usize, writer: &mut impl Write) -> io::Result<usize> {
+        let total = self.current_total_lines();
+        for index in (start..total).filter_map(|row| self.line_index(row)) {
+            writeln!(writer, "{}", self.lines[index])?;
+        }
+        Ok(total)
+    }
+
+    /// Writes the text of the lines shown from row `start` on without
+    /// styling, returning the row after the last one written.
+    pub fn write_plain_lines(
+        &self,
+        start: usize,
+        writer: &mut impl Write,
+        options: &RenderOptions,
+    ) -> io::Result<usize> {
+        let total = self.current_total_lines();
+        for index in (start..total).filter_map(|row| self.line_index(row)) {
+            writeln!(writer, "{}", Line::from(self.parse_spans(index, options)))?;
+        }
+        Ok(total)
+    }
+
+    pub fn lines(
+        &mut self,
+        line_number_start: usize,
+        line_size: usize,
+        options: &RenderOptions,
+    ) -> Result<Vec<Line<'_>>> {
         log::trace!("create lines {line_number_start} {line_size}");
 
-        if line_size == 0 || self.lines.len() < line_number_start {
+        let total = self.current_total_lines();
+        if line_size == 0 || total < line_number_start {
             return Ok(Vec::new());
         }
-        let line_size = cmp::min(line_size, self.lines.len() - line_number_start);
-        let mut lines = Vec::with_capacity(line_size);
-        for line in self.lines[line_number_start..line_number_start + line_size].iter() {
-            let spans = parse_styled_spans(line.clone().into_bytes());
-            lines.push(spans);
+        let line_size = cmp::min(line_size, total - line_number_start);
+        Ok((line_number_start..line_number_start + line_size)
+            .map(|row| self.line(row, options))
+            .collect())
+    }
+
+    fn line(&self, row: usize, options: &RenderOptions) -> Line<'static> {
+        let Some(filter) = &self.filter else {
+            return self.cut(Line::from(self.spans(row, options)), options);
+        };
+        match filter.rows()[row] {
+            FilterRow::Match(index) => {
+                let line = Line::from(self.spans(index, options));
+                let Some(pattern) = filter.pattern() else {
+                    return self.cut(line, options);
+                };
+                let ranges = pattern.match_ranges(&line.to_string());
+                self.cut(
+                    highlight_ranges(line, &ranges, Style::new().reversed()),
+                    options,
+                )
+            }
+            FilterRow::Context(index) => self
+                .cut(Line::from(self.spans(index, options)), options)
+                .dim(),
+            FilterRow::Separator => Line::raw("--").dim(),
+        }
+    }
+
+    /// The first `count` lines of the input, as far as they are read,
+    /// regardless of any filter.
+    pub fn header(&self, count: usize, options: &RenderOptions) -> Vec<Line<'static>> {
+        (0..cmp::min(count, self.lines_read()))
+            .map(|index| self.cut(Line::from(self.spans(index, options)), options))
+            .collect()
+    }
+
+    /// Applies the horizontal scroll offset and column limit to a line.
+    fn cut(&self, line: Line<'static>, options: &RenderOptions) -> Line<'static> {
+        if options.column_offset == 0 && options.max_columns.is_none() {
+            return line;
+        }
+        slice_columns(line, options.column_offset, options.max_columns)
+    }
+
+    fn spans(&self, index: usize, options: &RenderOptions) -> Vec<Span<'static>> {
+        let mut cache = self.span_cache.borrow_mut();
+        if let Some(spans) = cache.get(index, options) {
+            return spans.clone();
         }
+        let spans = self.parse_spans(index, options);
+        cache.insert(index, options, spans.clone());
+        spans
+    }
+
+    /// Turns line `index` into spans without going through the cache, for
+    /// lines that are only looked at once.
+    fn parse_spans(&self, index: usize, options: &RenderOptions) -> Vec<Span<'static>> {
+        let line = &self.lines[index];
+        let spans = if let Some(highlighter) = &self.highlighter {
+            highlighter.borrow_mut().highlight(&self.lines, index)
+        } else if options.styled {
+            parse_styled_spans(line.as_bytes().to_vec())
+        } else {
+            vec![Span::raw(line.to_string())]
+        };
+        let spans = match diff::line_style(line).filter(|_| self.diff == DiffMode::Always) {
+            // the line's own colors win
+            Some(style) => spans
+                .into_iter()
+                .map(|span| {
+                    let span_style = style.patch(span.style);
+                    span.style(span_style)
+                })
+                .collect(),
+            None => spans,
+        };
+        let spans = expand_page_controls(spans, options.form_feed, options.vertical_tab);
+        let mut spans = expand_control_chars(spans, options.control_chars);
+        if options.show_whitespace {
+            spans = mark_trailing_spaces(spans);
+        }
+        expand_tabs(
+            spans,
+            options.tab_width,
+            options.show_tabs || options.show_whitespace,
+        )
+    }
+
+    /// Widest of the `size` rows from `start` on, in columns, before any
+    /// horizontal scrolling or column limit.
+    pub fn max_width(&self, start: usize, size: usize, options: &RenderOptions) -> usize {
+        let end = cmp::min(start.saturating_add(size), self.current_total_lines());
+        (start..end)
+            .filter_map(|row| self.line_index(row))
+            .map(|index| Line::from(self.spans(index, options)).width())
+            .max()
+            .unwrap_or(0)
+    }
 
-        Ok(lines.iter().map(|line| Line::from(line.clone())).collect())
+    /// Number of screen rows line `row` takes up when wrapped at `width`
+    /// columns.
+    pub fn display_rows(&self, row: usize, width: usize, options: &RenderOptions) -> usize {
+        let mut cache = self.row_cache.borrow_mut();
+        cache.check(width, options);
+        if let Some(&count) = cache.counts.get(row).filter(|&&count| count > 0) {
+            return count as usize;
+        }
+        let count = match self.line_index(row) {
+            Some(index) => {
+                // lines counted far from the screen only pass through
+                let spans = self.span_cache.borrow().get(index, options).cloned();
+                let spans = spans.unwrap_or_else(|| self.parse_spans(index, options));
+                let line = self.cut(Line::from(spans), options);
+                wrap_line(line, width, options.wrap_indent).len()
+            }
+            None => 1,
+        };
+        if cache.counts.len() <= row {
+            cache.counts.resize(row + 1, 0);
+        }
+        cache.counts[row] = count.try_into().unwrap_or(u32::MAX);
+        count
+    }
+
+    /// Number of screen rows the rows before `row` take up when wrapped at
+    /// `width` columns. Only the rows not counted by an earlier call are
+    /// wrapped.
+    pub fn rows_before(&self, row: usize, width: usize, options: &RenderOptions) -> usize {
+        let mut cache = self.row_cache.borrow_mut();
+        cache.check(width, options);
+        if cache.starts.is_empty() {
+            cache.starts.push(0);
+        }
+        while cache.starts.len() <= row {
+            let counted = cache.starts.len() - 1;
+            let before = cache.starts[counted];
+            drop(cache);
+            let rows = self.display_rows(counted, width, options);
+            cache = self.row_cache.borrow_mut();
+            cache.starts.push(before + rows);
+        }
+        cache.starts[row]
+    }
+
+    /// Like [`Self::rows_before`], but wraps at most `budget` lines not
+    /// counted by an earlier call, and is `None` if that doesn't get as far
+    /// as `row`. A call after another picks up where it stopped, so that a
+    /// long input is counted a chunk at a time.
+    pub fn rows_before_within(
+        &self,
+        row: usize,
+        width: usize,
+        options: &RenderOptions,
+        budget: usize,
+    ) -> Option<usize> {
+        let counted = {
+            let mut cache = self.row_cache.borrow_mut();
+            cache.check(width, options);
+            cache.starts.len().saturating_sub(1)
+        };
+        let reach = cmp::min(row, counted.saturating_add(budget));
+        let rows = self.rows_before(reach, width, options);
+        (reach == row).then_some(rows)
+    }
+
+    /// The last line that can be at the top of a `height` rows high screen
+    /// while the screen stays filled. `wrap_width` is the width lines are
+    /// wrapped at, if they are wrapped.
+    pub fn max_top_line(
+        &self,
+        height: usize,
+        wrap_width: Option<usize>,
+        options: &RenderOptions,
+    ) -> usize {
+        let total = self.current_total_lines();
+        let Some(width) = wrap_width else {
+            return total.saturating_sub(height);
+        };
+        let mut top = total;
+        let mut rows = 0;
+        while top > 0 {
+            rows += self.display_rows(top - 1, width, options);
+            if rows > height {
+                break;
+            }
+            top -= 1;
+        }
+        // a last line taller than the screen still has to be reachable
+        cmp::min(top, total.saturating_sub(1))
     }
 }
 
 pub struct InputReader {
     inner: Box<dyn BufRead>,
-    tx: Sender<Event>,
+    tx: InputSender,
 }
 
 impl InputReader {
-    pub fn new<R: BufRead + 'static>(reader: R, tx: Sender<Event>) -> InputReader {
+    /// Most bytes handed over by one read. Blocks this big are split into
+    /// lines on several threads.
+    const BLOCK_SIZE: usize = 8 << 20;
+    /// Bytes read at first, doubled up to `BLOCK_SIZE` for as long as reads
+    /// fill the block.
+    const MIN_BLOCK_SIZE: usize = 64 << 10;
+    /// How often a followed file is checked for more lines.
+    const FOLLOW_INTERVAL: Duration = Duration::from_millis(250);
+    /// How long a watched file goes unchecked when nothing is heard of it,
+    /// in case a change went unnoticed, as on network filesystems, and so
+    /// that a closed input's reader gets to stop.
+    const WATCH_TIMEOUT: Duration = Duration::from_secs(2);
+    /// Times a read that failed in a way that may pass is tried again.
+    const READ_RETRIES: u32 = 5;
+    /// How long to wait before trying a failed read again, longer with each
+    /// try.
+    const RETRY_DELAY: Duration = Duration::from_millis(100);
+
+    fn new<R: BufRead + 'static>(reader: R, tx: InputSender) -> InputReader {
         Self {
             inner: Box::new(reader),
             tx,
         }
     }
 
-    pub fn read_line(&mut self, buf: &mut String) -> Result<usize> {
-        let res = self.inner.read_line(buf)?;
-        log::info!("read line {:?}", buf);
-        *buf = buf.replace('\t', "  ");
-        Ok(res)
+    /// Decodes what is read from now on from `encoding` into UTF-8, see
+    /// [`DecodingReader`]. Returns the length of the byte order mark that is
+    /// dropped, which is where the first line really starts.
+    fn decode(&mut self, encoding: &'static Encoding) -> u64 {
+        let bom = self
+            .inner
+            .fill_buf()
+            .map_or(0, |head| encoding::bom_len(encoding, head));
+        let inner = std::mem::replace(&mut self.inner, Box::new(io::empty()));
+        self.inner = Box::new(DecodingReader::new(inner, encoding));
+        bom
+    }
+
+    /// The first buffered bytes of the input, without consuming them.
+    /// Transient errors are retried as by [`Self::read_block`].
+    pub fn sample(&mut self) -> Result<&[u8]> {
+        self.retrying(|inner| inner.fill_buf().map(|head| head.len()))?;
+        // buffered by now, so this doesn't read again
+        Ok(self.inner.fill_buf()?)
+    }
+
+    /// Reads whatever is available into `buf`, up to its size, returning 0
+    /// at the end of the input. Transient errors are retried a few times,
+    /// with a warning sent for each.
+    pub fn read_block(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let size = self.retrying(|inner| inner.read(buf))?;
+        log::debug!("read {size} bytes");
+        Ok(size)
+    }
+
+    /// Ends an input that failed to be read before any of it was, as one
+    /// with nothing in it, with a warning for why, rather than failing the
+    /// reader.
+    fn give_up(&self, err: Report) -> Result<()> {
+        let _ = self
+            .tx
+            .send(Event::ReadWarning(format!("Stopped reading: {err}")));
+        let _ = self.tx.send(Event::Eof);
+        Ok(())
+    }
+
+    fn retrying<T>(
+        &mut self,
+        mut read: impl FnMut(&mut dyn BufRead) -> io::Result<T>,
+    ) -> Result<T> {
+        let mut retries = 0;
+        loop {
+            match read(&mut *self.inner) {
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) if is_transient(&e) && retries < Self::READ_RETRIES => {
+                    retries += 1;
+                    let _ = self.tx.send(Event::ReadWarning(format!(
+                        "Read failed, trying again ({retries}/{}): {e}",
+                        Self::READ_RETRIES
+                    )));
+                    thread::sleep(Self::RETRY_DELAY * retries);
+                }
+                res => return Ok(res?),
+            }
+        }
+    }
+}
+
+/// Whether a failed read may work when tried again, as after a network
+/// filesystem timing out, unlike one of a file that is gone.
+fn is_transient(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+    ) || err.raw_os_error() == Some(libc::EIO)
+}
+
+/// Read lines on their way to the app, sent in batches to keep the number of
+/// events down.
+struct LineBatch {
+    tx: InputSender,
+    lines: Vec<Arc<str>>,
+    offsets: Vec<u64>,
+    flush_interval: Duration,
+    sent_lines: usize,
+    last_flush: Option<Instant>,
+}
+
+impl LineBatch {
+    fn new(tx: InputSender, flush_interval: Duration) -> Self {
+        Self {
+            tx,
+            lines: Vec::new(),
+            offsets: Vec::new(),
+            flush_interval,
+            sent_lines: 0,
+            // the first line goes out at once
+            last_flush: None,
+        }
+    }
+
+    /// Adds a line starting at byte `offset`, sending the batch if it has
+    /// been held long enough.
+    fn push(&mut self, line: Arc<str>, offset: u64) {
+        self.lines.push(line);
+        self.offsets.push(offset);
+        let interval = if self.sent_lines < ReadOptions::EAGER_LINES {
+            self.flush_interval.min(ReadOptions::DEFAULT_FLUSH_INTERVAL)
+        } else {
+            self.flush_interval
+        };
+        if self
+            .last_flush
+            .is_none_or(|last| last.elapsed() >= interval)
+        {
+            self.flush();
+        }
+    }
+
+    fn flush(&mut self) {
+        if self.lines.is_empty() {
+            return;
+        }
+        self.sent_lines += self.lines.len();
+        let _ = self.tx.send(Event::NewLines(
+            std::mem::take(&mut self.lines),
+            std::mem::take(&mut self.offsets),
+        ));
+        self.last_flush = Some(Instant::now());
+    }
+}
+
+#[cfg(test)]
+impl OpenedInput {
+    /// An input that has already been read completely, for tests.
+    pub fn from_lines(lines: &[&str]) -> Self {
+        OpenedInput {
+            reader: Some(thread::spawn(|| Ok(()))),
+            id: u64::MAX,
+            closed: Arc::default(),
+            unfollowed: Arc::default(),
+            command_group: Arc::default(),
+            name: "test".to_string(),
+            start: StartPosition::Beginning,
+            lines: lines.iter().map(|&line| Arc::from(line)).collect(),
+            offsets: lines
+                .iter()
+                .scan(0, |offset, line| {
+                    let start = *offset;
+                    *offset += line.len() as u64 + 1;
+                    Some(start)
+                })
+                .collect(),
+            size: Some(lines.iter().map(|line| line.len() as u64 + 1).sum()),
+            reached_eof: true,
+            current_total_lines: lines.len(),
+            counted_lines: None,
+            partial: false,
+            filter: None,
+            squeeze: false,
+            span_cache: RefCell::default(),
+            row_cache: RefCell::default(),
+            highlighter: None,
+            diff: DiffMode::Never,
+            max_lines: None,
+            evicted: 0,
+            evicted_rows: 0,
+            encoding: UTF_8,
+        }
+    }
+
+    /// An input nothing has been read from yet, whose lines and end tests
+    /// send as events.
+    pub fn pending() -> Self {
+        OpenedInput {
+            size: None,
+            reached_eof: false,
+            ..Self::from_lines(&[])
+        }
+    }
+
+    /// Whether the reader is done, having read all it is going to.
+    pub fn reader_finished(&self) -> bool {
+        self.reader.as_ref().is_none_or(JoinHandle::is_finished)
+    }
+
+    /// A pending input that keeps at most `max_lines` lines.
+    pub fn pending_capped(max_lines: usize) -> Self {
+        OpenedInput {
+            max_lines: Some(max_lines),
+            ..Self::pending()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        fs,
+        io::{self, BufRead, BufReader, Read, Write},
+        process::{Command, Stdio},
+        sync::{Arc, mpsc},
+        time::{Duration, Instant},
+    };
+
+    use ratatui::style::{Color, Style};
+
+    use crate::{
+        diff::DiffMode,
+        event::Event,
+        filter::FilterView,
+        input::{
+            FileIdentity, Input, InputReader, InputSender, LineBatch, OpenedInput, ReadOptions,
+            RenderOptions, StartPosition, reopen_if_replaced,
+        },
+        search::Pattern,
+        temp_path::TempPath,
+    };
+
+    #[test]
+    fn test_byte_offsets() {
+        let path = TempPath::new("offsets");
+        fs::write(&path, "a\r\nbb\nccc").unwrap();
+        let (tx, rx) = mpsc::channel();
+        let options = ReadOptions {
+            start: StartPosition::Byte(3),
+            ..Default::default()
+        };
+        let mut input = Input::ordinary_file(&path).open(tx, &options).unwrap();
+        // both the reader and the line counter are done once the channel is
+        // closed
+        for event in rx {
+            input.handle_event(event).unwrap();
+        }
+
+        assert_eq!(input.lines, [Arc::from("bb"), Arc::from("ccc")]);
+        // the count agrees with the reader on the last line having no newline
+        assert_eq!(input.counted_lines, Some(2));
+        assert_eq!(input.byte_offset(0), Some(3));
+        assert_eq!(input.byte_offset(1), Some(6));
+        assert_eq!(input.size(), Some(9));
+        assert_eq!(input.line_at_byte(0), Some(0));
+        assert_eq!(input.line_at_byte(5), Some(0));
+        assert_eq!(input.line_at_byte(6), Some(1));
+        assert_eq!(input.line_at_byte(100), Some(1));
+
+        // a rotated file is read from its start again, behind a marker
+        let mut input = OpenedInput::pending();
+        let lines = ["a", "bb", "[file rotated]", "c", "dd"]
+            .map(Arc::from)
+            .into();
+        input
+            .handle_event(Event::NewLines(lines, vec![0, 2, 0, 0, 2]))
+            .unwrap();
+        assert_eq!(input.line_at_byte(0), Some(3));
+        assert_eq!(input.line_at_byte(1), Some(3));
+        assert_eq!(input.line_at_byte(2), Some(4));
+        assert_eq!(input.line_at_byte(100), Some(4));
+    }
+
+    #[test]
+    fn test_growing_blocks() {
+        let path = TempPath::new("blocks");
+        let content: String = (0..400_000).map(|i| format!("{i}\n")).collect();
+        fs::write(&path, &content).unwrap();
+        let (tx, rx) = mpsc::channel();
+        let mut input = Input::ordinary_file(&path)
+            .open(tx, &ReadOptions::default())
+            .unwrap();
+        for event in rx {
+            input.handle_event(event).unwrap();
+        }
+
+        // lines split across the blocks, as they grow, come out whole
+        assert_eq!(input.lines.len(), 400_000);
+        assert_eq!(input.lines[123_456], Arc::from("123456"));
+        assert_eq!(input.byte_offset(399_999), Some(content.len() as u64 - 7));
+    }
+
+    #[test]
+    fn test_reopen_if_replaced() {
+        let dir = TempPath::new("reopen");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("log");
+        fs::write(&path, "one\ntwo\n").unwrap();
+        let identity = FileIdentity::of(&fs::metadata(&path).unwrap());
+        let marker = |read| reopen_if_replaced(&path, identity, read).map(|(_, marker)| marker);
+        assert_eq!(marker(Some(8)), None);
+
+        fs::write(&path, "new\n").unwrap();
+        assert_eq!(marker(Some(8)), Some("[file truncated]"));
+        // which takes knowing how much was read
+        assert_eq!(marker(None), None);
+        // grown back past where reading was, it looks like it just grew
+        fs::write(&path, "longer than before\n").unwrap();
+        assert_eq!(marker(Some(8)), None);
+
+        fs::rename(&path, dir.join("log.1")).unwrap();
+        // until the new file is created, the old one is read on
+        assert_eq!(marker(Some(8)), None);
+        fs::write(&path, "fresh\n").unwrap();
+        let (mut file, marker) = reopen_if_replaced(&path, identity, None).unwrap();
+        assert_eq!(marker, "[file rotated]");
+        let mut content = String::new();
+        file.read_to_string(&mut content).unwrap();
+        assert_eq!(content, "fresh\n");
+    }
+
+    #[test]
+    fn test_encodings() {
+        let read = |name: &str, bytes: &[u8], encoding: &str, start: StartPosition| {
+            let path = TempPath::new(name);
+            fs::write(&path, bytes).unwrap();
+            let (tx, rx) = mpsc::channel();
+            let options = ReadOptions {
+                start,
+                encoding: Some(encoding.parse().unwrap()),
+                ..Default::default()
+            };
+            let mut input = Input::ordinary_file(&path).open(tx, &options).unwrap();
+            for event in rx {
+                input.handle_event(event).unwrap();
+            }
+            input
+        };
+
+        // Latin-1 is split as bytes, so lines start where they do in the file
+        let input = read(
+            "latin1",
+            b"caf\xe9\n\xa3 5\n",
+            "latin1",
+            StartPosition::Byte(1),
+        );
+        assert_eq!(input.lines, [Arc::from("£ 5")]);
+        assert_eq!(input.byte_offset(0), Some(5));
+        assert_eq!(input.counted_lines, Some(1));
+
+        // and UTF-16 is decoded before being split, from the start
+        let utf16: Vec<u8> = "\u{feff}one\r\nДва\n\u{10a}"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        let input = read("utf16", &utf16, "auto", StartPosition::Byte(4));
+        assert_eq!(input.encoding().name(), "UTF-16LE");
+        assert_eq!(
+            input.lines,
+            [Arc::from("one"), Arc::from("Два"), Arc::from("Ċ")]
+        );
+        assert_eq!(input.counted_lines, None);
+        // where its lines are in the file, past the byte order mark
+        assert_eq!(input.offsets, [2, 12, 20]);
+        assert_eq!(input.line_at_byte(13), Some(1));
+
+        // a stateful encoding has none to tell
+        let input = read(
+            "iso2022jp",
+            b"a\nb\n",
+            "iso-2022-jp",
+            StartPosition::Beginning,
+        );
+        assert_eq!(input.lines, [Arc::from("a"), Arc::from("b")]);
+        assert_eq!(input.byte_offset(1), None);
+        assert_eq!(input.line_at_byte(2), None);
+    }
+
+    #[test]
+    fn test_follow_utf16() {
+        let utf16 =
+            |text: &str| -> Vec<u8> { text.encode_utf16().flat_map(u16::to_le_bytes).collect() };
+        let path = TempPath::new("follow-utf16");
+        // longer decoded than in the file, which mustn't look like the file
+        // was cut short
+        fs::write(&path, utf16("\u{feff}日本語のテキストです\n")).unwrap();
+        let (tx, rx) = mpsc::channel();
+        let options = ReadOptions {
+            encoding: Some("auto".parse().unwrap()),
+            follow_name: true,
+            ..Default::default()
+        };
+        let mut input = Input::ordinary_file(&path).open(tx, &options).unwrap();
+        let read_until = |input: &mut OpenedInput, done: &dyn Fn(&OpenedInput) -> bool| {
+            while !done(input) {
+                let event = rx.recv_timeout(Duration::from_secs(10)).unwrap();
+                input.handle_event(event).unwrap();
+            }
+        };
+        read_until(&mut input, &|input| input.reached_eof());
+        fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(&utf16("中文\n"))
+            .unwrap();
+        read_until(&mut input, &|input| input.lines.len() > 1);
+        input.close();
+        assert_eq!(
+            input.lines,
+            [Arc::from("日本語のテキストです"), Arc::from("中文")]
+        );
+        assert_eq!(input.offsets, [2, 24]);
+    }
+
+    #[test]
+    fn test_null_delimited() {
+        let path = TempPath::new("null");
+        fs::write(&path, "a\0two\nlines\0b\r\n\0c").unwrap();
+        let (tx, rx) = mpsc::channel();
+        let options = ReadOptions {
+            start: StartPosition::Byte(1),
+            delimiter: 0,
+            ..Default::default()
+        };
+        let mut input = Input::ordinary_file(&path).open(tx, &options).unwrap();
+        let mut binary = false;
+        for event in rx {
+            binary |=
+                matches!(event, Event::Input(_, ref event) if matches!(**event, Event::Binary));
+            input.handle_event(event).unwrap();
+        }
+
+        assert!(!binary);
+        assert_eq!(
+            input.lines,
+            [Arc::from("two\nlines"), Arc::from("b\r\n"), Arc::from("c")]
+        );
+        assert_eq!(input.counted_lines, Some(3));
+        assert_eq!(input.byte_offset(1), Some(12));
+    }
+
+    #[test]
+    fn test_process_substitution() {
+        // bash keeps the pipe of `<(...)` open as fd 3, which is then opened
+        // by path the way `least <(...)` would
+        let mut bash = Command::new("bash")
+            .args([
+                "-c",
+                "exec 3< <(printf 'a\\nb\\n'; sleep 0.3; printf c); echo; sleep 5",
+            ])
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut ready = String::new();
+        BufReader::new(bash.stdout.as_mut().unwrap())
+            .read_line(&mut ready)
+            .unwrap();
+        let path = format!("/proc/{}/fd/3", bash.id());
+        let input = Input::ordinary_file(&path);
+        input.check().unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let options = ReadOptions {
+            start: StartPosition::Byte(2),
+            ..Default::default()
+        };
+        let mut input = input.open(tx, &options).unwrap();
+        assert_eq!(input.size(), None);
+        assert_eq!(input.start(), StartPosition::Beginning);
+        while !input.reached_eof() {
+            let event = rx.recv().unwrap();
+            // nothing is counted or read ahead of the reader
+            assert!(
+                !matches!(&event, Event::Input(_, event) if matches!(**event, Event::LineCount(_)))
+            );
+            input.handle_event(event).unwrap();
+        }
+        bash.kill().unwrap();
+        bash.wait().unwrap();
+        assert_eq!(
+            input.lines,
+            [Arc::from("a"), Arc::from("b"), Arc::from("c")]
+        );
+        assert_eq!(input.total_lines(), Some(3));
+    }
+
+    #[test]
+    fn test_piped() {
+        let run = |command: &str| {
+            let (tx, rx) = mpsc::channel();
+            let input = Input::piped(command.to_string(), b"ab\ncd\n".to_vec());
+            let mut opened = input.open(tx, &ReadOptions::default()).unwrap();
+            let mut warnings = Vec::new();
+            for event in rx {
+                let Event::Input(_, event) = event else {
+                    unreachable!()
+                };
+                match *event {
+                    Event::ReadWarning(warning) => warnings.push(warning),
+                    event => opened.handle_event(event).unwrap(),
+                }
+            }
+            let lines = opened.row_lines(0..opened.current_total_lines());
+            (lines, warnings)
+        };
+        let (lines, warnings) = run("tr a-z A-Z");
+        assert_eq!(lines, [Some(Arc::from("AB")), Some(Arc::from("CD"))]);
+        assert!(warnings.is_empty());
+
+        // what went wrong is told, next to whatever was written
+        let (lines, warnings) = run("head -n 1; echo oops >&2; exit 3");
+        assert_eq!(lines, [Some(Arc::from("ab"))]);
+        assert_eq!(warnings, ["head -n 1; echo oops >&2; exit 3: oops"]);
+        let (_, warnings) = run("exit 3");
+        assert_eq!(warnings, ["exit 3 failed: exit status: 3"]);
+
+        // a command is given as long as it needs while it keeps writing, and
+        // stopped once it goes quiet for too long
+        let (lines, warnings) = run("for i in 1 2 3 4 5; do echo $i; sleep 0.5; done");
+        assert_eq!(lines.len(), 5);
+        assert!(warnings.is_empty());
+        let (lines, warnings) = run("echo a; sleep 30");
+        assert_eq!(lines, [Some(Arc::from("a"))]);
+        assert_eq!(
+            warnings,
+            ["Stopped echo a; sleep 30 after 1s without output"]
+        );
+
+        // a command still running is stopped on closing, quietly
+        let (tx, rx) = mpsc::channel();
+        let input = Input::piped("sleep 30".to_string(), Vec::new());
+        let opened = input.open(tx, &ReadOptions::default()).unwrap();
+        assert!(opened.command_group.lock().unwrap().is_some());
+        opened.close();
+        let start = Instant::now();
+        let warned = rx.into_iter().any(|event| {
+            matches!(event, Event::Input(_, event) if matches!(*event, Event::ReadWarning(_)))
+        });
+        assert!(start.elapsed() < Duration::from_secs(10));
+        assert!(!warned);
+        assert!(opened.command_group.lock().unwrap().is_none());
+    }
+
+    /// Fails to read `failures` times with errors of `kind` before reading
+    /// `data`.
+    struct FlakyReader {
+        failures: usize,
+        kind: io::ErrorKind,
+        data: &'static [u8],
+    }
+
+    impl Read for FlakyReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.failures > 0 {
+                self.failures -= 1;
+                return Err(self.kind.into());
+            }
+            self.data.read(buf)
+        }
+    }
+
+    #[test]
+    fn test_reader() {
+        let input = Input::reader("memory", io::Cursor::new(b"ab\ncd\ne".to_vec()));
+        assert_eq!(input.name(), "memory");
+        let (tx, rx) = mpsc::channel();
+        let opened = input
+            .clone()
+            .open(tx.clone(), &ReadOptions::default())
+            .unwrap();
+        drop(tx);
+        let events: Vec<_> = rx
+            .into_iter()
+            .map(|event| match event {
+                Event::Input(id, event) if id == opened.id() => *event,
+                _ => unreachable!(),
+            })
+            .collect();
+        // lines in batches as they are read, however they are split up, and
+        // the end once the last line is known to have no newline coming
+        let [batches @ .., Event::Eof] = &events[..] else {
+            panic!("no end to the {} events", events.len());
+        };
+        let (mut lines, mut offsets) = (Vec::new(), Vec::new());
+        for batch in batches {
+            let Event::NewLines(batch_lines, batch_offsets) = batch else {
+                panic!("an event other than lines");
+            };
+            lines.extend_from_slice(batch_lines);
+            offsets.extend_from_slice(batch_offsets);
+        }
+        assert_eq!(lines, [Arc::from("ab"), Arc::from("cd"), Arc::from("e")]);
+        assert_eq!(offsets, [0, 3, 6]);
+
+        // the reader is gone once read
+        let (tx, _rx) = mpsc::channel();
+        let err = input.open(tx, &ReadOptions::default()).unwrap_err();
+        assert_eq!(err.to_string(), "memory can't be read again");
+    }
+
+    #[test]
+    fn test_read_retries() {
+        let read = |failures, kind| {
+            let (tx, rx) = mpsc::channel();
+            let tx = InputSender {
+                id: 0,
+                tx,
+                closed: Arc::default(),
+            };
+            let flaky = FlakyReader {
+                failures,
+                kind,
+                data: b"a\n",
+            };
+            let mut reader = InputReader::new(BufReader::new(flaky), tx);
+            let mut block = [0; 16];
+            let size = reader.read_block(&mut block).ok();
+            drop(reader);
+            let warnings = rx
+                .into_iter()
+                .filter_map(|event| match event {
+                    Event::Input(_, event) => Some(event),
+                    _ => None,
+                })
+                .filter(|event| matches!(**event, Event::ReadWarning(_)))
+                .count();
+            (size, warnings)
+        };
+        assert_eq!(read(2, io::ErrorKind::TimedOut), (Some(2), 2));
+        // a file that is gone isn't coming back
+        assert_eq!(read(1, io::ErrorKind::NotFound), (None, 0));
+
+        // nor is one that fails for good before anything is read, which is
+        // then read as empty rather than failing the reader
+        let flaky = FlakyReader {
+            failures: InputReader::READ_RETRIES as usize + 1,
+            kind: io::ErrorKind::TimedOut,
+            data: b"a\n",
+        };
+        let (tx, rx) = mpsc::channel();
+        let input = Input::reader("flaky", BufReader::new(flaky));
+        let mut opened = input.open(tx, &ReadOptions::default()).unwrap();
+        let mut warnings = Vec::new();
+        for event in rx {
+            let Event::Input(_, event) = event else {
+                unreachable!()
+            };
+            match *event {
+                Event::ReadWarning(warning) => warnings.push(warning),
+                Event::ReaderThreadErrReturned => panic!("the reader failed"),
+                event => opened.handle_event(event).unwrap(),
+            }
+        }
+        assert!(opened.join_reader().is_ok());
+        assert!(opened.reached_eof());
+        assert_eq!(opened.current_total_lines(), 0);
+        assert_eq!(warnings.len(), InputReader::READ_RETRIES as usize + 1);
+        assert!(warnings.last().unwrap().starts_with("Stopped reading"));
+    }
+
+    #[test]
+    fn test_line_batch() {
+        let batches = |flush_interval| {
+            let (tx, rx) = mpsc::channel();
+            let tx = InputSender {
+                id: 0,
+                tx,
+                closed: Arc::default(),
+            };
+            let mut batch = LineBatch::new(tx, flush_interval);
+            for i in 0..ReadOptions::EAGER_LINES as u64 + 10 {
+                batch.push(Arc::from(i.to_string()), i);
+            }
+            batch.flush();
+            drop(batch);
+            rx.into_iter().count()
+        };
+        // the first line goes out at once and the rest wait for the interval
+        assert_eq!(batches(Duration::from_secs(3600)), 2);
+        assert_eq!(batches(Duration::ZERO), ReadOptions::EAGER_LINES + 10);
+    }
+
+    #[test]
+    fn test_partial_line() {
+        let mut input = OpenedInput::from_lines(&["a"]);
+        let partial = |line: &str| Event::PartialLine(Arc::from(line), 2);
+        input.handle_event(partial("Name? ")).unwrap();
+        assert_eq!(input.current_total_lines(), 2);
+        assert_eq!(input.complete_rows(), 1);
+        input.handle_event(partial("Name? Al")).unwrap();
+        assert_eq!(input.lines, [Arc::from("a"), Arc::from("Name? Al")]);
+
+        let filter = FilterView::new(Pattern::new("Name".to_string()), 0);
+        input.set_filter(Some(filter));
+        assert_eq!(input.current_total_lines(), 0);
+        let lines = vec![Arc::from("Name? Alice"), Arc::from("b")];
+        input
+            .handle_event(Event::NewLines(lines, vec![2, 14]))
+            .unwrap();
+        assert_eq!(input.current_total_lines(), 1);
+        input.set_filter(None);
+        assert_eq!(
+            input.lines,
+            [Arc::from("a"), Arc::from("Name? Alice"), Arc::from("b")]
+        );
+        assert_eq!(input.byte_offset(2), Some(14));
+        assert_eq!(input.complete_rows(), 3);
+    }
+
+    #[test]
+    fn test_find_paragraph_boundary() {
+        use crate::search::SearchDirection::{Backward, Forward};
+
+        let input = OpenedInput::from_lines(&["a", "b", "", "c", "", "  ", "d", "e", "", "f"]);
+        assert_eq!(input.find_paragraph_boundary(0, Forward), Some(2));
+        assert_eq!(input.find_paragraph_boundary(2, Forward), Some(4));
+        // the whitespace line belongs to the same run of blank lines
+        assert_eq!(input.find_paragraph_boundary(4, Forward), Some(8));
+        assert_eq!(input.find_paragraph_boundary(8, Forward), None);
+
+        assert_eq!(input.find_paragraph_boundary(9, Backward), Some(8));
+        assert_eq!(input.find_paragraph_boundary(8, Backward), Some(5));
+        assert_eq!(input.find_paragraph_boundary(5, Backward), Some(2));
+        assert_eq!(input.find_paragraph_boundary(2, Backward), None);
+    }
+
+    #[test]
+    fn test_diff_auto() {
+        let options = RenderOptions::default();
+        let mut input = OpenedInput::from_lines(&["--- a", "+++ b", "-old", "+new", " same"]);
+        input.set_diff(DiffMode::Auto);
+        assert_eq!(input.spans(2, &options)[0].style.fg, Some(Color::Red));
+        assert_eq!(input.spans(3, &options)[0].style.fg, Some(Color::Green));
+        assert_eq!(input.spans(4, &options)[0].style, Style::new());
+
+        let mut input = OpenedInput::from_lines(&["- a list", "+ not a diff"]);
+        input.set_diff(DiffMode::Auto);
+        assert_eq!(input.spans(0, &options)[0].style, Style::new());
+    }
+
+    #[test]
+    fn test_max_top_line() {
+        let options = RenderOptions::default();
+        let long = "x".repeat(25);
+        // rows at width 10: 1, 3, 1, 1, 3
+        let input = OpenedInput::from_lines(&["a", &long, "b", "", &long]);
+
+        assert_eq!(input.max_top_line(3, None, &options), 2);
+        assert_eq!(input.max_top_line(10, None, &options), 0);
+
+        assert_eq!(input.display_rows(1, 10, &options), 3);
+        assert_eq!(input.max_top_line(3, Some(10), &options), 4);
+        assert_eq!(input.max_top_line(4, Some(10), &options), 3);
+        assert_eq!(input.max_top_line(5, Some(10), &options), 2);
+        assert_eq!(input.max_top_line(7, Some(10), &options), 2);
+        assert_eq!(input.max_top_line(8, Some(10), &options), 1);
+        assert_eq!(input.max_top_line(9, Some(10), &options), 0);
+        // the last line alone overflows the screen
+        assert_eq!(input.max_top_line(2, Some(10), &options), 4);
+    }
+
+    #[test]
+    fn test_rows_before() {
+        let options = RenderOptions::default();
+        let long = "x".repeat(25);
+        let mut input = OpenedInput::from_lines(&["a", &long, "b", "", &long]);
+        assert_eq!(input.rows_before(0, 10, &options), 0);
+        assert_eq!(input.rows_before(2, 10, &options), 4);
+        assert_eq!(input.rows_before(5, 10, &options), 9);
+        assert_eq!(input.rows_before(5, 5, &options), 13);
+        let cut = RenderOptions {
+            max_columns: Some(10),
+            ..options.clone()
+        };
+        assert_eq!(input.rows_before(5, 5, &cut), 7);
+
+        let filter = FilterView::new(Pattern::new("x".to_string()), 0);
+        input.set_filter(Some(filter));
+        assert_eq!(input.rows_before(1, 10, &options), 3);
+        input.set_filter(None);
+
+        // a partial line changes as the rest of it is read
+        input
+            .handle_event(Event::PartialLine(Arc::from("c"), 100))
+            .unwrap();
+        assert_eq!(input.rows_before(6, 10, &options), 10);
+        input
+            .handle_event(Event::PartialLine(Arc::from(long.as_str()), 100))
+            .unwrap();
+        assert_eq!(input.rows_before(6, 10, &options), 12);
+
+        // counted a chunk at a time, each going on from the last
+        let input = OpenedInput::from_lines(&[long.as_str(); 5]);
+        assert_eq!(input.rows_before_within(5, 10, &options, 2), None);
+        assert_eq!(input.row_cache.borrow().starts, [0, 3, 6]);
+        assert_eq!(input.rows_before_within(5, 10, &options, 2), None);
+        assert_eq!(input.rows_before_within(5, 10, &options, 2), Some(15));
+        assert_eq!(input.rows_before_within(1, 10, &options, 0), Some(3));
     }
 }
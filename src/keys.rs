@@ -1,45 +1,229 @@
-use crossterm::event::{KeyCode, KeyEvent};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::search::SearchDirection;
 
 #[derive(Default, Debug, Clone, Copy)]
 pub enum KeyState {
     #[default]
     Normal,
+    /// A numeric count typed before a command, e.g. the `3` of `3n`.
+    Count(usize),
     WaitingG,
     WaitingGNumber(usize),
 }
 
 #[derive(Debug, Clone, Copy)]
 pub enum Action {
-    GoToMain,
     GoToTop,
     GoToBottom,
+    /// Go to a line by its 1-based number, as users count lines.
     GoToLine(usize),
+    /// Go to the line the given number of lines before the end.
+    GoToLineFromEnd(usize),
+    /// Go to the line with the byte at the given offset in the input.
+    GoToByte(u64),
     ScrollUpOneLine,
     ScrollDownOneLine,
-    ScrollUpHalfScreen,
-    ScrollDownHalfScreen,
+    /// Scroll by half a screen, or by the given count which is then kept for
+    /// later half-screen scrolls.
+    ScrollUpHalfScreen(Option<usize>),
+    ScrollDownHalfScreen(Option<usize>),
     ScrollUpScreen,
     ScrollDownScreen,
+    /// Scroll sideways by half a screen.
+    ScrollLeft,
+    ScrollRight,
+    /// Scroll back to the first column.
+    ScrollToHorizontalStart,
+    /// Scroll right until the end of the longest line on screen shows.
+    ScrollToHorizontalEnd,
+    /// Go to the blank line after the current paragraph, or to the bottom.
+    NextParagraph,
+    /// Go to the blank line before the current paragraph, or to the top.
+    PrevParagraph,
+    StartSearch(SearchDirection),
+    /// Prompt for a file to save the buffer to.
+    Save,
+    /// Prompt for a `:` command, such as `:e FILE` to open another file or
+    /// `:set nowrap`.
+    EnterCommand,
+    /// Prompt for a shell command to pipe the lines read through, paging what
+    /// it writes.
+    StartPipe,
+    /// Prompt for a pattern to filter lines by, showing the given number of
+    /// lines of context around each match.
+    StartFilter(Option<usize>),
+    /// Repeat the last search in its own direction, the given number of times.
+    SearchNext(usize),
+    /// Repeat the last search in the opposite direction.
+    SearchPrev(usize),
+    /// Clear the terminal and redraw everything.
+    Refresh,
+    ToggleWrap,
+    /// Mark trailing spaces and tabs, or stop doing so.
+    ToggleWhitespace,
+    /// Switch between the colors for dark and light backgrounds.
+    ToggleTheme,
+    /// Number lines by their distance from the cursor line, or by their
+    /// number in the input again.
+    ToggleRelativeNumbers,
+    ToggleAutoScroll,
+    AutoScrollFaster,
+    AutoScrollSlower,
+    /// Go to the next input, or the previous one, that lines have been read
+    /// from since it was last shown.
+    NextUnreadFile,
+    PrevUnreadFile,
+    /// Show what is known about the current input over the lines.
+    ShowInfo,
+    /// Open the current file in the editor, reading it again afterwards.
+    EditFile,
+    /// Repeat the last repeatable action the given number of times.
+    Repeat(usize),
     None,
+    /// Quit, after asking first if so configured.
     Quit,
+    /// Quit without asking.
+    ForceQuit,
+}
+
+/// Whether `key` is Ctrl-C, which raw mode delivers as a key press rather
+/// than as SIGINT.
+pub fn is_interrupt(key: &KeyEvent) -> bool {
+    key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c')
+}
+
+impl Action {
+    /// Whether `.` can repeat this action. These are the moves relative to
+    /// where the view is: scrolling by lines, screens and columns, jumping
+    /// between paragraphs, and repeating a search. Jumps to an absolute
+    /// place would go nowhere new, and the rest toggle things, open prompts
+    /// or quit.
+    pub fn is_repeatable(self) -> bool {
+        matches!(
+            self,
+            Action::ScrollUpOneLine
+                | Action::ScrollDownOneLine
+                | Action::ScrollUpHalfScreen(_)
+                | Action::ScrollDownHalfScreen(_)
+                | Action::ScrollUpScreen
+                | Action::ScrollDownScreen
+                | Action::ScrollLeft
+                | Action::ScrollRight
+                | Action::NextParagraph
+                | Action::PrevParagraph
+                | Action::SearchNext(_)
+                | Action::SearchPrev(_)
+        )
+    }
 }
 
 impl KeyState {
+    /// A short description of a partly entered command, or `None` when no
+    /// command is pending.
+    pub fn pending(self) -> Option<String> {
+        match self {
+            KeyState::Normal => None,
+            KeyState::Count(n) => Some(format!(":{n}")),
+            KeyState::WaitingG => Some(":g".to_string()),
+            KeyState::WaitingGNumber(n) => Some(format!(":g{n}")),
+        }
+    }
+
     pub fn next(self, key: KeyEvent) -> (KeyState, Action) {
+        // Ctrl-C quits, unless it is abandoning a pending command
+        if is_interrupt(&key) {
+            return match self {
+                KeyState::Normal => (KeyState::Normal, Action::Quit),
+                _ => (KeyState::Normal, Action::None),
+            };
+        }
         match self {
             KeyState::Normal => match (key.modifiers, key.code) {
+                (KeyModifiers::CONTROL, KeyCode::Char('l')) | (_, KeyCode::Char('r')) => {
+                    (KeyState::Normal, Action::Refresh)
+                }
                 (_, KeyCode::Esc | KeyCode::Char('q')) => (KeyState::Normal, Action::Quit),
-                (_, KeyCode::Char('d')) => (KeyState::Normal, Action::ScrollDownHalfScreen),
-                (_, KeyCode::Char('u')) => (KeyState::Normal, Action::ScrollUpHalfScreen),
-                (_, KeyCode::Char('f')) => (KeyState::Normal, Action::ScrollDownScreen),
-                (_, KeyCode::Char('b')) => (KeyState::Normal, Action::ScrollUpScreen),
-                (_, KeyCode::Char('j')) => (KeyState::Normal, Action::ScrollDownOneLine),
-                (_, KeyCode::Char('k')) => (KeyState::Normal, Action::ScrollUpOneLine),
+                (_, KeyCode::Char('Q')) => (KeyState::Normal, Action::ForceQuit),
+                (KeyModifiers::CONTROL, KeyCode::Char('g')) | (_, KeyCode::Char('=')) => {
+                    (KeyState::Normal, Action::ShowInfo)
+                }
+                (_, KeyCode::Char('d')) => (KeyState::Normal, Action::ScrollDownHalfScreen(None)),
+                (_, KeyCode::Char('u')) => (KeyState::Normal, Action::ScrollUpHalfScreen(None)),
+                (_, KeyCode::Char('f' | ' ') | KeyCode::PageDown) => {
+                    (KeyState::Normal, Action::ScrollDownScreen)
+                }
+                (_, KeyCode::Char('b') | KeyCode::PageUp) => {
+                    (KeyState::Normal, Action::ScrollUpScreen)
+                }
+                (_, KeyCode::Char('j') | KeyCode::Down | KeyCode::Enter) => {
+                    (KeyState::Normal, Action::ScrollDownOneLine)
+                }
+                (_, KeyCode::Char('k') | KeyCode::Up) => {
+                    (KeyState::Normal, Action::ScrollUpOneLine)
+                }
+                (_, KeyCode::Left) => (KeyState::Normal, Action::ScrollLeft),
+                (_, KeyCode::Right) => (KeyState::Normal, Action::ScrollRight),
+                // a count never starts with 0, so it is free to mean the first column
+                (_, KeyCode::Char('0')) => (KeyState::Normal, Action::ScrollToHorizontalStart),
+                (_, KeyCode::Char('$')) => (KeyState::Normal, Action::ScrollToHorizontalEnd),
+                (_, KeyCode::Char('}')) => (KeyState::Normal, Action::NextParagraph),
+                (_, KeyCode::Char('{')) => (KeyState::Normal, Action::PrevParagraph),
                 (_, KeyCode::Char('g')) => (KeyState::WaitingG, Action::None),
-                (_, KeyCode::Char('G')) => (KeyState::Normal, Action::GoToBottom),
+                (_, KeyCode::Home) => (KeyState::Normal, Action::GoToTop),
+                (_, KeyCode::Char('G') | KeyCode::End) => (KeyState::Normal, Action::GoToBottom),
+                (_, KeyCode::Char('/')) => (
+                    KeyState::Normal,
+                    Action::StartSearch(SearchDirection::Forward),
+                ),
+                (_, KeyCode::Char('?')) => (
+                    KeyState::Normal,
+                    Action::StartSearch(SearchDirection::Backward),
+                ),
+                (_, KeyCode::Char('w')) => (KeyState::Normal, Action::ToggleWrap),
+                (_, KeyCode::Char('W')) => (KeyState::Normal, Action::ToggleWhitespace),
+                (_, KeyCode::Char('T')) => (KeyState::Normal, Action::ToggleTheme),
+                (_, KeyCode::Char('#')) => (KeyState::Normal, Action::ToggleRelativeNumbers),
+                (_, KeyCode::Char('a')) => (KeyState::Normal, Action::ToggleAutoScroll),
+                (_, KeyCode::Char('+')) => (KeyState::Normal, Action::AutoScrollFaster),
+                (_, KeyCode::Char('-')) => (KeyState::Normal, Action::AutoScrollSlower),
+                (_, KeyCode::Char('s')) => (KeyState::Normal, Action::Save),
+                (_, KeyCode::Char('v')) => (KeyState::Normal, Action::EditFile),
+                (_, KeyCode::Char(':')) => (KeyState::Normal, Action::EnterCommand),
+                (_, KeyCode::Char('&')) => (KeyState::Normal, Action::StartFilter(None)),
+                (_, KeyCode::Char('|')) => (KeyState::Normal, Action::StartPipe),
+                (_, KeyCode::Char(']')) => (KeyState::Normal, Action::NextUnreadFile),
+                (_, KeyCode::Char('[')) => (KeyState::Normal, Action::PrevUnreadFile),
+                (_, KeyCode::Char('n')) => (KeyState::Normal, Action::SearchNext(1)),
+                (_, KeyCode::Char('N')) => (KeyState::Normal, Action::SearchPrev(1)),
+                (_, KeyCode::Char('.')) => (KeyState::Normal, Action::Repeat(1)),
+                (_, KeyCode::Char(c @ '1'..='9')) => {
+                    let n = c.to_digit(10).unwrap() as usize;
+                    (KeyState::Count(n), Action::None)
+                }
                 _ => (KeyState::Normal, Action::None),
             },
+            KeyState::Count(n) => match (key.modifiers, key.code) {
+                // Esc abandons a pending command rather than quitting
+                (_, KeyCode::Esc) => (KeyState::Normal, Action::None),
+                (_, KeyCode::Char(c)) if c.is_ascii_digit() => {
+                    let c = c.to_digit(10).unwrap() as usize;
+                    let n = n.saturating_mul(10).saturating_add(c);
+                    (KeyState::Count(n), Action::None)
+                }
+                (_, KeyCode::Char('n')) => (KeyState::Normal, Action::SearchNext(n)),
+                (_, KeyCode::Char('N')) => (KeyState::Normal, Action::SearchPrev(n)),
+                (_, KeyCode::Char('.')) => (KeyState::Normal, Action::Repeat(n)),
+                (_, KeyCode::Char('d')) => {
+                    (KeyState::Normal, Action::ScrollDownHalfScreen(Some(n)))
+                }
+                (_, KeyCode::Char('u')) => (KeyState::Normal, Action::ScrollUpHalfScreen(Some(n))),
+                (_, KeyCode::Char('&')) => (KeyState::Normal, Action::StartFilter(Some(n))),
+                // commands that take no count just drop it
+                _ => KeyState::Normal.next(key),
+            },
             KeyState::WaitingG => match (key.modifiers, key.code) {
+                (_, KeyCode::Esc) => (KeyState::Normal, Action::None),
                 (_, KeyCode::Char('g')) => (KeyState::Normal, Action::GoToTop),
                 (_, KeyCode::Char(c)) if c.is_ascii_digit() => {
                     let c = c.to_digit(10).unwrap() as usize;
@@ -49,14 +233,124 @@ impl KeyState {
                 _ => (KeyState::Normal, Action::None),
             },
             KeyState::WaitingGNumber(n) => match (key.modifiers, key.code) {
+                (_, KeyCode::Esc) => (KeyState::Normal, Action::None),
                 (_, KeyCode::Char(c)) if c.is_ascii_digit() => {
                     let c = c.to_digit(10).unwrap() as usize;
-                    let n = n * 10 + c;
+                    let n = n.saturating_mul(10).saturating_add(c);
                     (KeyState::WaitingGNumber(n), Action::None)
                 }
                 (_, KeyCode::Enter) => (KeyState::Normal, Action::GoToLine(n)),
+                (_, KeyCode::Char('-')) => (KeyState::Normal, Action::GoToLineFromEnd(n)),
                 _ => (KeyState::Normal, Action::None),
             },
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    use crate::keys::{Action, KeyState};
+
+    fn feed(keys: &[KeyCode]) -> Action {
+        let mut state = KeyState::Normal;
+        let mut action = Action::None;
+        for &code in keys {
+            (state, action) = state.next(KeyEvent::from(code));
+        }
+        action
+    }
+
+    #[test]
+    fn test_go_to_line_passes_typed_number() {
+        use KeyCode::{Char, Enter};
+
+        assert!(matches!(
+            feed(&[Char('g'), Char('1'), Enter]),
+            Action::GoToLine(1)
+        ));
+        assert!(matches!(
+            feed(&[Char('g'), Char('4'), Char('2'), Enter]),
+            Action::GoToLine(42)
+        ));
+        assert!(matches!(
+            feed(&[Char('g'), Char('0'), Enter]),
+            Action::GoToLine(0)
+        ));
+    }
+
+    #[test]
+    fn test_go_to_line_from_end() {
+        use KeyCode::Char;
+
+        assert!(matches!(
+            feed(&[Char('g'), Char('1'), Char('0'), Char('-')]),
+            Action::GoToLineFromEnd(10)
+        ));
+        // a plain `-` still slows down auto-scrolling
+        assert!(matches!(feed(&[Char('-')]), Action::AutoScrollSlower));
+    }
+
+    #[test]
+    fn test_navigation_keys() {
+        use KeyCode::{Down, End, Home, PageDown, PageUp, Up};
+
+        assert!(matches!(feed(&[Home]), Action::GoToTop));
+        assert!(matches!(feed(&[End]), Action::GoToBottom));
+        assert!(matches!(feed(&[PageUp]), Action::ScrollUpScreen));
+        assert!(matches!(feed(&[PageDown]), Action::ScrollDownScreen));
+        assert!(matches!(feed(&[Up]), Action::ScrollUpOneLine));
+        assert!(matches!(feed(&[Down]), Action::ScrollDownOneLine));
+    }
+
+    #[test]
+    fn test_zero_key() {
+        use KeyCode::{Char, Enter};
+
+        assert!(matches!(
+            feed(&[Char('0')]),
+            Action::ScrollToHorizontalStart
+        ));
+        assert!(matches!(
+            feed(&[Char('1'), Char('0'), Char('n')]),
+            Action::SearchNext(10)
+        ));
+        assert!(matches!(
+            feed(&[Char('g'), Char('0'), Enter]),
+            Action::GoToLine(0)
+        ));
+    }
+
+    #[test]
+    fn test_interrupt() {
+        let ctrl_c = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
+        assert!(matches!(
+            KeyState::Normal.next(ctrl_c),
+            (KeyState::Normal, Action::Quit)
+        ));
+        assert!(matches!(
+            KeyState::Count(3).next(ctrl_c),
+            (KeyState::Normal, Action::None)
+        ));
+        assert!(matches!(
+            KeyState::WaitingGNumber(3).next(ctrl_c),
+            (KeyState::Normal, Action::None)
+        ));
+    }
+
+    #[test]
+    fn test_less_keys() {
+        use KeyCode::{Char, Enter};
+
+        assert!(matches!(feed(&[Char(' ')]), Action::ScrollDownScreen));
+        assert!(matches!(feed(&[Char('=')]), Action::ShowInfo));
+        assert!(matches!(feed(&[Char('b')]), Action::ScrollUpScreen));
+        assert!(matches!(feed(&[Enter]), Action::ScrollDownOneLine));
+        // Enter still ends a gN command
+        assert!(matches!(
+            feed(&[Char('g'), Char('7'), Enter]),
+            Action::GoToLine(7)
+        ));
+    }
+}
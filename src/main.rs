@@ -1,24 +1,140 @@
-#![feature(slice_internals)]
-
 mod app;
+mod command;
+mod control;
+mod diff;
+mod encoding;
 mod error;
 mod event;
+mod filter;
 mod input;
 mod keys;
+mod positions;
+mod prompt;
+mod search;
+mod syntax;
+#[cfg(test)]
+mod temp_path;
+mod theme;
 mod tracing;
 mod utils;
+mod watch;
+
+use std::{
+    ffi::OsString,
+    fmt,
+    io::{self, stdout},
+};
 
 use app::{App, Cli};
 use clap::Parser;
+use ratatui::{
+    DefaultTerminal, TerminalOptions, Viewport,
+    crossterm::{
+        Command,
+        cursor::{MoveTo, Show},
+        event::{DisableBracketedPaste, EnableBracketedPaste},
+        execute,
+        terminal::disable_raw_mode,
+    },
+};
+
+use crate::{error::*, utils::split_args};
+
+/// Environment variable holding default options. They are parsed before the
+/// command line, so explicit arguments override them, and both override the
+/// built-in defaults.
+const OPTIONS_ENV: &str = "LEAST_OPTS";
+
+fn args() -> Vec<OsString> {
+    let mut args: Vec<OsString> = std::env::args_os().collect();
+    if let Ok(options) = std::env::var(OPTIONS_ENV) {
+        let options = split_args(&options).into_iter().map(OsString::from);
+        // keep the program name first
+        let at = args.len().min(1);
+        args.splice(at..at, options);
+    }
+    args
+}
 
-use crate::error::*;
+/// Puts the cursor keys and the keypad in application mode, or back in
+/// normal mode, as terminfo's `smkx` and `rmkx` do. In application mode the
+/// arrows, Home and End are sent the same way by every terminal, so they
+/// read the same everywhere.
+struct KeypadMode(bool);
+
+impl Command for KeypadMode {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        if self.0 {
+            f.write_str("\x1b[?1h\x1b=")
+        } else {
+            f.write_str("\x1b[?1l\x1b>")
+        }
+    }
+}
+
+/// Sets up the terminal for drawing. Without `alternate_screen` the pager
+/// draws over the visible part of the normal screen.
+fn init_terminal(alternate_screen: bool, keypad: bool) -> io::Result<DefaultTerminal> {
+    let terminal = if alternate_screen {
+        ratatui::init()
+    } else {
+        let mut terminal = ratatui::init_with_options(TerminalOptions {
+            viewport: Viewport::Fullscreen,
+        });
+        // start from a blank screen so nothing already there shows through
+        terminal.clear()?;
+        terminal
+    };
+    // pasted text then comes in one piece, so the line breaks in it can't
+    // submit a prompt; a terminal already set up is paged without it rather
+    // than left that way
+    let _ = execute!(stdout(), EnableBracketedPaste);
+    if keypad {
+        let _ = execute!(stdout(), KeypadMode(true));
+    }
+    Ok(terminal)
+}
+
+fn restore_terminal(alternate_screen: bool, keypad: bool) -> io::Result<()> {
+    // failing to turn these off mustn't keep the terminal from being restored
+    let _ = execute!(stdout(), DisableBracketedPaste);
+    if keypad {
+        let _ = execute!(stdout(), KeypadMode(false));
+    }
+    if alternate_screen {
+        ratatui::restore();
+        return Ok(());
+    }
+    // leave the last page where it is and continue below it
+    disable_raw_mode()?;
+    let (_, height) = ratatui::crossterm::terminal::size()?;
+    execute!(stdout(), MoveTo(0, height.saturating_sub(1)), Show)?;
+    println!();
+    Ok(())
+}
 
 fn main() -> Result<()> {
-    let cli = Cli::parse();
+    let cli = Cli::parse_from(args());
     color_eyre::install()?;
-    tracing::initialize_logging()?;
-    let terminal = ratatui::init();
-    let result = App::new(cli).run(terminal);
-    ratatui::restore();
-    result
+    tracing::initialize_logging(cli.verbose, cli.log_file.as_deref())?;
+    let alternate_screen = !cli.no_init;
+    let keypad = !cli.no_keypad;
+    let dump = cli.dump;
+    let app = App::new(cli)?;
+    if dump {
+        return app.dump(&mut stdout().lock());
+    }
+    if !App::keyboard_available() {
+        // no terminal at all, not even /dev/tty behind a piped stdin, so
+        // behave like cat
+        return app.copy_inputs(&mut stdout().lock());
+    }
+    let terminal = init_terminal(alternate_screen, keypad)?;
+    let result = app.run(terminal);
+    restore_terminal(alternate_screen, keypad)?;
+    if let Some(signal) = result? {
+        // the conventional status for a process ended by a signal
+        std::process::exit(128 + signal);
+    }
+    Ok(())
 }
@@ -0,0 +1,153 @@
+//! Where each file was left off, kept between runs in the cache directory.
+
+use std::{
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::tracing::get_cache_dir;
+
+const POSITIONS_FILE: &str = "positions";
+/// Files remembered at most; the least recently left ones are forgotten.
+const MAX_ENTRIES: usize = 1000;
+
+/// Top lines of files by canonical path, most recently left first.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Positions {
+    entries: Vec<(PathBuf, usize)>,
+}
+
+impl Positions {
+    fn path() -> PathBuf {
+        get_cache_dir().join(POSITIONS_FILE)
+    }
+
+    /// Reads the saved positions. A missing or unreadable file just means
+    /// nothing is remembered.
+    pub fn load() -> Self {
+        Self::load_from(&Self::path())
+    }
+
+    fn load_from(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .map(|content| Self::parse(&content))
+            .unwrap_or_default()
+    }
+
+    /// Records that `file` was left at `line` and saves every position.
+    pub fn remember(file: &Path, line: usize) -> io::Result<()> {
+        Self::remember_in(&Self::path(), file, line)
+    }
+
+    /// Records `file` in the positions saved at `path`. Other instances may
+    /// be saving theirs at the same time, so the positions are read and
+    /// written back under a lock on a file beside them, and the new ones are
+    /// renamed into place so nobody reads them half written.
+    fn remember_in(path: &Path, file: &Path, line: usize) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let lock = File::options()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path.with_extension("lock"))?;
+        lock.lock()?;
+        let mut positions = Self::load_from(path);
+        positions.set(file, line);
+        let temp = path.with_extension(format!("{}.tmp", std::process::id()));
+        fs::write(&temp, positions.to_string())?;
+        fs::rename(&temp, path)
+    }
+
+    pub fn get(&self, file: &Path) -> Option<usize> {
+        self.entries
+            .iter()
+            .find(|(path, _)| path == file)
+            .map(|&(_, line)| line)
+    }
+
+    fn set(&mut self, file: &Path, line: usize) {
+        self.entries.retain(|(path, _)| path != file);
+        self.entries.insert(0, (file.to_path_buf(), line));
+        self.entries.truncate(MAX_ENTRIES);
+    }
+
+    /// One `line<TAB>path` entry per line; malformed lines are skipped.
+    fn parse(content: &str) -> Self {
+        let entries = content
+            .lines()
+            .filter_map(|entry| {
+                let (line, path) = entry.split_once('\t')?;
+                Some((PathBuf::from(path), line.parse().ok()?))
+            })
+            .collect();
+        Self { entries }
+    }
+}
+
+impl std::fmt::Display for Positions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (path, line) in &self.entries {
+            writeln!(f, "{line}\t{}", path.display())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs, path::Path, thread};
+
+    use crate::{
+        positions::{MAX_ENTRIES, Positions},
+        temp_path::TempPath,
+    };
+
+    #[test]
+    fn test_positions() {
+        let mut positions = Positions::parse("12\t/a b\nbad\nx\t/c\n3\t/d\n");
+        assert_eq!(positions.get(Path::new("/a b")), Some(12));
+        assert_eq!(positions.get(Path::new("/c")), None);
+
+        positions.set(Path::new("/d"), 5);
+        assert_eq!(positions.to_string(), "5\t/d\n12\t/a b\n");
+        assert_eq!(Positions::parse(&positions.to_string()), positions);
+
+        for i in 0..MAX_ENTRIES {
+            positions.set(Path::new(&format!("/{i}")), i);
+        }
+        assert_eq!(positions.entries.len(), MAX_ENTRIES);
+        assert_eq!(positions.get(Path::new("/d")), None);
+    }
+
+    #[test]
+    fn test_concurrent_remember() {
+        let dir = TempPath::new("positions");
+        let path = dir.join("positions");
+        Positions::remember_in(&path, Path::new("/first"), 7).unwrap();
+        Positions::remember_in(&path, Path::new("/second"), 9).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "9\t/second\n7\t/first\n"
+        );
+
+        // instances quitting together each keep the others' positions
+        thread::scope(|scope| {
+            for i in 0..8 {
+                let path = &path;
+                scope.spawn(move || {
+                    let file = format!("/file{i}");
+                    Positions::remember_in(path, Path::new(&file), i).unwrap();
+                });
+            }
+        });
+        let positions = Positions::load_from(&path);
+        assert_eq!(positions.entries.len(), 10);
+        for i in 0..8 {
+            assert_eq!(positions.get(Path::new(&format!("/file{i}"))), Some(i));
+        }
+        assert_eq!(positions.get(Path::new("/first")), Some(7));
+    }
+}
@@ -0,0 +1,78 @@
+use crossterm::event::{KeyCode, KeyEvent};
+
+use crate::keys::is_interrupt;
+
+/// Result of feeding a key into a [`Prompt`].
+#[derive(Debug, PartialEq, Eq)]
+pub enum PromptResult {
+    Pending,
+    Submit(String),
+    Cancel,
+}
+
+/// A single line of editable text shown in the status bar.
+#[derive(Default, Debug)]
+pub struct Prompt {
+    input: String,
+    /// Cursor position, counted in chars.
+    cursor: usize,
+}
+
+impl Prompt {
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn clear(&mut self) {
+        self.input.clear();
+        self.cursor = 0;
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> PromptResult {
+        if is_interrupt(&key) {
+            return PromptResult::Cancel;
+        }
+        match key.code {
+            KeyCode::Enter => return PromptResult::Submit(std::mem::take(&mut self.input)),
+            KeyCode::Esc => return PromptResult::Cancel,
+            // like less, erasing past the start of the prompt abandons it
+            KeyCode::Backspace if self.input.is_empty() => return PromptResult::Cancel,
+            KeyCode::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.input.remove(self.byte_index());
+            }
+            KeyCode::Delete if self.cursor < self.input.chars().count() => {
+                self.input.remove(self.byte_index());
+            }
+            KeyCode::Left => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Right => self.cursor = (self.cursor + 1).min(self.input.chars().count()),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.input.chars().count(),
+            KeyCode::Char(c) => {
+                self.input.insert(self.byte_index(), c);
+                self.cursor += 1;
+            }
+            _ => {}
+        }
+        PromptResult::Pending
+    }
+
+    /// Inserts pasted text at the cursor. The prompt holds a single line, so
+    /// line breaks and any other control characters are left out.
+    pub fn paste(&mut self, text: &str) {
+        let text: String = text.chars().filter(|c| !c.is_control()).collect();
+        self.input.insert_str(self.byte_index(), &text);
+        self.cursor += text.chars().count();
+    }
+
+    fn byte_index(&self) -> usize {
+        self.input
+            .char_indices()
+            .nth(self.cursor)
+            .map_or(self.input.len(), |(i, _)| i)
+    }
+}
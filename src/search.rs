@@ -0,0 +1,332 @@
+use std::{
+    ops::Range,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+        mpsc::{self, Sender},
+    },
+    thread,
+};
+
+use crate::{event::Event, utils::strip_styling};
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchDirection {
+    #[default]
+    Forward,
+    Backward,
+}
+
+impl SearchDirection {
+    pub fn reversed(self) -> Self {
+        match self {
+            SearchDirection::Forward => SearchDirection::Backward,
+            SearchDirection::Backward => SearchDirection::Forward,
+        }
+    }
+
+    /// The key used to start a search in this direction, shown as the prompt.
+    pub fn prompt(self) -> char {
+        match self {
+            SearchDirection::Forward => '/',
+            SearchDirection::Backward => '?',
+        }
+    }
+}
+
+/// What searching and filtering look for in a line.
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    pub query: String,
+    /// Only match the query where it isn't part of a longer word, so `err`
+    /// finds `err:` but not `error`.
+    pub whole_word: bool,
+    /// Match letters whatever their case, so `err` also finds `ERR`.
+    pub ignore_case: bool,
+}
+
+impl Pattern {
+    pub fn new(query: String) -> Self {
+        Self {
+            query,
+            whole_word: false,
+            ignore_case: false,
+        }
+    }
+
+    /// Whether `line` has a match. It is the text as shown, with styling
+    /// stripped, so that escapes neither match nor sit at a word boundary.
+    pub fn is_match(&self, line: &str) -> bool {
+        self.matches(line).next().is_some()
+    }
+
+    /// Byte ranges of the non-overlapping matches in `text`.
+    pub fn match_ranges(&self, text: &str) -> Vec<Range<usize>> {
+        self.matches(text).collect()
+    }
+
+    fn matches<'a>(&'a self, text: &'a str) -> impl Iterator<Item = Range<usize>> + 'a {
+        let is_word = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+        let found: Vec<_> = if self.ignore_case && !self.query.is_empty() {
+            Self::matches_ignoring_case(text, &self.query)
+        } else {
+            text.match_indices(&self.query)
+                .map(|(start, m)| start..start + m.len())
+                .collect()
+        };
+        found.into_iter().filter(move |range| {
+            !self.whole_word
+                || !is_word(text[..range.start].chars().next_back())
+                    && !is_word(text[range.end..].chars().next())
+        })
+    }
+
+    /// Byte ranges of the non-overlapping matches of `query`, which isn't
+    /// empty, in `text`, with both lowercased. They are ranges of `text` as
+    /// it is, however lowercasing changes its length.
+    fn matches_ignoring_case(text: &str, query: &str) -> Vec<Range<usize>> {
+        let query: Vec<char> = query.chars().flat_map(char::to_lowercase).collect();
+        let mut ranges = Vec::new();
+        let mut start = 0;
+        while let Some(c) = text[start..].chars().next() {
+            match Self::lowercase_prefix(&text[start..], &query) {
+                Some(len) => {
+                    ranges.push(start..start + len);
+                    start += len;
+                }
+                None => start += c.len_utf8(),
+            }
+        }
+        ranges
+    }
+
+    /// Bytes at the start of `text` that lowercase to `query`, if they do.
+    fn lowercase_prefix(text: &str, query: &[char]) -> Option<usize> {
+        let mut rest = query;
+        for (i, c) in text.char_indices() {
+            for lower in c.to_lowercase() {
+                let (&next, tail) = rest.split_first()?;
+                if next != lower {
+                    return None;
+                }
+                rest = tail;
+            }
+            if rest.is_empty() {
+                return Some(i + c.len_utf8());
+            }
+        }
+        None
+    }
+}
+
+/// The last submitted search, which `n` and `N` repeat.
+#[derive(Debug, Clone)]
+pub struct Search {
+    pub pattern: Pattern,
+    pub direction: SearchDirection,
+}
+
+impl Search {
+    pub fn new(query: String, direction: SearchDirection) -> Self {
+        Self {
+            pattern: Pattern::new(query),
+            direction,
+        }
+    }
+
+    /// The same search, matching whole words only if `whole_word` is set.
+    pub fn whole_word(mut self, whole_word: bool) -> Self {
+        self.pattern.whole_word = whole_word;
+        self
+    }
+
+    /// The same search, matching letters whatever their case if
+    /// `ignore_case` is set.
+    pub fn ignore_case(mut self, ignore_case: bool) -> Self {
+        self.pattern.ignore_case = ignore_case;
+        self
+    }
+
+    pub fn is_match(&self, line: &str) -> bool {
+        self.pattern.is_match(line)
+    }
+}
+
+/// The rows matching a search, as found so far by a [`MatchCounter`].
+#[derive(Debug, Default)]
+pub struct MatchCount {
+    rows: Vec<usize>,
+    scanned: usize,
+}
+
+impl MatchCount {
+    /// Rows handed to the counter in one step.
+    pub const STEP: usize = 50_000;
+
+    /// Rows counted so far, from the first one.
+    pub fn scanned(&self) -> usize {
+        self.scanned
+    }
+
+    /// Records the matches among the next rows, up to `scanned`.
+    pub fn extend(&mut self, rows: impl IntoIterator<Item = usize>, scanned: usize) {
+        self.rows.extend(rows);
+        self.scanned = scanned;
+    }
+
+    pub fn total(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn last(&self) -> Option<usize> {
+        self.rows.last().copied()
+    }
+
+    /// The row of the match `ordinal`, counting from 1.
+    pub fn nth(&self, ordinal: usize) -> Option<usize> {
+        self.rows.get(ordinal.checked_sub(1)?).copied()
+    }
+
+    /// Which match, counting from 1, is at `row`.
+    pub fn ordinal(&self, row: usize) -> Option<usize> {
+        self.rows.binary_search(&row).ok().map(|i| i + 1)
+    }
+}
+
+/// Rows handed to a [`MatchCounter`], starting at the given row. Filter
+/// separators have no text.
+type Step = (usize, Vec<Option<Arc<str>>>);
+
+/// Counts the matches of a search on a worker thread so that a huge buffer
+/// doesn't hold up the screen. The worker is handed rows a step at a time and
+/// sends the matching ones back as [`Event::Matches`], tagged with the
+/// counter's id. Dropping the counter stops the worker after its current
+/// step, and whatever it still sends is told apart by the id.
+#[derive(Debug)]
+pub struct MatchCounter {
+    id: u64,
+    steps: Sender<Step>,
+    /// Whether a step is out whose matches haven't come back yet.
+    busy: bool,
+}
+
+impl MatchCounter {
+    pub fn spawn(search: &Search, tx: Sender<Event>) -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let (steps, rx) = mpsc::channel::<Step>();
+        let search = search.clone();
+        thread::spawn(move || {
+            for (start, lines) in rx {
+                let scanned = start + lines.len();
+                let rows = lines
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, line)| {
+                        line.as_deref()
+                            .is_some_and(|line| search.is_match(&strip_styling(line)))
+                    })
+                    .map(|(i, _)| start + i)
+                    .collect();
+                if tx.send(Event::Matches { id, rows, scanned }).is_err() {
+                    break;
+                }
+            }
+        });
+        Self {
+            id,
+            steps,
+            busy: false,
+        }
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn busy(&self) -> bool {
+        self.busy
+    }
+
+    /// Hands the worker the next rows to look at, starting at row `start`.
+    pub fn send(&mut self, start: usize, lines: Vec<Option<Arc<str>>>) {
+        self.busy = self.steps.send((start, lines)).is_ok();
+    }
+
+    /// Notes that the matches of the step that was out have come back.
+    pub fn done(&mut self) {
+        self.busy = false;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, mpsc};
+
+    use crate::{
+        event::Event,
+        search::{MatchCount, MatchCounter, Pattern, Search, SearchDirection},
+    };
+
+    #[test]
+    fn test_whole_word() {
+        let mut pattern = Pattern::new("err".to_string());
+        assert!(pattern.is_match("error: disk full"));
+        pattern.whole_word = true;
+        assert!(!pattern.is_match("error: disk full"));
+        assert!(!pattern.is_match("stderr"));
+        assert!(!pattern.is_match("err_code"));
+        assert!(pattern.is_match("err"));
+        assert!(pattern.is_match("[err] disk full"));
+        assert_eq!(
+            pattern.match_ranges("errors, err, err2 (err)"),
+            [8..11, 19..22]
+        );
+    }
+
+    #[test]
+    fn test_ignore_case() {
+        let mut pattern = Pattern::new("Err".to_string());
+        assert!(!pattern.is_match("ERROR"));
+        pattern.ignore_case = true;
+        assert!(pattern.is_match("ERROR"));
+        assert!(pattern.is_match("an err"));
+        // ranges are of the text as given, though some letters lowercase to
+        // more bytes or fewer
+        assert_eq!(pattern.match_ranges("İERR, err"), [2..5, 7..10]);
+        pattern.whole_word = true;
+        assert_eq!(pattern.match_ranges("ERRORS, ERR (err)"), [8..11, 13..16]);
+    }
+
+    #[test]
+    fn test_match_counter() {
+        let (tx, rx) = mpsc::channel();
+        let search = Search::new("x".to_string(), SearchDirection::Forward);
+        let mut counter = MatchCounter::spawn(&search, tx);
+        let lines = ["ax", "b", "xx"].map(|line| Some(Arc::from(line)));
+        counter.send(10, [lines.to_vec(), vec![None]].concat());
+        assert!(counter.busy());
+        let Ok(Event::Matches { id, rows, scanned }) = rx.recv() else {
+            panic!("expected matches");
+        };
+        assert_eq!(id, counter.id());
+        assert_eq!(rows, [10, 12]);
+        assert_eq!(scanned, 14);
+
+        // the worker stops once its counter is gone
+        drop(counter);
+        assert!(rx.recv().is_err());
+    }
+
+    #[test]
+    fn test_match_count() {
+        let mut count = MatchCount::default();
+        count.extend([1, 4], 5);
+        count.extend([7], 8);
+        assert_eq!(count.scanned(), 8);
+        assert_eq!(count.total(), 3);
+        assert_eq!(count.ordinal(4), Some(2));
+        assert_eq!(count.ordinal(7), Some(3));
+        assert_eq!(count.ordinal(5), None);
+    }
+}
@@ -0,0 +1,211 @@
+//! Syntax highlighting of source files with syntect, chosen by the file's
+//! extension or by `--syntax-lang`.
+
+use std::{path::Path, sync::Arc};
+
+use color_eyre::eyre::eyre;
+use lazy_static::lazy_static;
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::Span,
+};
+use syntect::{
+    highlighting::{self, FontStyle, HighlightIterator, HighlightState, Theme, ThemeSet},
+    parsing::{ParseState, ScopeStack, SyntaxReference, SyntaxSet},
+};
+
+use crate::error::*;
+
+lazy_static! {
+    static ref SYNTAXES: SyntaxSet = SyntaxSet::load_defaults_newlines();
+    static ref THEMES: ThemeSet = ThemeSet::load_defaults();
+}
+
+/// Where the parser and highlighter are at the start of a line.
+type State = (ParseState, HighlightState);
+
+/// The language and theme highlighting is asked for, both looked up as the
+/// pager starts so that a bad name is reported right away.
+#[derive(Debug, Clone, Copy)]
+pub struct Syntax {
+    theme: &'static Theme,
+    /// The language all inputs are in, rather than the one their file
+    /// extension says.
+    language: Option<&'static SyntaxReference>,
+}
+
+impl Syntax {
+    /// Looks up `theme`, and `language` by name or by extension, like `rust`
+    /// or `rs`.
+    pub fn new(language: Option<&str>, theme: &str) -> Result<Self> {
+        let theme = THEMES.themes.get(theme).ok_or_else(|| {
+            let known: Vec<_> = THEMES.themes.keys().map(String::as_str).collect();
+            eyre!(
+                "unknown syntax theme {theme}, expected one of: {}",
+                known.join(", ")
+            )
+        })?;
+        let language = language
+            .map(|language| {
+                SYNTAXES
+                    .find_syntax_by_token(language)
+                    .ok_or_else(|| eyre!("unknown language: {language}"))
+            })
+            .transpose()?;
+        Ok(Self { theme, language })
+    }
+
+    /// A highlighter for an input at `path`, unless it is in no language
+    /// known by its extension or name.
+    pub fn highlighter(&self, path: Option<&Path>) -> Option<Highlighter> {
+        let by_name = || {
+            let path = path?;
+            [path.extension(), path.file_name()]
+                .into_iter()
+                .flatten()
+                .filter_map(|name| name.to_str())
+                .find_map(|name| SYNTAXES.find_syntax_by_extension(name))
+        };
+        let language = self.language.or_else(by_name)?;
+        Some(Highlighter::new(language, self.theme))
+    }
+}
+
+/// Highlights the lines of one input. A line's colors depend on every line
+/// before it, so the state at the start of every [`Self::CHECKPOINT`]th line
+/// is kept to pick up from, as is the state after the last line highlighted,
+/// which is where drawing a screen goes next.
+pub struct Highlighter {
+    theme: &'static Theme,
+    checkpoints: Vec<State>,
+    /// The line highlighted last, and the state after it.
+    last: Option<(usize, State)>,
+}
+
+impl Highlighter {
+    /// Lines between kept states.
+    const CHECKPOINT: usize = 64;
+
+    fn new(syntax: &SyntaxReference, theme: &'static Theme) -> Self {
+        let highlighter = highlighting::Highlighter::new(theme);
+        let start = (
+            ParseState::new(syntax),
+            HighlightState::new(&highlighter, ScopeStack::new()),
+        );
+        Self {
+            theme,
+            checkpoints: vec![start],
+            last: None,
+        }
+    }
+
+    /// The spans of `lines[index]`, colored by what comes before it.
+    pub fn highlight(&mut self, lines: &[Arc<str>], index: usize) -> Vec<Span<'static>> {
+        let (mut line, mut state) = match self.last.take() {
+            Some((last, state)) if last + 1 == index => (index, state),
+            _ => {
+                let checkpoint = (index / Self::CHECKPOINT).min(self.checkpoints.len() - 1);
+                (
+                    checkpoint * Self::CHECKPOINT,
+                    self.checkpoints[checkpoint].clone(),
+                )
+            }
+        };
+        let highlighter = highlighting::Highlighter::new(self.theme);
+        loop {
+            if line % Self::CHECKPOINT == 0 && line / Self::CHECKPOINT == self.checkpoints.len() {
+                self.checkpoints.push(state.clone());
+            }
+            let spans = Self::highlight_line(&mut state, &highlighter, &lines[line]);
+            if line == index {
+                self.last = Some((index, state));
+                return spans;
+            }
+            line += 1;
+        }
+    }
+
+    /// Forgets what was worked out from line `index` on, as the line has
+    /// changed.
+    pub fn forget(&mut self, index: usize) {
+        self.checkpoints.truncate(index / Self::CHECKPOINT + 1);
+        self.last = None;
+    }
+
+    fn highlight_line(
+        (parse_state, highlight_state): &mut State,
+        highlighter: &highlighting::Highlighter,
+        line: &str,
+    ) -> Vec<Span<'static>> {
+        // the syntaxes expect lines to end in a newline
+        let text = format!("{line}\n");
+        let Ok(ops) = parse_state.parse_line(&text, &SYNTAXES) else {
+            return vec![Span::raw(line.to_string())];
+        };
+        HighlightIterator::new(highlight_state, &ops, &text, highlighter)
+            .map(|(style, piece)| (style, piece.strip_suffix('\n').unwrap_or(piece)))
+            .filter(|(_, piece)| !piece.is_empty())
+            .map(|(style, piece)| Span::styled(piece.to_string(), to_style(style)))
+            .collect()
+    }
+}
+
+/// The foreground and font style of a syntect style. The background is left
+/// to the terminal.
+fn to_style(style: highlighting::Style) -> Style {
+    let highlighting::Color { r, g, b, .. } = style.foreground;
+    let mut modifier = Modifier::empty();
+    for (font, shown) in [
+        (FontStyle::BOLD, Modifier::BOLD),
+        (FontStyle::ITALIC, Modifier::ITALIC),
+        (FontStyle::UNDERLINE, Modifier::UNDERLINED),
+    ] {
+        if style.font_style.contains(font) {
+            modifier |= shown;
+        }
+    }
+    Style::new().fg(Color::Rgb(r, g, b)).add_modifier(modifier)
+}
+
+#[cfg(test)]
+mod test {
+    use std::{path::Path, sync::Arc};
+
+    use ratatui::text::Line;
+
+    use crate::syntax::Syntax;
+
+    #[test]
+    fn test_highlighter() {
+        let theme = "base16-ocean.dark";
+        assert!(Syntax::new(None, "no such theme").is_err());
+        assert!(Syntax::new(Some("no such language"), theme).is_err());
+        let syntax = Syntax::new(None, theme).unwrap();
+        assert!(
+            syntax
+                .highlighter(Some(Path::new("notes.unknown")))
+                .is_none()
+        );
+        assert!(syntax.highlighter(None).is_none());
+        assert!(syntax.highlighter(Some(Path::new("Makefile"))).is_some());
+        let rust = Syntax::new(Some("rust"), theme).unwrap();
+        assert!(rust.highlighter(None).is_some());
+
+        let mut highlighter = syntax.highlighter(Some(Path::new("src/main.rs"))).unwrap();
+        let lines: Vec<Arc<str>> = (0..200)
+            .map(|i| match i {
+                0 => Arc::from("/* a comment"),
+                199 => Arc::from("   still one */ fn main() {}"),
+                _ => Arc::from("fn f() {}"),
+            })
+            .collect();
+        let last = highlighter.highlight(&lines, 199);
+        assert_eq!(Line::from(last.clone()).to_string(), lines[199].as_ref());
+        // lines are colored by what comes before, whichever order they are
+        // highlighted in
+        let first = highlighter.highlight(&lines, 0);
+        assert_eq!(highlighter.highlight(&lines, 199), last);
+        assert_eq!(first.last().unwrap().style, last[0].style);
+        assert!(last.iter().any(|span| span.style != last[0].style));
+    }
+}
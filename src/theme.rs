@@ -0,0 +1,114 @@
+//! The colors the pager draws its own parts in, picked to stand out against
+//! a dark or a light terminal background.
+
+use clap::ValueEnum;
+use ratatui::style::{Color, Style, Stylize};
+
+/// What the terminal draws text on.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Background {
+    #[default]
+    Dark,
+    Light,
+}
+
+impl Background {
+    /// The background the terminal says it has in `COLORFGBG`, as rxvt and
+    /// Konsole set it, or dark when it doesn't say.
+    pub fn detect() -> Self {
+        std::env::var("COLORFGBG")
+            .ok()
+            .and_then(|colors| Self::from_colorfgbg(&colors))
+            .unwrap_or_default()
+    }
+
+    /// Reads `fg;bg`, or `fg;default;bg`, where the background is one of
+    /// the 16 basic colors.
+    fn from_colorfgbg(colors: &str) -> Option<Self> {
+        let background: u8 = colors.rsplit(';').next()?.parse().ok()?;
+        match background {
+            7 | 9..=15 => Some(Self::Light),
+            0..=6 | 8 => Some(Self::Dark),
+            _ => None,
+        }
+    }
+
+    /// The syntax theme that goes with the background, unless
+    /// `--syntax-theme` picks one.
+    pub fn syntax_theme(self) -> &'static str {
+        match self {
+            Self::Dark => "base16-ocean.dark",
+            Self::Light => "base16-ocean.light",
+        }
+    }
+}
+
+/// The styles of everything drawn that isn't styled by the input itself.
+#[derive(Debug, Clone, Copy)]
+pub struct Theme {
+    pub background: Background,
+    /// Text that sets no color of its own.
+    pub text: Style,
+    /// The status bar's input name and the messages shown in its place.
+    pub status: Style,
+    /// The line under the cursor with `--cursor`.
+    pub cursor_line: Style,
+    /// The part of `--position-bar` that has been scrolled past.
+    pub bar_done: Style,
+    /// The rest of `--position-bar`.
+    pub bar_left: Style,
+}
+
+impl Theme {
+    pub fn new(background: Background) -> Self {
+        match background {
+            Background::Dark => Self {
+                background,
+                text: Style::new().white(),
+                status: Style::new().reversed(),
+                cursor_line: Style::new().bg(Color::Indexed(236)),
+                bar_done: Style::new().fg(Color::Cyan),
+                bar_left: Style::new().fg(Color::Indexed(238)),
+            },
+            Background::Light => Self {
+                background,
+                text: Style::new().black(),
+                status: Style::new().black().bg(Color::Indexed(252)),
+                cursor_line: Style::new().bg(Color::Indexed(254)),
+                bar_done: Style::new().fg(Color::Blue),
+                bar_left: Style::new().fg(Color::Indexed(250)),
+            },
+        }
+    }
+
+    /// The theme for the other background.
+    pub fn toggled(self) -> Self {
+        Self::new(match self.background {
+            Background::Dark => Background::Light,
+            Background::Light => Background::Dark,
+        })
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::new(Background::default())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::theme::Background;
+
+    #[test]
+    fn test_from_colorfgbg() {
+        assert_eq!(Background::from_colorfgbg("15;0"), Some(Background::Dark));
+        assert_eq!(Background::from_colorfgbg("0;15"), Some(Background::Light));
+        assert_eq!(
+            Background::from_colorfgbg("0;default;7"),
+            Some(Background::Light)
+        );
+        assert_eq!(Background::from_colorfgbg("15;default"), None);
+        assert_eq!(Background::from_colorfgbg(""), None);
+    }
+}
@@ -1,6 +1,6 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use color_eyre::eyre::Result;
+use color_eyre::eyre::{Result, eyre};
 use directories::ProjectDirs;
 use lazy_static::lazy_static;
 use tracing_error::ErrorLayer;
@@ -21,24 +21,56 @@ fn project_directory() -> Option<ProjectDirs> {
 }
 
 pub fn get_data_dir() -> PathBuf {
-    let directory = if let Some(s) = DATA_FOLDER.clone() {
+    if let Some(s) = DATA_FOLDER.clone() {
         s
     } else if let Some(proj_dirs) = project_directory() {
         proj_dirs.data_local_dir().to_path_buf()
     } else {
         PathBuf::from(".").join(".data")
+    }
+}
+
+/// Where state worth keeping between runs, but safe to lose, is stored.
+pub fn get_cache_dir() -> PathBuf {
+    if let Some(s) = DATA_FOLDER.clone() {
+        s
+    } else if let Some(proj_dirs) = project_directory() {
+        proj_dirs.cache_dir().to_path_buf()
+    } else {
+        PathBuf::from(".").join(".cache")
+    }
+}
+
+/// The filter for `verbose` times `-v`: each one logs a level more than the
+/// default `info`. Without any, `RUST_LOG` or the crate's own variable may
+/// pick the filter instead.
+fn log_filter(verbose: u8) -> String {
+    let level = match verbose {
+        0 => {
+            return std::env::var("RUST_LOG")
+                .or_else(|_| std::env::var(LOG_ENV.clone()))
+                .unwrap_or_else(|_| format!("{}=info", env!("CARGO_CRATE_NAME")));
+        }
+        1 => "debug",
+        _ => "trace",
     };
-    directory
+    format!("{}={level}", env!("CARGO_CRATE_NAME"))
 }
 
-pub fn initialize_logging() -> Result<()> {
-    let directory = get_data_dir();
-    std::fs::create_dir_all(directory.clone())?;
-    let log_path = directory.join(LOG_FILE.clone());
-    let log_file = std::fs::File::create(log_path)?;
-    let log_filter = std::env::var("RUST_LOG")
-        .or_else(|_| std::env::var(LOG_ENV.clone()))
-        .unwrap_or_else(|_| format!("{}=info", env!("CARGO_CRATE_NAME")));
+/// Logs to `log_file`, or to a file in the data directory. Never to the
+/// terminal, which the pager draws on.
+pub fn initialize_logging(verbose: u8, log_file: Option<&Path>) -> Result<()> {
+    let log_path = match log_file {
+        Some(path) => path.to_path_buf(),
+        None => {
+            let directory = get_data_dir();
+            std::fs::create_dir_all(directory.clone())?;
+            directory.join(LOG_FILE.clone())
+        }
+    };
+    let log_file = std::fs::File::create(&log_path)
+        .map_err(|e| eyre!("'{}': {}", log_path.to_string_lossy(), e))?;
+    let log_filter = log_filter(verbose);
     let file_subscriber = tracing_subscriber::fmt::layer()
         .with_file(true)
         .with_line_number(true)
@@ -78,3 +110,15 @@ macro_rules! trace_dbg {
         trace_dbg!(level: tracing::Level::DEBUG, $ex)
     };
 }
+
+#[cfg(test)]
+mod test {
+    use crate::tracing::log_filter;
+
+    #[test]
+    fn test_log_filter() {
+        assert_eq!(log_filter(1), "least=debug");
+        assert_eq!(log_filter(2), "least=trace");
+        assert_eq!(log_filter(3), "least=trace");
+    }
+}
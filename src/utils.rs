@@ -1,37 +1,296 @@
-use std::io::Read;
+use std::{
+    io::Read,
+    num::NonZeroUsize,
+    ops::Range,
+    str::FromStr,
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
+use clap::ValueEnum;
 use ratatui::{
-    style::{Style, Stylize},
-    text::Span,
+    style::{Color, Modifier, Style, Stylize},
+    text::{Line, Span},
 };
+use unicode_width::UnicodeWidthChar;
 
 use crate::error::*;
 
-pub fn count_lines<R: Read>(reader: &mut R) -> Result<usize> {
+/// Counts the lines in `reader` the way the input reader splits them at
+/// `delimiter`, so a last line without one counts too.
+pub fn count_lines<R: Read>(reader: &mut R, delimiter: u8) -> Result<usize> {
     let mut buf = [0u8; 32 * 1024];
     let mut count = 0;
+    let mut last = None;
 
     loop {
-        let n = reader.read(&mut buf)?;
+        let n = match reader.read(&mut buf) {
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            res => res?,
+        };
         if n == 0 {
             break;
         }
-        count += buf[..n].iter().filter(|&&b| b == b'\n').count();
+        count += buf[..n].iter().filter(|&&b| b == delimiter).count();
+        last = Some(buf[n - 1]);
     }
 
+    if last.is_some_and(|b| b != delimiter) {
+        count += 1;
+    }
     Ok(count)
 }
 
+/// Splits `s` into arguments the way a shell would for simple cases:
+/// whitespace separates arguments, and single or double quotes group them.
+pub fn split_args(s: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut arg = None::<String>;
+    let mut quote = None;
+    for c in s.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => arg.get_or_insert_default().push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                arg.get_or_insert_default();
+            }
+            (None, c) if c.is_whitespace() => args.extend(arg.take()),
+            (None, c) => arg.get_or_insert_default().push(c),
+        }
+    }
+    args.extend(arg);
+    args
+}
+
+/// Positions of every `delimiter`, usually `\n`, in `bytes`, in order. Big
+/// inputs are split into chunks that are scanned on separate threads.
+pub fn newline_offsets(bytes: &[u8], delimiter: u8) -> Vec<usize> {
+    /// Smallest chunk worth a thread of its own. `memchr` gets through much
+    /// less in about the time it takes to start one, so a block read is
+    /// mostly scanned on the reader's own thread.
+    const MIN_CHUNK: usize = 4 << 20;
+    let threads = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+    let chunk = bytes.len().div_ceil(threads).max(MIN_CHUNK);
+    newline_offsets_in_chunks(bytes, delimiter, chunk)
+}
+
+fn newline_offsets_in_chunks(bytes: &[u8], delimiter: u8, chunk: usize) -> Vec<usize> {
+    let scan = |chunk: &[u8], start: usize| {
+        memchr::memchr_iter(delimiter, chunk)
+            .map(|i| start + i)
+            .collect::<Vec<_>>()
+    };
+    if bytes.len() <= chunk {
+        return scan(bytes, 0);
+    }
+    // chunks don't overlap, so a newline at the edge of one is found once
+    thread::scope(|scope| {
+        let scans: Vec<_> = bytes
+            .chunks(chunk)
+            .enumerate()
+            .map(|(i, part)| scope.spawn(move || scan(part, i * chunk)))
+            .collect();
+        scans
+            .into_iter()
+            .flat_map(|scan| scan.join().unwrap())
+            .collect()
+    })
+}
+
+/// Removes the line ending from a line read up to and including `delimiter`.
+/// For `\n`, the `\r` of a CRLF ending goes too, unless `keep_cr` is set; a
+/// `\r` anywhere else is left alone.
+pub fn trim_line_ending(line: &str, delimiter: u8, keep_cr: bool) -> &str {
+    match line.strip_suffix(char::from(delimiter)) {
+        Some(line) if keep_cr || delimiter != b'\n' => line,
+        Some(line) => line.strip_suffix('\r').unwrap_or(line),
+        None => line,
+    }
+}
+
+/// Guesses whether `sample`, the first bytes of an input, is binary data
+/// rather than text: it contains NUL bytes or is mostly control characters.
+pub fn looks_binary(sample: &[u8]) -> bool {
+    if sample.is_empty() {
+        return false;
+    }
+    if content_inspector::inspect(sample).is_binary() {
+        return true;
+    }
+    // tabs, newlines, form feeds, vertical tabs, overstrike and escape
+    // sequences all turn up in text that is meant to be paged
+    let control = sample
+        .iter()
+        .filter(|&&b| {
+            b.is_ascii_control() && !matches!(b, b'\t' | b'\n' | b'\r' | 0x08 | 0x0b | 0x0c | 0x1b)
+        })
+        .count();
+    control * 100 / sample.len() > 30
+}
+
+/// An escape sequence found in a line by [`parse_escape`].
+enum Escape {
+    /// `ESC [ ... m`, a change of colors or attributes.
+    Sgr(Vec<u16>),
+    /// `ESC ] 8 ; params ; URL ST`, which starts a hyperlink, or ends one
+    /// when the URL is empty.
+    Link(Option<String>),
+    /// Anything else, which is dropped rather than shown as garbage.
+    Other,
+}
+
+/// Parses the escape sequence starting at the `ESC` at `input[start]`,
+/// returning it along with the index just past it. An unterminated sequence
+/// runs to the end of the line.
+fn parse_escape(input: &[char], start: usize) -> (Escape, usize) {
+    let mut i = start + 1;
+    match input.get(i) {
+        Some('[') => {
+            i += 1;
+            let params_start = i;
+            // parameter and intermediate bytes, then a final byte
+            while i < input.len() && matches!(input[i], '\x20'..='\x3f') {
+                i += 1;
+            }
+            let params: String = input[params_start..i].iter().collect();
+            let end = (i + 1).min(input.len());
+            match input.get(i) {
+                Some('m') => {
+                    // an empty parameter means 0, as in `ESC [ m`
+                    let params = params
+                        .split([';', ':'])
+                        .map(|param| param.parse().unwrap_or(0))
+                        .collect();
+                    (Escape::Sgr(params), end)
+                }
+                _ => (Escape::Other, end),
+            }
+        }
+        Some(']') => {
+            i += 1;
+            let body_start = i;
+            // terminated by BEL or by ST, which is `ESC \`
+            let (body_end, end) = loop {
+                match input.get(i) {
+                    None => break (i, i),
+                    Some('\x07') => break (i, i + 1),
+                    Some('\x1b') if input.get(i + 1) == Some(&'\\') => break (i, i + 2),
+                    Some(_) => i += 1,
+                }
+            };
+            let body: String = input[body_start..body_end].iter().collect();
+            match body
+                .strip_prefix("8;")
+                .and_then(|link| link.split_once(';'))
+            {
+                Some((_, "")) => (Escape::Link(None), end),
+                Some((_, url)) => (Escape::Link(Some(url.to_string())), end),
+                None => (Escape::Other, end),
+            }
+        }
+        Some(_) => (Escape::Other, i + 1),
+        None => (Escape::Other, i),
+    }
+}
+
+/// Looks up color `n` of the 8 basic or, when `bright`, the 8 bright ones.
+fn ansi_color(n: u16, bright: bool) -> Color {
+    let n = n as u8 + if bright { 8 } else { 0 };
+    match n {
+        0 => Color::Black,
+        1 => Color::Red,
+        2 => Color::Green,
+        3 => Color::Yellow,
+        4 => Color::Blue,
+        5 => Color::Magenta,
+        6 => Color::Cyan,
+        7 => Color::Gray,
+        8 => Color::DarkGray,
+        9 => Color::LightRed,
+        10 => Color::LightGreen,
+        11 => Color::LightYellow,
+        12 => Color::LightBlue,
+        13 => Color::LightMagenta,
+        14 => Color::LightCyan,
+        _ => Color::White,
+    }
+}
+
+/// Reads an extended color, `5;n` or `2;r;g;b`, from the parameters that
+/// follow a 38 or 48.
+fn extended_color(params: &mut impl Iterator<Item = u16>) -> Option<Color> {
+    match params.next()? {
+        5 => Some(Color::Indexed(params.next()? as u8)),
+        2 => {
+            let (r, g, b) = (params.next()?, params.next()?, params.next()?);
+            Some(Color::Rgb(r as u8, g as u8, b as u8))
+        }
+        _ => None,
+    }
+}
+
+/// Applies the SGR parameters `params` to `style`.
+fn apply_sgr(mut style: Style, params: &[u16]) -> Style {
+    let mut params = params.iter().copied();
+    while let Some(param) = params.next() {
+        style = match param {
+            0 => Style::default(),
+            1 => style.add_modifier(Modifier::BOLD),
+            2 => style.add_modifier(Modifier::DIM),
+            3 => style.add_modifier(Modifier::ITALIC),
+            4 => style.add_modifier(Modifier::UNDERLINED),
+            5 => style.add_modifier(Modifier::SLOW_BLINK),
+            7 => style.add_modifier(Modifier::REVERSED),
+            8 => style.add_modifier(Modifier::HIDDEN),
+            9 => style.add_modifier(Modifier::CROSSED_OUT),
+            22 => style.remove_modifier(Modifier::BOLD | Modifier::DIM),
+            23 => style.remove_modifier(Modifier::ITALIC),
+            24 => style.remove_modifier(Modifier::UNDERLINED),
+            25 => style.remove_modifier(Modifier::SLOW_BLINK),
+            27 => style.remove_modifier(Modifier::REVERSED),
+            28 => style.remove_modifier(Modifier::HIDDEN),
+            29 => style.remove_modifier(Modifier::CROSSED_OUT),
+            30..=37 => style.fg(ansi_color(param - 30, false)),
+            38 => match extended_color(&mut params) {
+                Some(color) => style.fg(color),
+                None => style,
+            },
+            39 => Style { fg: None, ..style },
+            40..=47 => style.bg(ansi_color(param - 40, false)),
+            48 => match extended_color(&mut params) {
+                Some(color) => style.bg(color),
+                None => style,
+            },
+            49 => Style { bg: None, ..style },
+            90..=97 => style.fg(ansi_color(param - 90, true)),
+            100..=107 => style.bg(ansi_color(param - 100, true)),
+            _ => style,
+        };
+    }
+    style
+}
+
+/// Turns a line into styled spans, interpreting overstrike (`X\bX` for bold,
+/// `_\bX` for underline) and ANSI escape sequences. Hyperlinks are shown as
+/// their underlined text, since the URL can't be handed on to the terminal
+/// cell by cell.
 pub fn parse_styled_spans(input: Vec<u8>) -> Vec<Span<'static>> {
     enum State {
         Idle,
-        SawChar(u8),
-        SawCharBack(u8),
+        SawChar(char),
+        SawCharBack(char),
     }
 
+    let input: Vec<char> = String::from_utf8_lossy(&input).chars().collect();
+
     let mut result = Vec::new();
     let mut current_style = Style::default();
     let mut current_text = String::new();
+    // the style set by escape sequences, which overstrike adds to
+    let mut base = Style::default();
+    let mut ansi = Style::default();
+    let mut link = false;
 
     let mut state = State::Idle;
     let mut i = 0;
@@ -40,71 +299,72 @@ pub fn parse_styled_spans(input: Vec<u8>) -> Vec<Span<'static>> {
         |result: &mut Vec<Span>, current_text: &mut String, style: &mut Style, new_style: Style| {
             if *style != new_style {
                 if !current_text.is_empty() {
-                    result.push(Span::styled(current_text.clone(), *style));
-                    current_text.clear();
+                    result.push(Span::styled(std::mem::take(current_text), *style));
                 }
                 *style = new_style;
             }
         };
 
     while i < input.len() {
-        let byte = input[i];
+        let ch = input[i];
+        if ch == '\x1b' {
+            // whatever was waiting for a backspace is plain text after all
+            if let State::SawChar(prev) | State::SawCharBack(prev) = state {
+                push_span(&mut result, &mut current_text, &mut current_style, base);
+                current_text.push(prev);
+            }
+            let (escape, next) = parse_escape(&input, i);
+            match escape {
+                Escape::Sgr(params) => ansi = apply_sgr(ansi, &params),
+                Escape::Link(url) => link = url.is_some(),
+                Escape::Other => {}
+            }
+            base = if link { ansi.underlined() } else { ansi };
+            state = State::Idle;
+            i = next;
+            continue;
+        }
         match state {
             State::Idle => {
-                state = State::SawChar(byte);
+                state = State::SawChar(ch);
                 i += 1;
             }
             State::SawChar(prev) => {
-                if byte == 0x08 {
+                if ch == '\x08' {
                     state = State::SawCharBack(prev);
                     i += 1;
                 } else {
-                    push_span(
-                        &mut result,
-                        &mut current_text,
-                        &mut current_style,
-                        Style::default(),
-                    );
-                    current_text.push(prev as char);
-                    state = State::SawChar(byte);
+                    push_span(&mut result, &mut current_text, &mut current_style, base);
+                    current_text.push(prev);
+                    state = State::SawChar(ch);
                     i += 1;
                 }
             }
             State::SawCharBack(prev) => {
-                if prev == byte {
+                if prev == ch {
                     // X\bX → Bold
                     push_span(
                         &mut result,
                         &mut current_text,
                         &mut current_style,
-                        Style::new().bold(),
+                        base.bold(),
                     );
-                    current_text.push(byte as char);
-                } else if prev == b'_' {
+                    current_text.push(ch);
+                } else if prev == '_' {
                     // _\bX → Underline
                     push_span(
                         &mut result,
                         &mut current_text,
                         &mut current_style,
-                        Style::default().underlined(),
+                        base.underlined(),
                     );
-                    current_text.push(byte as char);
+                    current_text.push(ch);
                 } else {
                     // Not a recognized pattern, emit prev and handle current as new
-                    push_span(
-                        &mut result,
-                        &mut current_text,
-                        &mut current_style,
-                        Style::default(),
-                    );
-                    current_text.push(prev as char);
-                    push_span(
-                        &mut result,
-                        &mut current_text,
-                        &mut current_style,
-                        Style::default(),
-                    );
-                    current_text.push(byte as char);
+                    push_span(&mut result, &mut current_text, &mut current_style, base);
+                    current_text.push(prev);
+                    push_span(&mut result, &mut current_text, &mut current_style, base);
+                    current_text.push(ch);
                 }
                 state = State::Idle;
                 i += 1;
@@ -114,13 +374,8 @@ pub fn parse_styled_spans(input: Vec<u8>) -> Vec<Span<'static>> {
 
     // Flush remaining state
     if let State::SawChar(c) = state {
-        push_span(
-            &mut result,
-            &mut current_text,
-            &mut current_style,
-            Style::default(),
-        );
-        current_text.push(c as char);
+        push_span(&mut result, &mut current_text, &mut current_style, base);
+        current_text.push(c);
     }
 
     if !current_text.is_empty() {
@@ -129,14 +384,583 @@ pub fn parse_styled_spans(input: Vec<u8>) -> Vec<Span<'static>> {
     result
 }
 
+/// How far the rows a wrapped line continues on are indented.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WrapIndent {
+    /// As far as the line itself is indented.
+    Keep,
+    /// By a fixed number of columns.
+    Columns(usize),
+}
+
+impl Default for WrapIndent {
+    fn default() -> Self {
+        WrapIndent::Columns(0)
+    }
+}
+
+impl FromStr for WrapIndent {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "keep" => Ok(WrapIndent::Keep),
+            _ => s
+                .parse()
+                .map(WrapIndent::Columns)
+                .map_err(|_| format!("expected `keep` or a number of columns, got `{s}`")),
+        }
+    }
+}
+
+/// The text of `line` without its styling: overstrike collapsed to the
+/// characters it shows and escape sequences dropped.
+pub fn strip_styling(line: &str) -> String {
+    parse_styled_spans(line.as_bytes().to_vec())
+        .into_iter()
+        .map(|span| span.content)
+        .collect()
+}
+
+/// Splits `line` into rows at most `width` columns wide. A character that
+/// doesn't fit in what is left of a row starts the next one, so every row but
+/// the last may be a column short when wide characters are involved. Rows
+/// after the first are indented as `indent` says, by at most half the width
+/// so that they keep some room for text.
+pub fn wrap_line(line: Line<'_>, width: usize, indent: WrapIndent) -> Vec<Line<'static>> {
+    let width = width.max(1);
+    let indent = match indent {
+        WrapIndent::Keep => line
+            .spans
+            .iter()
+            .flat_map(|span| span.content.chars())
+            .take_while(|c| c.is_whitespace())
+            .map(|c| c.width().unwrap_or(0))
+            .sum(),
+        WrapIndent::Columns(columns) => columns,
+    }
+    .min(width / 2);
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut row_width = 0;
+    // where the text of the current row starts, after any indentation
+    let mut row_start = 0;
+    for span in line.spans {
+        let mut text = String::new();
+        for c in span.content.chars() {
+            let w = c.width().unwrap_or(0);
+            if row_width + w > width && row_width > row_start {
+                if !text.is_empty() {
+                    row.push(Span::styled(std::mem::take(&mut text), span.style));
+                }
+                rows.push(Line::from(std::mem::take(&mut row)));
+                if indent > 0 {
+                    row.push(Span::raw(" ".repeat(indent)));
+                }
+                row_width = indent;
+                row_start = indent;
+            }
+            text.push(c);
+            row_width += w;
+        }
+        if !text.is_empty() {
+            row.push(Span::styled(text, span.style));
+        }
+    }
+    rows.push(Line::from(row));
+    rows
+}
+
+/// Columns between tab stops unless `--tabs` says otherwise.
+pub const TAB_WIDTH: usize = 8;
+
+/// Replaces tabs with blanks up to the next tab stop, every `tab_width`
+/// columns, or with a dim arrow followed by blanks when `show` is set.
+pub fn expand_tabs(spans: Vec<Span<'static>>, tab_width: usize, show: bool) -> Vec<Span<'static>> {
+    if !spans.iter().any(|span| span.content.contains('\t')) {
+        return spans;
+    }
+    let mut expanded = Vec::with_capacity(spans.len());
+    let mut column = 0;
+    for span in spans {
+        let mut text = String::new();
+        for c in span.content.chars() {
+            if c != '\t' {
+                text.push(c);
+                column += c.width().unwrap_or(0);
+                continue;
+            }
+            let width = tab_width - column % tab_width;
+            column += width;
+            if show {
+                if !text.is_empty() {
+                    expanded.push(Span::styled(std::mem::take(&mut text), span.style));
+                }
+                let marker = format!("→{}", " ".repeat(width - 1));
+                expanded.push(Span::styled(marker, span.style.dim()));
+            } else {
+                text.extend(std::iter::repeat_n(' ', width));
+            }
+        }
+        if !text.is_empty() {
+            expanded.push(Span::styled(text, span.style));
+        }
+    }
+    expanded
+}
+
+/// Shows the spaces ending a line as dim dots, keeping the style of the span
+/// each is in. Tabs among them are left for [`expand_tabs`] to show.
+pub fn mark_trailing_spaces(spans: Vec<Span<'static>>) -> Vec<Span<'static>> {
+    let text: String = spans.iter().map(|span| span.content.as_ref()).collect();
+    let start = text.trim_end_matches([' ', '\t']).len();
+    if !text[start..].contains(' ') {
+        return spans;
+    }
+    let mut marked = Vec::with_capacity(spans.len() + 1);
+    let mut offset = 0;
+    for span in spans {
+        let end = offset + span.content.len();
+        if end <= start {
+            offset = end;
+            marked.push(span);
+            continue;
+        }
+        let split = start.saturating_sub(offset);
+        offset = end;
+        let (kept, trailing) = span.content.split_at(split);
+        if !kept.is_empty() {
+            marked.push(Span::styled(kept.to_string(), span.style));
+        }
+        let trailing = trailing.replace(' ', "·");
+        marked.push(Span::styled(trailing, span.style.dim()));
+    }
+    marked
+}
+
+/// How a form feed, the page break of text meant for printers, is shown.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum FormFeed {
+    /// As a dim rule across the page
+    #[default]
+    Rule,
+    /// In caret notation, ^L
+    Caret,
+    /// As a blank
+    Blank,
+}
+
+/// How a vertical tab is shown.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum VerticalTab {
+    /// In caret notation, ^K
+    #[default]
+    Caret,
+    /// As a blank
+    Blank,
+}
+
+/// How the other control characters are shown: those that aren't tabs, line
+/// or page breaks, or part of the escape sequences styling is read from.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ControlChars {
+    /// In caret notation, ^@ for NUL
+    #[default]
+    Caret,
+    /// As the character's picture from Unicode's Control Pictures, ␀ for NUL
+    Picture,
+    /// As the character's code in hex between angle brackets, <00> for NUL,
+    /// the way less shows bytes it can't print
+    Hex,
+}
+
+/// `^` and the character 64 places on, or `^?` for DEL, if `c` is an ASCII
+/// control character.
+pub fn caret_notation(c: char) -> Option<String> {
+    c.is_ascii_control()
+        .then(|| format!("^{}", char::from(c as u8 ^ 0x40)))
+}
+
+/// The picture standing for an ASCII control character: `U+2400` on for
+/// NUL to US, and `U+2421` for DEL.
+pub fn control_picture(c: char) -> Option<char> {
+    match c {
+        '\0'..='\x1f' => char::from_u32(0x2400 + c as u32),
+        '\x7f' => Some('\u{2421}'),
+        _ => None,
+    }
+}
+
+/// Replaces the control characters left once tabs and page controls have
+/// been seen to, which would otherwise go to the terminal as they are, as
+/// `control_chars` says.
+pub fn expand_control_chars(
+    spans: Vec<Span<'static>>,
+    control_chars: ControlChars,
+) -> Vec<Span<'static>> {
+    let replaced = |c: char| c.is_ascii_control() && c != '\t';
+    if !spans.iter().any(|span| span.content.contains(replaced)) {
+        return spans;
+    }
+    let mut expanded = Vec::with_capacity(spans.len());
+    for span in spans {
+        let mut text = String::new();
+        for c in span.content.chars() {
+            if !replaced(c) {
+                text.push(c);
+                continue;
+            }
+            if !text.is_empty() {
+                expanded.push(Span::styled(std::mem::take(&mut text), span.style));
+            }
+            let picture = match control_chars {
+                ControlChars::Caret => caret_notation(c),
+                ControlChars::Picture => control_picture(c).map(String::from),
+                ControlChars::Hex => Some(format!("<{:02X}>", u32::from(c))),
+            };
+            expanded.push(Span::styled(picture.unwrap_or_default(), span.style.dim()));
+        }
+        if !text.is_empty() {
+            expanded.push(Span::styled(text, span.style));
+        }
+    }
+    expanded
+}
+
+/// Columns taken by the rule a form feed is shown as, about a printed page.
+pub const PAGE_BREAK_WIDTH: usize = 72;
+
+/// Replaces form feeds and vertical tabs, which terminals have no good way
+/// of showing in a line, as `form_feed` and `vertical_tab` say. A newline,
+/// which only ends up inside a line when lines are split at NULs, is shown
+/// as `↵`.
+pub fn expand_page_controls(
+    spans: Vec<Span<'static>>,
+    form_feed: FormFeed,
+    vertical_tab: VerticalTab,
+) -> Vec<Span<'static>> {
+    if !spans
+        .iter()
+        .any(|span| span.content.contains(['\n', '\x0b', '\x0c']))
+    {
+        return spans;
+    }
+    let mut expanded = Vec::with_capacity(spans.len());
+    for span in spans {
+        let mut text = String::new();
+        for c in span.content.chars() {
+            let shown = match (c, form_feed, vertical_tab) {
+                ('\x0c', FormFeed::Rule, _) => "─".repeat(PAGE_BREAK_WIDTH),
+                ('\x0c', FormFeed::Caret, _) => "^L".to_string(),
+                ('\x0b', _, VerticalTab::Caret) => "^K".to_string(),
+                ('\n', ..) => "↵".to_string(),
+                ('\x0c', FormFeed::Blank, _) | ('\x0b', _, VerticalTab::Blank) => {
+                    text.push(' ');
+                    continue;
+                }
+                _ => {
+                    text.push(c);
+                    continue;
+                }
+            };
+            if !text.is_empty() {
+                expanded.push(Span::styled(std::mem::take(&mut text), span.style));
+            }
+            expanded.push(Span::styled(shown, span.style.dim()));
+        }
+        if !text.is_empty() {
+            expanded.push(Span::styled(text, span.style));
+        }
+    }
+    expanded
+}
+
+/// Cuts the first `start` columns off `line` and whatever is left past
+/// `width` columns, marking the cut at the end with an ellipsis. A wide
+/// character split at `start` is replaced by blanks so columns stay aligned.
+pub fn slice_columns(line: Line<'_>, start: usize, width: Option<usize>) -> Line<'static> {
+    slice_columns_marked(line, start, width, None)
+}
+
+/// Like [`slice_columns`], marking a cut at the end with `mark` instead of an
+/// ellipsis in the style of what was cut.
+fn slice_columns_marked(
+    line: Line<'_>,
+    start: usize,
+    width: Option<usize>,
+    mark: Option<Span<'static>>,
+) -> Line<'static> {
+    let width = width.unwrap_or(usize::MAX);
+    let mut spans: Vec<Span<'static>> = Vec::new();
+    let mut column = 0;
+    let mut end = None;
+    // zero width characters go wherever the character before them went
+    let mut skipping = false;
+    'spans: for span in line.spans {
+        let mut text = String::new();
+        for c in span.content.chars() {
+            let w = c.width().unwrap_or(0);
+            if w == 0 {
+                if !skipping {
+                    text.push(c);
+                }
+                continue;
+            }
+            let from = column;
+            column += w;
+            skipping = column <= start;
+            if skipping {
+                continue;
+            }
+            if column - start > width {
+                end = Some(span.style);
+                if !text.is_empty() {
+                    spans.push(Span::styled(text, span.style));
+                }
+                break 'spans;
+            }
+            if from < start {
+                text.extend(std::iter::repeat_n(' ', column - start));
+            } else {
+                text.push(c);
+            }
+        }
+        if !text.is_empty() {
+            spans.push(Span::styled(text, span.style));
+        }
+    }
+    if let Some(style) = end {
+        // make room for the ellipsis in the last column
+        let mut kept = spans.iter().map(|span| span.width()).sum::<usize>();
+        while kept >= width && !spans.is_empty() {
+            let last = spans.last_mut().unwrap();
+            let mut content = last.content.to_string();
+            let c = content.pop().unwrap();
+            kept -= c.width().unwrap_or(0);
+            if content.is_empty() {
+                spans.pop();
+            } else {
+                last.content = content.into();
+            }
+        }
+        if width > 0 {
+            spans.push(mark.unwrap_or_else(|| Span::styled("…", style)));
+        }
+    }
+    Line {
+        style: line.style,
+        alignment: line.alignment,
+        spans,
+    }
+}
+
+/// The characters `--chop-marks` marks lines cut off at the screen edges with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChopMarks {
+    pub left: char,
+    pub right: char,
+}
+
+impl FromStr for ChopMarks {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let marks: Vec<char> = s.chars().collect();
+        match marks[..] {
+            [left, right] if left.width() == Some(1) && right.width() == Some(1) => {
+                Ok(ChopMarks { left, right })
+            }
+            _ => Err(format!(
+                "expected two characters a column wide each, got `{s}`"
+            )),
+        }
+    }
+}
+
+/// Marks where `line`, cut to where the screen is scrolled sideways to, goes
+/// on past the edges of a screen `width` columns wide: the first column when
+/// `scrolled` past some of it, the last when it is wider than the screen. The
+/// marks are dim, and don't take on the style of what they cover, so they
+/// don't pass for part of a highlighted match.
+pub fn mark_cut_edges(
+    line: Line<'_>,
+    width: usize,
+    scrolled: bool,
+    marks: ChopMarks,
+) -> Line<'static> {
+    let mark = |c: char| Span::styled(c.to_string(), Style::new().dim());
+    let mut line = if width > 0 && line.width() > width {
+        slice_columns_marked(line, 0, Some(width), Some(mark(marks.right)))
+    } else {
+        slice_columns(line, 0, None)
+    };
+    if scrolled && width > 1 && line.width() > 0 {
+        let mut rest = slice_columns(line, 1, None);
+        rest.spans.insert(0, mark(marks.left));
+        line = rest;
+    }
+    line
+}
+
+/// Patches `style` onto the parts of `line` covered by `ranges`, which are
+/// sorted byte ranges into the text of the whole line.
+pub fn highlight_ranges(line: Line<'_>, ranges: &[Range<usize>], style: Style) -> Line<'static> {
+    let Line {
+        style: line_style,
+        alignment,
+        spans,
+    } = line;
+    let mut highlighted = Vec::with_capacity(spans.len());
+    let mut offset = 0;
+    for span in spans {
+        let content = &span.content;
+        let end = offset + content.len();
+        let mut pos = 0;
+        for range in ranges.iter().filter(|r| r.start < end && r.end > offset) {
+            let start = range.start.max(offset) - offset;
+            let stop = range.end.min(end) - offset;
+            if start > pos {
+                highlighted.push(Span::styled(content[pos..start].to_string(), span.style));
+            }
+            highlighted.push(Span::styled(
+                content[start..stop].to_string(),
+                span.style.patch(style),
+            ));
+            pos = stop;
+        }
+        if pos < content.len() {
+            highlighted.push(Span::styled(content[pos..].to_string(), span.style));
+        }
+        offset = end;
+    }
+    Line {
+        style: line_style,
+        alignment,
+        spans: highlighted,
+    }
+}
+
+/// `time` as `YYYY-MM-DD HH:MM:SS UTC`, or `-` for one before 1970.
+pub fn format_timestamp(time: SystemTime) -> String {
+    let Ok(since) = time.duration_since(UNIX_EPOCH) else {
+        return "-".to_string();
+    };
+    let secs = since.as_secs();
+    let (days, secs) = (secs / 86400, secs % 86400);
+    // the civil date of a day count, after Howard Hinnant's civil_from_days
+    let z = days + 719_468;
+    let (era, day_of_era) = (z / 146_097, z % 146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02} UTC",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+/// What a `%` specifier of a `--prompt` stands for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromptSpec {
+    /// `%f`, the name of the input.
+    File,
+    /// `%l`, the number of the top line.
+    Line,
+    /// `%L`, the number of lines in the input, as far as known.
+    Lines,
+    /// `%p`, how far into the input the bottom of the screen is, in percent.
+    Percent,
+    /// `%b`, the byte offset of the top line.
+    Byte,
+    /// `%B`, the size of the input in bytes, or `?` while unknown.
+    Size,
+}
+
+/// A `--prompt` status line: text with `%` specifiers to fill in, `%%` for a
+/// `%` itself. Unknown specifiers are refused when it is parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PromptFormat(Vec<PromptPart>);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PromptPart {
+    Text(String),
+    Spec(PromptSpec),
+}
+
+impl FromStr for PromptFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut chars = s.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                text.push(c);
+                continue;
+            }
+            let spec = match chars.next() {
+                Some('%') => {
+                    text.push('%');
+                    continue;
+                }
+                Some('f') => PromptSpec::File,
+                Some('l') => PromptSpec::Line,
+                Some('L') => PromptSpec::Lines,
+                Some('p') => PromptSpec::Percent,
+                Some('b') => PromptSpec::Byte,
+                Some('B') => PromptSpec::Size,
+                Some(other) => return Err(format!("unknown specifier `%{other}`")),
+                None => return Err("`%` at the end, use `%%` for a `%`".to_string()),
+            };
+            if !text.is_empty() {
+                parts.push(PromptPart::Text(std::mem::take(&mut text)));
+            }
+            parts.push(PromptPart::Spec(spec));
+        }
+        if !text.is_empty() {
+            parts.push(PromptPart::Text(text));
+        }
+        Ok(PromptFormat(parts))
+    }
+}
+
+impl PromptFormat {
+    /// The prompt with each specifier replaced by its `value`.
+    pub fn expand(&self, value: impl Fn(PromptSpec) -> String) -> String {
+        self.0
+            .iter()
+            .map(|part| match part {
+                PromptPart::Text(text) => text.clone(),
+                PromptPart::Spec(spec) => value(*spec),
+            })
+            .collect()
+    }
+}
+
 #[cfg(test)]
 mod test {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use clap::ValueEnum;
     use ratatui::{
-        style::{Style, Stylize},
-        text::Span,
+        style::{Color, Modifier, Style, Stylize},
+        text::{Line, Span},
     };
 
-    use crate::utils::parse_styled_spans;
+    use crate::utils::{
+        ChopMarks, ControlChars, FormFeed, PAGE_BREAK_WIDTH, PromptFormat, PromptSpec, VerticalTab,
+        WrapIndent, caret_notation, control_picture, count_lines, expand_control_chars,
+        expand_page_controls, expand_tabs, format_timestamp, highlight_ranges, looks_binary,
+        mark_cut_edges, mark_trailing_spaces, newline_offsets, newline_offsets_in_chunks,
+        parse_styled_spans, slice_columns, split_args, strip_styling, trim_line_ending, wrap_line,
+    };
 
     #[test]
     fn test_backspace_chars() {
@@ -147,4 +971,410 @@ mod test {
         assert_eq!(spans[3], Span::styled("X", Style::new().underlined()));
         assert_eq!(spans[4], Span::styled(" plain", Style::new()));
     }
+
+    #[test]
+    fn test_ansi_escapes() {
+        let spans =
+            parse_styled_spans(b"\x1b[1;31mred\x1b[0m \x1b[38;5;42mx\x1b[39m\x1b[K".to_vec());
+        assert_eq!(
+            spans,
+            [
+                Span::styled("red", Style::new().bold().red()),
+                Span::raw(" "),
+                Span::styled("x", Style::new().fg(Color::Indexed(42))),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_common_colors() {
+        let style = |sequence: &str| {
+            let line = format!("\x1b[{sequence}mx");
+            parse_styled_spans(line.into_bytes())[0].style
+        };
+        assert_eq!(style("32"), Style::new().green());
+        assert_eq!(style("91"), Style::new().fg(Color::LightRed));
+        assert_eq!(style("44"), Style::new().bg(Color::Blue));
+        assert_eq!(style("103"), Style::new().bg(Color::LightYellow));
+        assert_eq!(style("38;5;208"), Style::new().fg(Color::Indexed(208)));
+        assert_eq!(
+            style("38;2;10;20;30;48;2;1;2;3"),
+            Style::new()
+                .fg(Color::Rgb(10, 20, 30))
+                .bg(Color::Rgb(1, 2, 3))
+        );
+        // git's bold, dim and reset sequences
+        assert_eq!(style("1;33"), Style::new().bold().yellow());
+        assert_eq!(style("2;3;4"), Style::new().dim().italic().underlined());
+        let reset = style("1;31;22;39");
+        assert_eq!((reset.fg, reset.add_modifier), (None, Modifier::empty()));
+    }
+
+    #[test]
+    fn test_hyperlinks() {
+        // terminated by ST and by BEL
+        let data = b"see \x1b]8;;https://example.com\x1b\\the docs\x1b]8;;\x1b\\ or \x1b]8;id=1;file:///tmp\x07M\x08M\x1b]8;;\x07";
+        let spans = parse_styled_spans(data.to_vec());
+        assert_eq!(
+            spans,
+            [
+                Span::raw("see "),
+                Span::styled("the docs", Style::new().underlined()),
+                Span::raw(" or "),
+                Span::styled("M", Style::new().underlined().bold()),
+            ]
+        );
+        // an unterminated link doesn't leak its URL onto the screen
+        let spans = parse_styled_spans(b"a\x1b]8;;https://example.com".to_vec());
+        assert_eq!(spans, [Span::raw("a")]);
+    }
+
+    #[test]
+    fn test_strip_styling() {
+        assert_eq!(strip_styling("N\x08NA\x08AM\x08ME\x08E _\x08x"), "NAME x");
+        assert_eq!(strip_styling("\x1b[1;31mred\x1b[0m plain"), "red plain");
+        assert_eq!(strip_styling("tab\tkept"), "tab\tkept");
+    }
+
+    #[test]
+    fn test_count_lines() {
+        let count = |data: &[u8]| count_lines(&mut &data[..], b'\n').unwrap();
+        assert_eq!(count(b""), 0);
+        assert_eq!(count(b"\n"), 1);
+        assert_eq!(count(b"a\nb\n"), 2);
+        assert_eq!(count(b"a\nb"), 2);
+        assert_eq!(count_lines(&mut &b"a\nb\0c"[..], 0).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_newline_offsets() {
+        let bytes = b"ab\n\ncd\nefg\n\nh";
+        let expected = [2, 3, 6, 10, 11];
+        assert_eq!(newline_offsets(bytes, b'\n'), expected);
+        // chunks ending right on a newline, starting with one, and without any
+        for chunk in 1..bytes.len() {
+            assert_eq!(newline_offsets_in_chunks(bytes, b'\n', chunk), expected);
+        }
+        assert!(newline_offsets(b"", b'\n').is_empty());
+        assert_eq!(newline_offsets(b"a\nb\0c\0", 0), [3, 5]);
+    }
+
+    #[test]
+    fn test_trim_line_ending() {
+        let lines = ["lf\n", "crlf\r\n", "in\rside\n", "two\r\r\n", "last\r"];
+        let trimmed = lines.map(|line| trim_line_ending(line, b'\n', false));
+        assert_eq!(trimmed, ["lf", "crlf", "in\rside", "two\r", "last\r"]);
+        let raw = lines.map(|line| trim_line_ending(line, b'\n', true));
+        assert_eq!(raw, ["lf", "crlf\r", "in\rside", "two\r\r", "last\r"]);
+        assert_eq!(trim_line_ending("crlf\r\n\0", 0, false), "crlf\r\n");
+    }
+
+    #[test]
+    fn test_looks_binary() {
+        assert!(!looks_binary(b""));
+        assert!(!looks_binary(
+            b"plain\ttext\r\nN\x08NAME \x1b[1mbold\x1b[0m\n"
+        ));
+        assert!(looks_binary(b"ELF\x00\x01\x02text"));
+        assert!(looks_binary(b"\x01\x02\x03\x04\x05abc"));
+    }
+
+    #[test]
+    fn test_wrap_line() {
+        assert_eq!(
+            wrap_line(Line::raw(""), 4, WrapIndent::default()),
+            vec![Line::raw("")]
+        );
+        assert_eq!(
+            wrap_line(Line::raw("abcd"), 4, WrapIndent::default()),
+            vec![Line::raw("abcd")]
+        );
+
+        let line = Line::from(vec![
+            Span::raw("abc"),
+            Span::styled("defgh", Style::new().bold()),
+        ]);
+        assert_eq!(
+            wrap_line(line, 4, WrapIndent::default()),
+            vec![
+                Line::from(vec![
+                    Span::raw("abc"),
+                    Span::styled("d", Style::new().bold())
+                ]),
+                Line::from(Span::styled("efgh", Style::new().bold())),
+            ]
+        );
+
+        // a wide character never straddles two rows
+        assert_eq!(
+            wrap_line(Line::raw("a中文"), 4, WrapIndent::default()),
+            vec![Line::raw("a中"), Line::raw("文")]
+        );
+    }
+
+    #[test]
+    fn test_wrap_indent() {
+        assert_eq!(
+            wrap_line(Line::raw("  abcdefgh"), 6, WrapIndent::Keep),
+            vec![
+                Line::raw("  abcd"),
+                Line::from(vec![Span::raw("  "), Span::raw("efgh")]),
+            ]
+        );
+        assert_eq!(
+            wrap_line(Line::raw("abcdefgh"), 6, WrapIndent::Columns(3)),
+            vec![
+                Line::raw("abcdef"),
+                Line::from(vec![Span::raw("   "), Span::raw("gh")]),
+            ]
+        );
+        // deep indentation still leaves half the row for text
+        assert_eq!(
+            wrap_line(Line::raw("      abcd"), 4, WrapIndent::Keep).len(),
+            4
+        );
+        assert_eq!("keep".parse(), Ok(WrapIndent::Keep));
+        assert_eq!("2".parse(), Ok(WrapIndent::Columns(2)));
+        assert!("deep".parse::<WrapIndent>().is_err());
+    }
+
+    #[test]
+    fn test_expand_tabs() {
+        let spans = vec![
+            Span::raw("a\tb"),
+            Span::styled("123456\tc\t", Style::new().bold()),
+        ];
+        assert_eq!(
+            Line::from(expand_tabs(spans.clone(), 8, false)).to_string(),
+            format!("a{}b123456 c{}", " ".repeat(7), " ".repeat(7))
+        );
+        assert_eq!(
+            Line::from(expand_tabs(spans.clone(), 4, false)).to_string(),
+            format!("a{}b123456 c{}", " ".repeat(3), " ".repeat(3))
+        );
+        assert_eq!(
+            expand_tabs(spans, 8, true),
+            [
+                Span::raw("a"),
+                Span::styled("→      ", Style::new().dim()),
+                Span::raw("b"),
+                Span::styled("123456", Style::new().bold()),
+                Span::styled("→", Style::new().bold().dim()),
+                Span::styled("c", Style::new().bold()),
+                Span::styled("→      ", Style::new().bold().dim()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mark_trailing_spaces() {
+        let spans = vec![Span::raw("a b "), Span::styled(" \t ", Style::new().red())];
+        assert_eq!(
+            mark_trailing_spaces(spans),
+            [
+                Span::raw("a b"),
+                Span::styled("·", Style::new().dim()),
+                Span::styled("·\t·", Style::new().red().dim()),
+            ]
+        );
+        let tab_only = vec![Span::raw("a\t")];
+        assert_eq!(mark_trailing_spaces(tab_only.clone()), tab_only);
+        assert_eq!(
+            Line::from(mark_trailing_spaces(vec![Span::raw("  ")])).to_string(),
+            "··"
+        );
+    }
+
+    #[test]
+    fn test_expand_page_controls() {
+        let spans = || {
+            vec![
+                Span::raw("a\x0cb"),
+                Span::styled("\x0bc", Style::new().bold()),
+            ]
+        };
+        let expand = |form_feed, vertical_tab| {
+            Line::from(expand_page_controls(spans(), form_feed, vertical_tab)).to_string()
+        };
+        assert_eq!(
+            expand(FormFeed::Rule, VerticalTab::Caret),
+            format!("a{}b^Kc", "─".repeat(PAGE_BREAK_WIDTH))
+        );
+        assert_eq!(expand(FormFeed::Caret, VerticalTab::Blank), "a^Lb c");
+        assert_eq!(expand(FormFeed::Blank, VerticalTab::Blank), "a b c");
+        assert_eq!(
+            expand_page_controls(spans(), FormFeed::Caret, VerticalTab::Caret),
+            [
+                Span::raw("a"),
+                Span::styled("^L", Style::new().dim()),
+                Span::raw("b"),
+                Span::styled("^K", Style::new().bold().dim()),
+                Span::styled("c", Style::new().bold()),
+            ]
+        );
+        let record = vec![Span::raw("a\nb")];
+        assert_eq!(
+            Line::from(expand_page_controls(
+                record,
+                FormFeed::Rule,
+                VerticalTab::Caret
+            ))
+            .to_string(),
+            "a↵b"
+        );
+        assert_eq!(FormFeed::from_str("caret", false), Ok(FormFeed::Caret));
+        assert!(VerticalTab::from_str("rule", false).is_err());
+    }
+
+    #[test]
+    fn test_control_chars() {
+        assert_eq!(control_picture('\0'), Some('␀'));
+        assert_eq!(control_picture('\t'), Some('␉'));
+        assert_eq!(control_picture('\x1b'), Some('␛'));
+        assert_eq!(control_picture('\x1f'), Some('␟'));
+        assert_eq!(control_picture('\x7f'), Some('␡'));
+        assert_eq!(control_picture('a'), None);
+        assert_eq!(caret_notation('\0').as_deref(), Some("^@"));
+        assert_eq!(caret_notation('\x07').as_deref(), Some("^G"));
+        assert_eq!(caret_notation('\x7f').as_deref(), Some("^?"));
+        assert_eq!(caret_notation('é'), None);
+
+        let spans = || {
+            vec![
+                Span::raw("a\0b\t"),
+                Span::styled("\x07", Style::new().bold()),
+            ]
+        };
+        assert_eq!(
+            expand_control_chars(spans(), ControlChars::Picture),
+            [
+                Span::raw("a"),
+                Span::styled("␀", Style::new().dim()),
+                Span::raw("b\t"),
+                Span::styled("␇", Style::new().bold().dim()),
+            ]
+        );
+        assert_eq!(
+            Line::from(expand_control_chars(spans(), ControlChars::Caret)).to_string(),
+            "a^@b\t^G"
+        );
+        assert_eq!(
+            Line::from(expand_control_chars(spans(), ControlChars::Hex)).to_string(),
+            "a<00>b\t<07>"
+        );
+        assert_eq!(
+            ControlChars::from_str("picture", false),
+            Ok(ControlChars::Picture)
+        );
+        assert_eq!(ControlChars::from_str("hex", false), Ok(ControlChars::Hex));
+        assert!(ControlChars::from_str("rule", false).is_err());
+    }
+
+    #[test]
+    fn test_slice_columns() {
+        let slice = |text, start, width| slice_columns(Line::raw(text), start, width).to_string();
+        assert_eq!(slice("abcdef", 0, None), "abcdef");
+        assert_eq!(slice("abcdef", 0, Some(6)), "abcdef");
+        assert_eq!(slice("abcdef", 0, Some(4)), "abc…");
+        assert_eq!(slice("abcdef", 2, Some(3)), "cd…");
+        assert_eq!(slice("abcdef", 8, Some(3)), "");
+
+        // wide characters are counted by their columns
+        assert_eq!(slice("中文字", 0, Some(4)), "中…");
+        assert_eq!(slice("中文字", 1, None), " 文字");
+
+        let line = Line::from(vec![
+            Span::raw("ab"),
+            Span::styled("cdef", Style::new().bold()),
+        ]);
+        assert_eq!(
+            slice_columns(line, 1, Some(3)),
+            Line::from(vec![
+                Span::raw("b"),
+                Span::styled("c", Style::new().bold()),
+                Span::styled("…", Style::new().bold()),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_mark_cut_edges() {
+        let marks: ChopMarks = "<>".parse().unwrap();
+        assert!("<".parse::<ChopMarks>().is_err());
+        assert!("<中".parse::<ChopMarks>().is_err());
+        let mark = |text: &str, scrolled| {
+            mark_cut_edges(Line::raw(text.to_string()), 4, scrolled, marks).to_string()
+        };
+        assert_eq!(mark("abcd", false), "abcd");
+        assert_eq!(mark("abcdef", false), "abc>");
+        assert_eq!(mark("abcdef", true), "<bc>");
+        assert_eq!(mark("ab", true), "<b");
+        assert_eq!(mark("", true), "");
+
+        // the mark doesn't look like part of the match it covers
+        let line = Line::from(vec![Span::raw("abc"), Span::raw("def").reversed()]);
+        let marked = mark_cut_edges(line, 4, false, marks);
+        assert_eq!(marked.spans.last().unwrap().style, Style::new().dim());
+    }
+
+    #[test]
+    fn test_split_args() {
+        assert!(split_args("  ").is_empty());
+        assert_eq!(split_args("-f  --color never"), ["-f", "--color", "never"]);
+        assert_eq!(
+            split_args("--jump-target=\"center\" 'a b' \"\""),
+            ["--jump-target=center", "a b", ""]
+        );
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        let at = |secs| format_timestamp(UNIX_EPOCH + Duration::from_secs(secs));
+        assert_eq!(at(0), "1970-01-01 00:00:00 UTC");
+        assert_eq!(at(951_782_400), "2000-02-29 00:00:00 UTC");
+        assert_eq!(at(1_791_979_199), "2026-10-14 11:59:59 UTC");
+        assert_eq!(format_timestamp(UNIX_EPOCH - Duration::from_secs(1)), "-");
+    }
+
+    #[test]
+    fn test_prompt_format() {
+        let format: PromptFormat = "%f line %l/%L (%p%%) %b of %B".parse().unwrap();
+        let expanded = format.expand(|spec| match spec {
+            PromptSpec::File => "log.txt".to_string(),
+            PromptSpec::Line => "3".to_string(),
+            PromptSpec::Lines => "40".to_string(),
+            PromptSpec::Percent => "25".to_string(),
+            PromptSpec::Byte => "120".to_string(),
+            PromptSpec::Size => "?".to_string(),
+        });
+        assert_eq!(expanded, "log.txt line 3/40 (25%) 120 of ?");
+        assert_eq!(
+            "no specifiers"
+                .parse::<PromptFormat>()
+                .unwrap()
+                .expand(|_| unreachable!()),
+            "no specifiers"
+        );
+        assert!("%x".parse::<PromptFormat>().is_err());
+        assert!("50%".parse::<PromptFormat>().is_err());
+    }
+
+    #[test]
+    fn test_highlight_ranges() {
+        let line = Line::from(vec![
+            Span::raw("foo "),
+            Span::styled("bar baz", Style::new().bold()),
+        ]);
+        let highlighted = highlight_ranges(line, &[2..5, 8..11], Style::new().reversed());
+        assert_eq!(
+            highlighted.spans,
+            [
+                Span::raw("fo"),
+                Span::styled("o ", Style::new().reversed()),
+                Span::styled("b", Style::new().bold().reversed()),
+                Span::styled("ar ", Style::new().bold()),
+                Span::styled("baz", Style::new().bold().reversed()),
+            ]
+        );
+    }
 }
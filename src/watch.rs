@@ -0,0 +1,210 @@
+//! Waiting for a followed file to change. On Linux inotify wakes the reader
+//! as soon as the file is written to, renamed or replaced; elsewhere, or when
+//! inotify can't be set up, the reader sleeps between looks instead.
+
+use std::{path::Path, time::Duration};
+
+/// Watches the directory of a followed file for changes to entries of the
+/// file's name, which catches the file being written to as well as a new file
+/// taking its place.
+pub struct FileWatch {
+    #[cfg(target_os = "linux")]
+    inner: inotify::Watch,
+}
+
+impl FileWatch {
+    /// Starts watching `path`, or returns `None` where that isn't possible and
+    /// the file has to be polled.
+    pub fn new(path: &Path) -> Option<Self> {
+        #[cfg(target_os = "linux")]
+        {
+            let inner = inotify::Watch::new(path)
+                .inspect_err(|err| log::debug!("polling {}: {err}", path.display()))
+                .ok()?;
+            Some(Self { inner })
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            let _ = path;
+            None
+        }
+    }
+
+    /// Waits until the file may have changed, or until `timeout` has passed.
+    pub fn wait(&mut self, timeout: Duration) {
+        #[cfg(target_os = "linux")]
+        self.inner.wait(timeout);
+        #[cfg(not(target_os = "linux"))]
+        std::thread::sleep(timeout);
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod inotify {
+    use std::{
+        ffi::{CString, OsString},
+        io, mem,
+        os::{
+            fd::{AsRawFd, FromRawFd, OwnedFd},
+            unix::ffi::OsStrExt,
+        },
+        path::Path,
+        time::{Duration, Instant},
+    };
+
+    /// What happens to a directory entry that may mean the followed file has
+    /// more to read, or is another file now.
+    const MASK: u32 = libc::IN_MODIFY
+        | libc::IN_ATTRIB
+        | libc::IN_CLOSE_WRITE
+        | libc::IN_CREATE
+        | libc::IN_DELETE
+        | libc::IN_MOVED_FROM
+        | libc::IN_MOVED_TO;
+
+    pub struct Watch {
+        fd: OwnedFd,
+        name: OsString,
+    }
+
+    impl Watch {
+        pub fn new(path: &Path) -> io::Result<Self> {
+            let name = path
+                .file_name()
+                .ok_or_else(|| io::Error::other("no file name"))?
+                .to_os_string();
+            let dir = match path.parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => dir,
+                _ => Path::new("."),
+            };
+            let dir = CString::new(dir.as_os_str().as_bytes())?;
+            // SAFETY: inotify_init1 takes no pointers
+            let fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            // SAFETY: the descriptor was just opened, and nothing else owns
+            // it; the watch goes away when it is closed
+            let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+            // SAFETY: `dir` is a NUL terminated string that outlives the call
+            let watch = unsafe { libc::inotify_add_watch(fd.as_raw_fd(), dir.as_ptr(), MASK) };
+            if watch < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(Self { fd, name })
+        }
+
+        /// Waits for an event about the followed file, leaving out those
+        /// about the other files in the directory.
+        pub fn wait(&mut self, timeout: Duration) {
+            let deadline = Instant::now() + timeout;
+            loop {
+                let left = deadline.saturating_duration_since(Instant::now());
+                if left.is_zero() {
+                    return;
+                }
+                // poll may time out a little before the deadline
+                if !self.poll(left) {
+                    continue;
+                }
+                match self.read_events() {
+                    Ok(true) => return,
+                    Ok(false) => {}
+                    // a broken watch is no reason to stop following
+                    Err(err) => {
+                        log::debug!("reading inotify events: {err}");
+                        std::thread::sleep(left);
+                        return;
+                    }
+                }
+            }
+        }
+
+        /// Waits up to `timeout` for events to read, returning whether there
+        /// are any.
+        fn poll(&self, timeout: Duration) -> bool {
+            let mut pollfd = libc::pollfd {
+                fd: self.fd.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            let millis = timeout.as_millis().clamp(1, i32::MAX as u128) as i32;
+            // SAFETY: `pollfd` is one valid element for the duration of the call
+            let ready = unsafe { libc::poll(&mut pollfd, 1, millis) };
+            ready > 0
+        }
+
+        /// Reads the events waiting, returning whether any is about the
+        /// followed file or the directory itself.
+        fn read_events(&mut self) -> io::Result<bool> {
+            let header = mem::size_of::<libc::inotify_event>();
+            let mut buf = vec![0u8; 4096];
+            let mut relevant = false;
+            loop {
+                // SAFETY: `buf` is valid for writes of its whole length
+                let size =
+                    unsafe { libc::read(self.fd.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len()) };
+                if size < 0 {
+                    let err = io::Error::last_os_error();
+                    return match err.kind() {
+                        io::ErrorKind::WouldBlock => Ok(relevant),
+                        io::ErrorKind::Interrupted => continue,
+                        _ => Err(err),
+                    };
+                }
+                let events = &buf[..size as usize];
+                let mut offset = 0;
+                while offset + header <= events.len() {
+                    // SAFETY: a whole header is in bounds, and it is read
+                    // without assuming alignment
+                    let event: libc::inotify_event =
+                        unsafe { std::ptr::read_unaligned(events[offset..].as_ptr().cast()) };
+                    let start = offset + header;
+                    let end = (start + event.len as usize).min(events.len());
+                    let name = events[start..end].split(|&b| b == 0).next().unwrap_or(&[]);
+                    // events without a name are about the directory, which
+                    // may be gone along with the file
+                    relevant |= name.is_empty() || name == self.name.as_bytes();
+                    offset = end;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod test {
+    use std::{
+        fs, thread,
+        time::{Duration, Instant},
+    };
+
+    use crate::{temp_path::TempPath, watch::FileWatch};
+
+    #[test]
+    fn test_file_watch() {
+        let dir = TempPath::new("watch");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("followed");
+        fs::write(&path, "a\n").unwrap();
+        let mut watch = FileWatch::new(&path).unwrap();
+
+        // other files in the directory don't wake it
+        let start = Instant::now();
+        fs::write(dir.join("other"), "b\n").unwrap();
+        watch.wait(Duration::from_millis(200));
+        assert!(start.elapsed() >= Duration::from_millis(200));
+
+        let writer = {
+            let path = path.clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(50));
+                fs::write(path, "a\nb\n").unwrap();
+            })
+        };
+        let start = Instant::now();
+        watch.wait(Duration::from_secs(10));
+        assert!(start.elapsed() < Duration::from_secs(5));
+        writer.join().unwrap();
+    }
+}